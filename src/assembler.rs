@@ -0,0 +1,193 @@
+/// This file contains the hack assembler, which turns assembly code into the binary rom image, as in nand2tetris project 6
+use std::collections::HashMap;
+
+use crate::error::{CompileError, Span};
+use crate::tokenizer::MAX_INT_CONST;
+
+/// The RAM address the first variable of a program is given
+pub const FIRST_VARIABLE_ADDRESS: u16 = 16;
+
+/// The symbols every program knows without defining them
+pub static PREDEFINED_SYMBOLS: [(&str, u16); 23] = [
+    ("SP", 0), ("LCL", 1), ("ARG", 2), ("THIS", 3), ("THAT", 4),
+    ("R0", 0), ("R1", 1), ("R2", 2), ("R3", 3), ("R4", 4), ("R5", 5), ("R6", 6), ("R7", 7),
+    ("R8", 8), ("R9", 9), ("R10", 10), ("R11", 11), ("R12", 12), ("R13", 13), ("R14", 14), ("R15", 15),
+    ("SCREEN", 16384), ("KBD", 24576),
+];
+
+/// The a-bit and the six c-bits of every computation, with the commuted forms of the symmetric ones
+pub static COMPUTATIONS: [(&str, u16); 40] = [
+    ("0", 0b0101010), ("1", 0b0111111), ("-1", 0b0111010), ("D", 0b0001100),
+    ("A", 0b0110000), ("!D", 0b0001101), ("!A", 0b0110001), ("-D", 0b0001111),
+    ("-A", 0b0110011), ("D+1", 0b0011111), ("A+1", 0b0110111), ("D-1", 0b0001110),
+    ("A-1", 0b0110010), ("D+A", 0b0000010), ("D-A", 0b0010011), ("A-D", 0b0000111),
+    ("D&A", 0b0000000), ("D|A", 0b0010101),
+    ("M", 0b1110000), ("!M", 0b1110001), ("-M", 0b1110011), ("M+1", 0b1110111),
+    ("M-1", 0b1110010), ("D+M", 0b1000010), ("D-M", 0b1010011), ("M-D", 0b1000111),
+    ("D&M", 0b1000000), ("D|M", 0b1010101),
+    ("A+D", 0b0000010), ("A&D", 0b0000000), ("A|D", 0b0010101),
+    ("M+D", 0b1000010), ("M&D", 0b1000000), ("M|D", 0b1010101),
+    ("1+D", 0b0011111), ("1+A", 0b0110111), ("1+M", 0b1110111),
+    ("-1+D", 0b0001110), ("-1+A", 0b0110010), ("-1+M", 0b1110010),
+];
+
+/// The three j-bits of every jump
+pub static JUMPS: [(&str, u16); 7] = [
+    ("JGT", 0b001), ("JEQ", 0b010), ("JGE", 0b011), ("JLT", 0b100), ("JNE", 0b101), ("JLE", 0b110), ("JMP", 0b111),
+];
+
+/// A line of assembly code that is not a comment or blank
+enum Line<'a> {
+    Label(&'a str),
+    Address(&'a str),
+    Compute { dest: &'a str, comp: &'a str, jump: &'a str },
+}
+
+/// Assembles hack assembly code into machine instructions
+///
+/// # Arguments
+///
+/// * `source` - The contents of an asm file
+///
+/// # Returns
+///
+/// * The 16-bit instructions of the program, or the first error found
+pub fn assemble(source: &str) -> Result<Vec<u16>, CompileError> {
+    let mut lines = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let code = line.split("//").next().unwrap_or("");
+        let column = code.len() - code.trim_start().len() + 1;
+        let code: String = code.chars().filter(|ch| !ch.is_whitespace()).collect();
+        if code.is_empty() {
+            continue;
+        }
+        lines.push((code, Span::new(line_index + 1, column, line.trim().chars().count())));
+    }
+
+    // The first pass gives every label the address of the instruction after it
+    let mut symbols: HashMap<String, u16> = PREDEFINED_SYMBOLS.iter().map(|(name, address)| (name.to_string(), *address)).collect();
+    let mut address: usize = 0;
+    for (code, span) in lines.iter() {
+        if let Line::Label(label) = split_line(code, *span)? {
+            check_symbol(label, *span)?;
+            if symbols.insert(label.to_string(), address as u16).is_some() {
+                return Err(CompileError::new(*span, format!("the symbol '{}' is defined more than once", label)));
+            }
+        } else {
+            address += 1;
+            if address > MAX_INT_CONST as usize + 1 {
+                return Err(CompileError::new(*span, "the program doesn't fit in the 32K rom".to_string()));
+            }
+        }
+    }
+
+    // The second pass translates the instructions, giving every new symbol the next free RAM address
+    let mut instructions = Vec::new();
+    let mut next_variable = FIRST_VARIABLE_ADDRESS;
+    for (code, span) in lines.iter() {
+        match split_line(code, *span)? {
+            Line::Label(_) => {}
+            Line::Address(value) => {
+                if value.starts_with(|ch: char| ch.is_ascii_digit()) {
+                    match value.parse::<u16>() {
+                        Ok(number) if number <= MAX_INT_CONST => { instructions.push(number); }
+                        _ => { return Err(CompileError::new(*span, format!("expected an address from 0 to {}, found '{}'", MAX_INT_CONST, value))); }
+                    }
+                } else {
+                    check_symbol(value, *span)?;
+                    let address = *symbols.entry(value.to_string()).or_insert_with(|| {
+                        next_variable += 1;
+                        next_variable - 1
+                    });
+                    instructions.push(address);
+                }
+            }
+            Line::Compute { dest, comp, jump } => {
+                let Some(&(_, comp_bits)) = COMPUTATIONS.iter().find(|(name, _)| *name == comp) else {
+                    return Err(CompileError::new(*span, format!("unknown computation '{}'", comp)));
+                };
+                let mut dest_bits: u16 = 0;
+                for register in dest.chars() {
+                    let bit = match register {
+                        'A' => { 0b100 }
+                        'D' => { 0b010 }
+                        'M' => { 0b001 }
+                        _ => { return Err(CompileError::new(*span, format!("unknown destination '{}'", dest))); }
+                    };
+                    dest_bits |= bit;
+                }
+                let jump_bits = if jump.is_empty() {
+                    0
+                } else {
+                    match JUMPS.iter().find(|(name, _)| *name == jump) {
+                        Some(&(_, bits)) => { bits }
+                        None => { return Err(CompileError::new(*span, format!("unknown jump '{}'", jump))); }
+                    }
+                };
+                instructions.push(0b1110_0000_0000_0000 | comp_bits << 6 | dest_bits << 3 | jump_bits);
+            }
+        }
+    }
+    Ok(instructions)
+}
+
+/// Writes machine instructions as the text of a .hack file, one instruction of 16 binary digits per line
+pub fn to_hack_text(instructions: &[u16]) -> String {
+    instructions.iter().map(|instruction| format!("{:016b}\n", instruction)).collect()
+}
+
+/// Reads the text of a .hack file back into machine instructions
+///
+/// # Arguments
+///
+/// * `source` - The contents of a hack file, an instruction of 16 binary digits per line
+///
+/// # Returns
+///
+/// * The 16-bit instructions of the program, or the first bad line
+pub fn from_hack_text(source: &str) -> Result<Vec<u16>, CompileError> {
+    let mut instructions = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let code = line.trim();
+        if code.is_empty() {
+            continue;
+        }
+        let span = Span::new(line_index + 1, line.len() - line.trim_start().len() + 1, code.chars().count());
+        if code.len() != 16 || !code.chars().all(|ch| ch == '0' || ch == '1') {
+            return Err(CompileError::new(span, format!("expected an instruction of 16 binary digits, found '{}'", code)));
+        }
+        instructions.push(u16::from_str_radix(code, 2).unwrap_or_default());
+    }
+    if instructions.len() > MAX_INT_CONST as usize + 1 {
+        return Err(CompileError::new(Span::default(), "the program doesn't fit in the 32K rom".to_string()));
+    }
+    Ok(instructions)
+}
+
+/// Finds out what kind of line the code is, and splits it into its parts
+fn split_line(code: &str, span: Span) -> Result<Line<'_>, CompileError> {
+    if let Some(label) = code.strip_prefix('(') {
+        return match label.strip_suffix(')') {
+            Some(label) => { Ok(Line::Label(label)) }
+            None => { Err(CompileError::new(span, "expected ')' at the end of the label".to_string())) }
+        };
+    }
+    if let Some(value) = code.strip_prefix('@') {
+        return Ok(Line::Address(value));
+    }
+    let (dest, rest) = code.split_once('=').unwrap_or(("", code));
+    let (comp, jump) = rest.split_once(';').unwrap_or((rest, ""));
+    Ok(Line::Compute { dest, comp, jump })
+}
+
+/// Makes sure a symbol is made of letters, digits, '_', '.', '$' and ':', and doesn't start with a digit
+fn check_symbol(symbol: &str, span: Span) -> Result<(), CompileError> {
+    let valid = !symbol.is_empty()
+        && !symbol.starts_with(|ch: char| ch.is_ascii_digit())
+        && symbol.chars().all(|ch| ch.is_ascii_alphanumeric() || "_.$:".contains(ch));
+    if valid {
+        Ok(())
+    } else {
+        Err(CompileError::new(span, format!("'{}' is not a valid symbol", symbol)))
+    }
+}
@@ -0,0 +1,283 @@
+/// This file contains the syntax tree of a jack class, as built by the parser.
+/// Every node keeps the span of the token it starts at, for error messages.
+use crate::error::Span;
+use crate::utility::{Kind, Precedence, op_precedence};
+
+/// A name written in the code, such as a variable, class or subroutine name
+#[derive(Clone, Debug, PartialEq)]
+pub struct Name {
+    pub name: String,
+    pub span: Span,
+}
+
+/// 'class' className '{' classVarDec* subroutineDec* '}'
+#[derive(Clone, Debug, PartialEq)]
+pub struct Class {
+    pub name: Name,
+    pub var_decs: Vec<ClassVarDec>,
+    pub subroutines: Vec<SubroutineDec>,
+    pub span: Span,
+}
+
+/// ('static' | 'field') type varName (',' varName)* ';'
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassVarDec {
+    pub kind: Kind,
+    pub data_type: Name,
+    pub names: Vec<Name>,
+    pub span: Span,
+}
+
+/// The keyword a subroutine is declared with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubroutineKind {
+    Constructor,
+    Function,
+    Method,
+}
+
+impl SubroutineKind {
+    /// The keyword of this kind of subroutine
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            SubroutineKind::Constructor => { "constructor" }
+            SubroutineKind::Function => { "function" }
+            SubroutineKind::Method => { "method" }
+        }
+    }
+}
+
+/// ('constructor' | 'function' | 'method') ('void' | type) subroutineName '(' parameterList ')' subroutineBody
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubroutineDec {
+    pub kind: SubroutineKind,
+    pub return_type: Name,
+    pub name: Name,
+    pub parameters: Vec<Parameter>,
+    pub var_decs: Vec<VarDec>,
+    pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+/// type varName, a single entry of a parameter list
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub data_type: Name,
+    pub name: Name,
+}
+
+/// 'var' type varName (',' varName)* ';'
+#[derive(Clone, Debug, PartialEq)]
+pub struct VarDec {
+    pub data_type: Name,
+    pub names: Vec<Name>,
+    pub span: Span,
+}
+
+/// A statement. The span of every statement is the span of its keyword.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    /// 'let' varName ('[' expression ']')? '=' expression ';'
+    Let { name: Name, index: Option<Expression>, value: Expression, span: Span },
+    /// 'if' '(' expression ')' '{' statements '}' ('else' '{' statements '}')?
+    If { condition: Expression, statements: Vec<Statement>, else_statements: Option<Vec<Statement>>, span: Span },
+    /// 'while' '(' expression ')' '{' statements '}'
+    While { condition: Expression, statements: Vec<Statement>, span: Span },
+    /// 'do' subroutineCall ';'
+    Do { call: SubroutineCall, span: Span },
+    /// 'return' expression? ';'
+    Return { value: Option<Expression>, span: Span },
+}
+
+impl Statement {
+    /// The span of the statement's keyword
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Let { span, .. } | Statement::If { span, .. } | Statement::While { span, .. }
+            | Statement::Do { span, .. } | Statement::Return { span, .. } => { *span }
+        }
+    }
+}
+
+/// term (op term)*
+/// The operators are kept in the order they are written, jack evaluates them from left to right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    pub term: Term,
+    pub rest: Vec<(Op, Term)>,
+}
+
+impl Expression {
+    /// The span of the first term of the expression
+    pub fn span(&self) -> Span {
+        self.term.span()
+    }
+
+    /// Orders the terms and operators of the expression as they are evaluated, every operator
+    /// following the two operands it is applied to
+    ///
+    /// # Arguments
+    ///
+    /// * `precedence` - Whether the operators are applied from left to right, or by their precedence levels
+    pub fn evaluation_order(&self, precedence: Precedence) -> Vec<Evaluation<'_>> {
+        let mut order = vec![Evaluation::Term(&self.term)];
+        // Every operator waits on the stack until an operator of the same or lower level follows it
+        let mut pending: Vec<(Op, Span)> = Vec::new();
+        for (op, term) in self.rest.iter() {
+            while let Some((previous, span)) = pending.last().copied() {
+                if precedence == Precedence::Standard && previous.precedence() < op.precedence() {
+                    break;
+                }
+                pending.pop();
+                order.push(Evaluation::Op(previous, span));
+            }
+            order.push(Evaluation::Term(term));
+            pending.push((*op, term.span()));
+        }
+        order.extend(pending.into_iter().rev().map(|(op, span)| Evaluation::Op(op, span)));
+        order
+    }
+}
+
+/// A step of evaluating an expression
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Evaluation<'a> {
+    /// Computes the value of a term
+    Term(&'a Term),
+    /// Applies an operator to the two values computed before it, with the span of its right operand
+    Op(Op, Span),
+}
+
+/// A binary operator
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    And,
+    Or,
+    Lt,
+    Gt,
+    Eq,
+}
+
+impl Op {
+    /// Finds the operator written as the given symbol
+    pub fn from_symbol(symbol: &str) -> Option<Op> {
+        match symbol {
+            "+" => { Some(Op::Add) }
+            "-" => { Some(Op::Sub) }
+            "*" => { Some(Op::Mul) }
+            "/" => { Some(Op::Div) }
+            "&" => { Some(Op::And) }
+            "|" => { Some(Op::Or) }
+            "<" => { Some(Op::Lt) }
+            ">" => { Some(Op::Gt) }
+            "=" => { Some(Op::Eq) }
+            _ => { None }
+        }
+    }
+
+    /// The symbol the operator is written as
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Op::Add => { "+" }
+            Op::Sub => { "-" }
+            Op::Mul => { "*" }
+            Op::Div => { "/" }
+            Op::And => { "&" }
+            Op::Or => { "|" }
+            Op::Lt => { "<" }
+            Op::Gt => { ">" }
+            Op::Eq => { "=" }
+        }
+    }
+
+    /// The conventional precedence level of the operator, higher levels are applied first
+    pub fn precedence(&self) -> usize {
+        op_precedence(self.symbol())
+    }
+}
+
+/// An unary operator
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    /// The symbol the operator is written as
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => { "-" }
+            UnaryOp::Not => { "~" }
+        }
+    }
+}
+
+/// 'true' | 'false' | 'null' | 'this'
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeywordConstant {
+    True,
+    False,
+    Null,
+    This,
+}
+
+impl KeywordConstant {
+    /// The keyword of the constant
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            KeywordConstant::True => { "true" }
+            KeywordConstant::False => { "false" }
+            KeywordConstant::Null => { "null" }
+            KeywordConstant::This => { "this" }
+        }
+    }
+}
+
+/// A term of an expression
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term {
+    IntegerConstant { value: u16, span: Span },
+    StringConstant { value: String, span: Span },
+    KeywordConstant { keyword: KeywordConstant, span: Span },
+    /// varName
+    Variable(Name),
+    /// varName '[' expression ']'
+    ArrayEntry { name: Name, index: Box<Expression> },
+    Call(SubroutineCall),
+    /// '(' expression ')'
+    Parenthesized { expression: Box<Expression>, span: Span },
+    /// unaryOp term
+    Unary { op: UnaryOp, term: Box<Term>, span: Span },
+}
+
+impl Term {
+    /// The span of the first token of the term
+    pub fn span(&self) -> Span {
+        match self {
+            Term::IntegerConstant { span, .. } | Term::StringConstant { span, .. } | Term::KeywordConstant { span, .. }
+            | Term::Parenthesized { span, .. } | Term::Unary { span, .. } => { *span }
+            Term::Variable(name) | Term::ArrayEntry { name, .. } => { name.span }
+            Term::Call(call) => { call.span() }
+        }
+    }
+}
+
+/// subroutineName '(' expressionList ')' | (className | varName) '.' subroutineName '(' expressionList ')'
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubroutineCall {
+    pub receiver: Option<Name>,
+    pub name: Name,
+    pub arguments: Vec<Expression>,
+}
+
+impl SubroutineCall {
+    /// The span of the first name of the call
+    pub fn span(&self) -> Span {
+        self.receiver.as_ref().unwrap_or(&self.name).span
+    }
+}
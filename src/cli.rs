@@ -0,0 +1,361 @@
+/// This file contains the command line parsing of jack2vm
+use jack2vm::utility::Precedence;
+
+pub static USAGE: &str = "Usage: jack2vm [OPTIONS] <PATH>...
+       jack2vm run [RUN OPTIONS] <PATH>
+       jack2vm test [TEST OPTIONS] <FILE.tst>...
+
+Compiles every given .jack file, and every .jack file inside every given directory, into .vm files.
+Run `jack2vm run --help` for running compiled programs, and `jack2vm test --help` for test scripts.
+
+Unlike the nand2tetris compiler, a subroutine whose end can be reached without a return statement
+is an error, void ones included, since its vm code would run on into the next function.
+
+Options:
+  -o, --out-dir <DIR>  Write the output files into DIR instead of next to the sources
+  -e, --emit <KINDS>   Comma separated list of the files to write for every class [default: vm]
+                         vm     - the compiled Xxx.vm file
+                         tokens - the tokens XxxT.xml file, as in nand2tetris project 10
+                         xml    - the parse tree Xxx.xml file, as in nand2tetris project 10
+                         asm    - the hack assembly of every directory (or single file) as Dir.asm,
+                                  including the .vm files next to the sources, such as the OS;
+                                  the program must define Sys.init and every function it calls
+                         hack   - the assembled Dir.hack rom image of the same program
+                         symbols - the symbol tables of the class and its subroutines, as
+                                  Xxx.symbols text and Xxx.symbols.json
+  -p, --precedence <MODE>
+                       How the operators of an expression are ordered [default: jack]
+                         jack     - strictly from left to right, and warn when parentheses would help
+                         standard - * / before + - before < > = before & |
+      --strict-types   Check that every value is used where its type fits, which jack doesn't;
+                       doubtful uses such as an int condition are warnings, others are errors
+      --deny-warnings  Fail to compile a class that has warnings, such as unused variables
+  -r, --recursive      Also search the subdirectories of the given directories
+  -q, --quiet          Print only errors
+  -v, --verbose        Print every file that is compiled and written
+  -h, --help           Print this message";
+
+pub static RUN_USAGE: &str = "Usage: jack2vm run [OPTIONS] <PATH>
+
+Runs the program made of every .vm file in the directory PATH (or the single .vm file PATH),
+and prints the final state of the RAM and the stack.
+A .hack or .asm file PATH runs in the CPU emulator instead, a step being a single instruction.
+
+Options:
+  -f, --function <NAME>  The function to start from [default: Sys.init]
+  -n, --max-steps <N>    Stop after running N vm commands or instructions [default: 10000000]
+      --ram <FROM>..<TO> Also print the RAM from address FROM up to, but not including, TO
+      --compare <FILE>   Compare the final RAM with the compare file FILE, whose columns are RAM[N]
+      --keys <FILE>      Press keys on the keyboard as the key script FILE says
+      --output <FILE>    Write everything printed by the Output class to FILE
+      --text-screen <FILE>
+                         Write the text the Output class left on the screen to FILE, row by row
+      --screen <FILE>    Write a snapshot of the screen memory map to FILE, as a PBM image
+  -h, --help             Print this message";
+
+pub static TEST_USAGE: &str = "Usage: jack2vm test [OPTIONS] <FILE.tst>...
+
+Runs nand2tetris test scripts. A script loads .vm files into the vm interpreter, or a .hack or .asm
+file into the CPU emulator, writes its output file, and compares every line with its compare file.
+
+Options:
+  -n, --max-steps <N>  Stop a script after running N vm commands or instructions [default: 10000000]
+  -h, --help           Print this message";
+
+/// The number of vm commands or instructions `run` runs if no --max-steps is given
+pub const DEFAULT_MAX_STEPS: u64 = 10_000_000;
+
+/// How much jack2vm prints while compiling
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// The kinds of files that can be written for every class
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Emit {
+    Vm,
+    Tokens,
+    Xml,
+    Asm,
+    Hack,
+    Symbols,
+}
+
+impl Emit {
+    /// Finds the kind of file by the name used on the command line
+    pub fn from_name(name: &str) -> Option<Emit> {
+        match name {
+            "vm" => { Some(Emit::Vm) }
+            "tokens" => { Some(Emit::Tokens) }
+            "xml" => { Some(Emit::Xml) }
+            "asm" => { Some(Emit::Asm) }
+            "hack" => { Some(Emit::Hack) }
+            "symbols" => { Some(Emit::Symbols) }
+            _ => { None }
+        }
+    }
+}
+
+/// The options given on the command line
+pub struct Options {
+    pub inputs: Vec<String>,
+    pub out_dir: Option<String>,
+    pub emit: Vec<Emit>,
+    pub precedence: Precedence,
+    pub strict_types: bool,
+    pub deny_warnings: bool,
+    pub recursive: bool,
+    pub verbosity: Verbosity,
+    pub help: bool,
+}
+
+impl Options {
+    /// Parses the command line arguments, without the program name
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments as given by `env::args().skip(1)`
+    ///
+    /// # Returns
+    ///
+    /// * The parsed options, or a message describing the bad argument
+    pub fn parse<I: Iterator<Item=String>>(mut args: I) -> Result<Self, String> {
+        let mut options = Options {
+            inputs: Vec::new(),
+            out_dir: None,
+            emit: Vec::new(),
+            precedence: Precedence::LeftToRight,
+            strict_types: false,
+            deny_warnings: false,
+            recursive: false,
+            verbosity: Verbosity::Normal,
+            help: false,
+        };
+
+        while let Some(arg) = args.next() {
+            if let Some(value) = arg.strip_prefix("--out-dir=") {
+                options.out_dir = Some(value.to_string());
+                continue;
+            }
+            if let Some(value) = arg.strip_prefix("--emit=") {
+                options.add_emit(value)?;
+                continue;
+            }
+            if let Some(value) = arg.strip_prefix("--precedence=") {
+                options.precedence = parse_precedence(value)?;
+                continue;
+            }
+            match arg.as_str() {
+                "-o" | "--out-dir" => {
+                    match args.next() {
+                        None => { return Err(format!("missing directory after '{}'", arg)); }
+                        Some(value) => { options.out_dir = Some(value); }
+                    }
+                }
+                "-e" | "--emit" => {
+                    match args.next() {
+                        None => { return Err(format!("missing file kinds after '{}'", arg)); }
+                        Some(value) => { options.add_emit(&value)?; }
+                    }
+                }
+                "-p" | "--precedence" => {
+                    match args.next() {
+                        None => { return Err(format!("missing precedence mode after '{}'", arg)); }
+                        Some(value) => { options.precedence = parse_precedence(&value)?; }
+                    }
+                }
+                "--strict-types" => { options.strict_types = true; }
+                "--deny-warnings" => { options.deny_warnings = true; }
+                "-r" | "--recursive" => { options.recursive = true; }
+                "-q" | "--quiet" => { options.verbosity = Verbosity::Quiet; }
+                "-v" | "--verbose" => { options.verbosity = Verbosity::Verbose; }
+                "-h" | "--help" => { options.help = true; }
+                _ => {
+                    if arg.starts_with('-') && arg.len() > 1 {
+                        return Err(format!("unknown option '{}'", arg));
+                    }
+                    options.inputs.push(arg);
+                }
+            }
+        }
+
+        if options.inputs.is_empty() && !options.help {
+            return Err("no input files or directories were given".to_string());
+        }
+        if options.emit.is_empty() {
+            options.emit.push(Emit::Vm);
+        }
+        Ok(options)
+    }
+
+    /// Adds the file kinds of a comma separated list to the kinds to emit
+    fn add_emit(&mut self, names: &str) -> Result<(), String> {
+        for name in names.split(',') {
+            match Emit::from_name(name.trim()) {
+                Some(emit) => {
+                    if !self.emit.contains(&emit) {
+                        self.emit.push(emit);
+                    }
+                }
+                None => { return Err(format!("unknown file kind '{}' for --emit", name)); }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the operator precedence by the name used on the command line
+fn parse_precedence(name: &str) -> Result<Precedence, String> {
+    match name {
+        "jack" => { Ok(Precedence::LeftToRight) }
+        "standard" => { Ok(Precedence::Standard) }
+        _ => { Err(format!("unknown precedence mode '{}', expected 'jack' or 'standard'", name)) }
+    }
+}
+
+/// The options given on the command line to the run command
+pub struct RunOptions {
+    pub input: String,
+    pub function: Option<String>,
+    pub max_steps: u64,
+    pub ram_ranges: Vec<(usize, usize)>,
+    pub compare_file: Option<String>,
+    pub key_script: Option<String>,
+    pub output_file: Option<String>,
+    pub text_screen_file: Option<String>,
+    pub screen_file: Option<String>,
+    pub help: bool,
+}
+
+impl RunOptions {
+    /// Parses the command line arguments that follow `run`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments after the word `run`
+    ///
+    /// # Returns
+    ///
+    /// * The parsed options, or a message describing the bad argument
+    pub fn parse<I: Iterator<Item=String>>(mut args: I) -> Result<Self, String> {
+        let mut options = RunOptions {
+            input: String::new(),
+            function: None,
+            max_steps: DEFAULT_MAX_STEPS,
+            ram_ranges: Vec::new(),
+            compare_file: None,
+            key_script: None,
+            output_file: None,
+            text_screen_file: None,
+            screen_file: None,
+            help: false,
+        };
+        let mut inputs: Vec<String> = Vec::new();
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => { (name.to_string(), Some(value.to_string())) }
+                _ => { (arg.to_string(), None) }
+            };
+            let mut value = |what: &str| {
+                inline_value.clone().or_else(|| args.next()).ok_or_else(|| format!("missing {} after '{}'", what, name))
+            };
+            match name.as_str() {
+                "-f" | "--function" => { options.function = Some(value("function name")?); }
+                "-n" | "--max-steps" => {
+                    let steps = value("number of steps")?;
+                    options.max_steps = steps.parse().map_err(|_| format!("'{}' is not a number of steps", steps))?;
+                }
+                "--ram" => {
+                    let range = value("RAM range")?;
+                    options.ram_ranges.push(parse_range(&range)?);
+                }
+                "--compare" => { options.compare_file = Some(value("file name")?); }
+                "--keys" => { options.key_script = Some(value("file name")?); }
+                "--output" => { options.output_file = Some(value("file name")?); }
+                "--text-screen" => { options.text_screen_file = Some(value("file name")?); }
+                "--screen" => { options.screen_file = Some(value("file name")?); }
+                "-h" | "--help" => { options.help = true; }
+                _ => {
+                    if arg.starts_with('-') && arg.len() > 1 {
+                        return Err(format!("unknown option '{}'", arg));
+                    }
+                    inputs.push(arg);
+                }
+            }
+        }
+
+        match inputs.len() {
+            0 if !options.help => { return Err("no program to run was given".to_string()); }
+            0 | 1 => { options.input = inputs.pop().unwrap_or_default(); }
+            _ => { return Err("only a single program can be run at a time".to_string()); }
+        }
+        Ok(options)
+    }
+}
+
+/// Parses a range of RAM addresses written as FROM..TO
+fn parse_range(range: &str) -> Result<(usize, usize), String> {
+    let bad_range = || format!("'{}' is not a RAM range such as 256..300", range);
+    let (from, to) = range.split_once("..").ok_or_else(bad_range)?;
+    let from: usize = from.parse().map_err(|_| bad_range())?;
+    let to: usize = to.parse().map_err(|_| bad_range())?;
+    if from > to || to > 32768 {
+        return Err(bad_range());
+    }
+    Ok((from, to))
+}
+
+/// The options given on the command line to the test command
+pub struct TestOptions {
+    pub scripts: Vec<String>,
+    pub max_steps: u64,
+    pub help: bool,
+}
+
+impl TestOptions {
+    /// Parses the command line arguments that follow `test`
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments after the word `test`
+    ///
+    /// # Returns
+    ///
+    /// * The parsed options, or a message describing the bad argument
+    pub fn parse<I: Iterator<Item=String>>(mut args: I) -> Result<Self, String> {
+        let mut options = TestOptions {
+            scripts: Vec::new(),
+            max_steps: DEFAULT_MAX_STEPS,
+            help: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => { (name.to_string(), Some(value.to_string())) }
+                _ => { (arg.to_string(), None) }
+            };
+            match name.as_str() {
+                "-n" | "--max-steps" => {
+                    let steps = inline_value.or_else(|| args.next()).ok_or_else(|| format!("missing number of steps after '{}'", name))?;
+                    options.max_steps = steps.parse().map_err(|_| format!("'{}' is not a number of steps", steps))?;
+                }
+                "-h" | "--help" => { options.help = true; }
+                _ => {
+                    if arg.starts_with('-') && arg.len() > 1 {
+                        return Err(format!("unknown option '{}'", arg));
+                    }
+                    options.scripts.push(arg);
+                }
+            }
+        }
+
+        if options.scripts.is_empty() && !options.help {
+            return Err("no test scripts were given".to_string());
+        }
+        Ok(options)
+    }
+}
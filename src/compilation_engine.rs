@@ -1,1253 +1,645 @@
-use std::fs;
-use lazy_static::lazy_static;
-use regex::Regex;
-
-use crate::symbol_table::SymbolTable;
-use crate::utility::{ADD, AND, BUILT_IN_CLASSES, CLASS_FUNC_TYPES, CLASS_VAR_TYPES, DATA_TYPES, EQ, GT, KEYWORD_CONSTANT, Kind, LT, NEG, NOT, OP, OR, SUB, UNARY_OP};
+use std::fs::File;
+use std::io::Write;
+
+use crate::ast::{Class, ClassVarDec, Evaluation, Expression, KeywordConstant, Name, Op, Parameter, Statement, SubroutineCall, SubroutineDec, SubroutineKind, Term, UnaryOp, VarDec};
+use crate::definite_assignment;
+use crate::error::{CompileError, Warning};
+use crate::signatures::Signatures;
+use crate::symbol_table::{DefineError, ScopeDump, SymbolTable};
+use crate::type_checker::TypeChecker;
+use crate::utility::{ADD, AND, DATA_TYPES, EQ, GT, Kind, LT, NEG, NOT, OR, Precedence, SUB};
 use crate::vm_writer::VMWriter;
-use crate::xmlwriter::XmlWriter;
 
-lazy_static! {
-    static ref IF_LABEL_INDEX:usize = 0;
-    static ref WHILE_LABEL_INDEX:usize = 0;
+/// What a subroutine is called on
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Receiver {
+    /// `object.subroutine()`, where object is a variable
+    Object,
+    /// `Class.subroutine()`
+    Class,
+    /// `subroutine()`, a method of the current object
+    This,
+}
+
+/// Whether control never goes past a statement: it returns, or loops forever
+fn ends(statement: &Statement) -> bool {
+    match statement {
+        Statement::Return { .. } => { true }
+        Statement::If { statements, else_statements: Some(else_statements), .. } => { always_ends(statements) && always_ends(else_statements) }
+        // jack has no break, so only a return leaves `while (true)`
+        Statement::While { condition, .. } => {
+            condition.rest.is_empty() && matches!(condition.term, Term::KeywordConstant { keyword: KeywordConstant::True, .. })
+        }
+        _ => { false }
+    }
+}
+
+/// Whether control never goes past a sequence of statements
+pub(crate) fn always_ends(statements: &[Statement]) -> bool {
+    statements.iter().any(ends)
 }
 
-pub struct CompilationEngine {
+/// Generates the vm code of a class by walking its syntax tree
+pub struct CompilationEngine<W: Write = File> {
     class_name: String,
-    //xml_file: XmlWriter,
-    vm_writer: VMWriter,
-    input_file: String,
-    class_symbol_table: SymbolTable,
-    subroutine_symbol_table: SymbolTable,
+    /// The subroutines of every class the calls can go to
+    signatures: Signatures,
+    /// The kind of the subroutine being compiled, as functions have no object to use fields of
+    subroutine_kind: SubroutineKind,
+    /// The full name of the subroutine being compiled
+    subroutine_name: String,
+    /// The declared return type of the subroutine being compiled
+    return_type: String,
+    vm_writer: VMWriter<W>,
+    /// The statics and fields of the class, and the arguments and locals of the subroutine being compiled
+    symbol_table: SymbolTable,
+    /// The symbols of the class and of every compiled subroutine, for the symbol dump
+    symbol_dump: Vec<ScopeDump>,
     while_label_index: usize,
     if_label_index: usize,
+    precedence: Precedence,
+    /// Whether the types of the values are checked once the class is compiled
+    strict_types: bool,
+    warnings: Vec<Warning>,
 }
 
-impl CompilationEngine {
-    /// Compiles the entire directory
-    pub fn compile(&mut self) {
-        self.compile_class();
-    }
-
-    /// Opens a jack file and gets ready to tokenize it
+impl<W: Write> CompilationEngine<W> {
+    /// Gets ready to write vm code
     ///
     /// # Arguments
     ///
-    /// * `path` - A path to the jack file, including the file extension
+    /// * `vm_writer` - The writer of the vm file
     ///
     /// # Returns
     ///
     /// * The newly created CompilationEngine object
-    pub fn new(path: &String) -> Self {
-        let remove_comments: Regex = Regex::new(r#"//.*\n|/\*.*\*/"#).unwrap();
-        let file_contents = fs::read_to_string(path).unwrap().as_str().to_owned();
-
-        let code = remove_comments.replace_all(&file_contents, "\n").to_string();
-
+    pub fn new(vm_writer: VMWriter<W>) -> Self {
         CompilationEngine {
-            class_name: code.split_whitespace().nth(1).unwrap().to_string(),
-            //xml_file: XmlWriter::new(&(path.to_owned().split(".jack").collect::<Vec<_>>()[0].to_owned() + "My.jack")),
-            vm_writer: VMWriter::new(&(path.to_owned().split(".jack").collect::<Vec<_>>()[0].to_owned() + ".jack")),
-            input_file: code,
-            class_symbol_table: SymbolTable::new(),
-            subroutine_symbol_table: SymbolTable::new(),
+            class_name: String::new(),
+            signatures: Signatures::new(),
+            subroutine_kind: SubroutineKind::Function,
+            subroutine_name: String::new(),
+            return_type: String::new(),
+            vm_writer,
+            symbol_table: SymbolTable::new(),
+            symbol_dump: Vec::new(),
             while_label_index: 0,
             if_label_index: 0,
+            precedence: Precedence::LeftToRight,
+            strict_types: false,
+            warnings: Vec::new(),
         }
     }
 
-    /// Compiles a complete class.
-    fn compile_class(&mut self) {
-        //self.output_file.open_tag("class".to_string());
-        let code = self.input_file.to_string();
-
-        let lines = code.lines();
-        let count = lines.count();
-        let mut lines = code.lines();
-
-        self.class_symbol_table = SymbolTable::new();
-
-        for _index in 1..count {
-            let line = lines.nth(0).unwrap();
-            let trimmed_line = line.trim_start();
-            let first_word = trimmed_line.split(" ").nth(0).unwrap();
-            if first_word == "class" {
-                self.class_name = trimmed_line.split(" ").nth(1).unwrap().to_string();
-                //self.output_file.write("keyword".to_string(), first_word.to_string());//class keyword
-                //self.output_file.write("identifier".to_string(), trimmed_line.split(" ").nth(1).unwrap().to_string());//class name
-                //self.output_file.write("symbol".to_string(), "{".to_string());//opening bracket
-                break;
-            }
-        }
-        let mut class_contents = code.get(code.find("{").unwrap()..code.rfind("}").unwrap() + 1).unwrap().lines();
-        //to get all the content of the class, such as fields, statics, constructors, methods and functions
-
-        for _index in 1..class_contents.clone().count() {
-            let line = class_contents.nth(0).unwrap();
-            let mut words = line.split_whitespace();
-            let tmp = words.nth(0);
-            let mut first_word = "";
-            match tmp {
-                None => {}
-                Some(value) => { first_word = value; }
-            }
-            if CLASS_VAR_TYPES.contains(&first_word) {
-                self.compile_class_var_dec(line.to_string());
-            }
-            if CLASS_FUNC_TYPES.contains(&first_word) {
-                // This is the source of the problem
-                // collect the lines one by one the same way as in while and if statement
-                let mut subroutine_code = Vec::new();
-                let new_code_source = code.get(code.find(line).unwrap()..code.len()).unwrap().lines();
-                let mut open_count = 0;
-                let mut close_count = 0;
-                for new_line in new_code_source {
-                    open_count += new_line.matches("{").count();
-                    close_count += new_line.matches("}").count();
-                    subroutine_code.push(new_line);
-
-                    if open_count == close_count && open_count != 0 {
-                        break;
-                    } else if open_count < close_count {
-                        let tmp = "{";
-                        panic!("ERROR IN JACK CODE: Missing \'{}\'", tmp)
-                    }
-                }
-                self.compile_subroutine_dec(subroutine_code.join("\n"));
-            }
-        }
-
-
-        //self.output_file.write("symbol".to_string(), "}".to_string());//closing bracket
-        //self.output_file.close_tag("class".to_string());
+    /// Sets how the operators of an expression are ordered. The default is from left to right, as in jack.
+    pub fn set_precedence(&mut self, precedence: Precedence) {
+        self.precedence = precedence;
     }
 
-    /// Compiles a static variable declaration or field declaration.
-    fn compile_class_var_dec(&mut self, line: String) {
-        let mut words = line.trim().get(0..line.trim().len() - 1).unwrap().split_whitespace();
-
-        //self.output_file.open_tag("classVarDec".to_string());
-        //self.output_file.write("keyword".to_string(), words.nth(0).unwrap().to_string());
-
-        let kind = match words.nth(0).unwrap() {
-            "field" => Kind::FIELD,
-            "static" => Kind::STATIC,
-            &_ => { Kind::NONE }
-        };
-        let data_type = words.nth(0).unwrap();
-        /*if DATA_TYPES.contains(&data_type) {
-            self.output_file.write("keyword".to_string(), data_type.to_string());
-        } else {
-            self.output_file.write("identifier".to_string(), data_type.to_string());
-        }*/
-
-        let mut comma = line.find(",");
-
-        if let Some(_value) = comma {
-
-            // TO DO: handle more than one var name
-            // EXAMPLE: "field int i, sum;"
-            let mut var_name = words.next().unwrap();
+    /// Sets the subroutines of the other classes of the program, which calls are checked against.
+    /// Without them, only the calls to the OS and to the class itself are checked.
+    pub fn set_signatures(&mut self, signatures: Signatures) {
+        self.signatures = signatures;
+    }
 
-            while let Some(_value) = comma {
-                //self.output_file.write("identifier".to_string(), var_name.get(0..var_name.find(',').unwrap()).unwrap().to_string());
-                //self.output_file.write("symbol".to_string(), ",".to_string());
-                if var_name.find(",") == Some(0) {
-                    var_name = var_name.get(1..var_name.len()).unwrap();
-                } else if var_name.find(",") == Some(var_name.len() - 1) {
-                    var_name = var_name.get(0..var_name.len() - 1).unwrap();
-                }
+    /// Sets whether the types of the values are checked, as jack itself doesn't check them
+    pub fn set_strict_types(&mut self, strict_types: bool) {
+        self.strict_types = strict_types;
+    }
 
-                self.class_symbol_table.define(var_name.to_string(), data_type.to_string(), kind);
+    /// The warnings found while compiling
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
 
-                var_name = words.next().unwrap();
+    /// The symbols the symbol table assigned, the class scope first and then every subroutine's scope
+    pub fn symbol_dump(&self) -> &[ScopeDump] {
+        &self.symbol_dump
+    }
 
-                comma = var_name.find(",");
-            }
-            self.class_symbol_table.define(var_name.to_string(), data_type.to_string(), kind);
-            //self.output_file.write("identifier".to_string(), var_name.get(0..var_name.find(';').unwrap()).unwrap().to_string());
-            //self.output_file.write("symbol".to_string(), ";".to_string());
-        } else {
-            let var_name = words.nth(0).unwrap();
-            self.class_symbol_table.define(var_name.get(0..var_name.len()).unwrap().to_string(), data_type.to_string(), kind);
+    /// Returns the writer of the vm code, once compiling is done
+    pub fn into_vm_writer(self) -> VMWriter<W> {
+        self.vm_writer
+    }
 
-            //self.output_file.write("identifier".to_string(), var_name.get(0..var_name.len() - 1).unwrap().to_string());
-            //self.output_file.write("symbol".to_string(), ";".to_string());
+    /// Compiles a parsed class
+    pub fn compile(&mut self, class: &Class) -> Result<(), CompileError> {
+        self.compile_class(class)?;
+        if self.strict_types {
+            let mut type_checker = TypeChecker::new(&self.signatures, self.precedence);
+            let result = type_checker.check(class);
+            self.warnings.extend_from_slice(type_checker.warnings());
+            result?;
         }
+        Ok(())
+    }
 
-        let mut next: &str;
-
-        let attempt = line.split(",");
-        if attempt.size_hint().0 > 0 {
-            loop {
-                next = words.next().unwrap();
-                if next == ";" {
-                    //self.output_file.write("symbol".to_string(), next.to_string());
-                    break;
-                } else if next == "," {
-                    //self.output_file.write("symbol".to_string(), next.to_string());
-                } else {
-                    self.class_symbol_table.define(words.next().unwrap().to_string(), data_type.to_string(), kind);
-
-                    //self.output_file.write("identifier".to_string(), words.next().unwrap().to_string());
-                }
-            }
+    /// Compiles a complete class.
+    fn compile_class(&mut self, class: &Class) -> Result<(), CompileError> {
+        self.class_name = class.name.name.to_string();
+        self.symbol_table = SymbolTable::new();
+        self.symbol_dump = Vec::new();
+        self.signatures.add_class(class);
+
+        for var_dec in class.var_decs.iter() {
+            self.compile_class_var_dec(var_dec)?;
         }
+        self.symbol_dump.push(self.symbol_table.dump(&self.class_name, "class"));
+        for subroutine in class.subroutines.iter() {
+            self.compile_subroutine_dec(subroutine)?;
+        }
+        self.warn_unused();
+        Ok(())
+    }
 
-        //self.xml_file.close_tag("classVarDec".to_string());
+    /// Compiles a static variable declaration or field declaration.
+    fn compile_class_var_dec(&mut self, var_dec: &ClassVarDec) -> Result<(), CompileError> {
+        for name in var_dec.names.iter() {
+            self.define(name, &var_dec.data_type.name, var_dec.kind)?;
+        }
+        Ok(())
     }
 
     /// Compiles a complete method, function or constructor.
-    pub fn compile_subroutine_dec(&mut self, content: String) {
-        //self.xml_file.open_tag("subroutineDec".to_string());
-
-        self.subroutine_symbol_table.start_subroutine();
+    fn compile_subroutine_dec(&mut self, subroutine: &SubroutineDec) -> Result<(), CompileError> {
+        self.symbol_table.start_subroutine();
+        self.subroutine_kind = subroutine.kind;
+        self.subroutine_name = format!("{}.{}", self.class_name, subroutine.name.name);
+        self.return_type = subroutine.return_type.name.to_string();
         self.if_label_index = 0;
         self.while_label_index = 0;
 
-        let func_dec = content.get(0..content.find("{").unwrap()).unwrap();
-
-        let mut words = func_dec.split_whitespace();
-
-        let subroutine_type = words.nth(0).unwrap();
-        let data_type = words.nth(0).unwrap();
-        let subroutine_name = words.nth(0).unwrap().split("(").nth(0).unwrap();
+        let local_vars_count = subroutine.var_decs.iter().map(|var_dec| var_dec.names.len()).sum();
+        self.vm_writer.write_function(self.subroutine_name.to_string(), local_vars_count)?;
 
-        let mut local_vars_count = 0;
-
-        for line in content.clone().lines() {
-            if line.trim().starts_with("var") {
-                local_vars_count += line.matches(",").count() + 1;
+        match subroutine.kind {
+            SubroutineKind::Constructor => {
+                // allocate memory for new object
+                self.vm_writer.write_push(Kind::NONE, "".to_string(), self.symbol_table.var_count(Kind::FIELD))?;
+                self.vm_writer.write_call("Memory.alloc".to_string(), 1)?;
+                self.vm_writer.write_pop(Kind::NONE, "pointer".to_string(), 0)?;
+            }
+            SubroutineKind::Method => {
+                // the first argument is the current object
+                let class_name = self.class_name.to_string();
+                self.define(&Name { name: "this".to_string(), span: subroutine.span }, &class_name, Kind::ARG)?;
+                self.vm_writer.write_push(Kind::ARG, "".to_string(), 0)?;
+                self.vm_writer.write_pop(Kind::NONE, "pointer".to_string(), 0)?;
+            }
+            SubroutineKind::Function => {
+                // function - static method
             }
         }
+        self.compile_parameter_list(&subroutine.parameters)?;
 
-        self.vm_writer.write_function(format!("{}.{}", self.class_name, subroutine_name), local_vars_count);
-        //self.xml_file.write("keyword".to_string(), words.nth(0).unwrap().to_string());//subroutine type - constructor/method/function keyword
-
-        //subroutine return type
-        if DATA_TYPES.contains(&data_type) {
-            //self.xml_file.write("keyword".to_string(), data_type.to_string());
-        } else {
-            //self.xml_file.write("identifier".to_string(), data_type.to_string());
-        }
-        //self.xml_file.write("identifier".to_string(), words.nth(0).unwrap().split("(").nth(0).unwrap().to_string());//subroutine name
-        //self.xml_file.write("symbol".to_string(), "(".to_string());
-
-        let param_list = func_dec.get(content.find("(").unwrap() + 1..content.find(")").unwrap()).unwrap();
-        let func_body = content.get(content.find("{").unwrap()..content.rfind("}").unwrap() + 1).unwrap();
-
-        if subroutine_type == "void" {
-            // return nothing
-        } else if subroutine_type == "constructor" {
-            // allocate memory for new object
-
-            self.vm_writer.write_push(Kind::NONE, "".to_string(), self.class_symbol_table.var_count(Kind::FIELD));
-            self.vm_writer.write_call("Memory.alloc".to_string(), 1);
-            self.vm_writer.write_pop(Kind::NONE, "pointer".to_string(), 0);
-        } else if subroutine_type == "method" {
-            // the first argument is the current object
-
-            self.subroutine_symbol_table.define("this".to_string(), self.class_name.to_string(), Kind::ARG);
-            self.vm_writer.write_push(Kind::ARG, "".to_string(), 0);
-            self.vm_writer.write_pop(Kind::NONE, "pointer".to_string(), 0);
-        } else {
-            // function - static method
-        }
-        self.compile_parameter_list(param_list.to_string());
-
-
-        self.compile_subroutine_body(func_body.to_string());
-
-        //self.xml_file.write("symbol".to_string(), ")".to_string());
-        //self.xml_file.close_tag("subroutineDec".to_string());
+        self.compile_subroutine_body(subroutine)
     }
 
     /// Compiles a (possibly empty) parameter list.
-    /// Does not handle the enclosing "()".
-    fn compile_parameter_list(&mut self, content: String) {
-        //self.xml_file.open_tag("parameterList".to_string());
-        let mut var_split;
-        let mut first_time = true;
-        if content.len() > 0 {
-            let mut vars = content.split(",");
-
-            let count = content.find(",");
-
-            if count.is_some() {
-                let times = count.unwrap(); // one to get to the end of the collection
-                let mut var_split;
-                for _index in 1..times {
-                    let temp = vars.next();
-
-                    match temp {
-                        None => { break; }
-                        Some(value) => {
-                            if !first_time {
-                                //self.xml_file.write("symbol".to_string(), ",".to_string());
-                            } else { first_time = false; }
-
-                            var_split = value.split_whitespace();
-                        }
-                    }
-                    let data_type = var_split.next().unwrap();
-
-                    let var_name = var_split.next().unwrap();
-                    self.subroutine_symbol_table.define(var_name.to_string(), data_type.to_string(), Kind::ARG);
-
-                    //self.xml_file.write("keyword".to_string(), var_split.next().unwrap().to_string());
-                    //self.xml_file.write("identifier".to_string(), var_split.next().unwrap().to_string());
-                }
-            }
-            let temp = vars.next();
-
-            match temp {
-                None => {}
-                Some(value) => {
-                    var_split = value.split_whitespace();
-                    let data_type = var_split.next().unwrap();
-                    let var_name = var_split.next().unwrap();
-                    self.subroutine_symbol_table.define(var_name.to_string(), data_type.to_string(), Kind::ARG);
-
-                    //self.xml_file.write("keyword".to_string(), var_split.next().unwrap().to_string());
-                    //self.xml_file.write("identifier".to_string(), var_split.next().unwrap().to_string());
-                }
-            }
+    fn compile_parameter_list(&mut self, parameters: &[Parameter]) -> Result<(), CompileError> {
+        for parameter in parameters.iter() {
+            self.define(&parameter.name, &parameter.data_type.name, Kind::ARG)?;
         }
-        //self.xml_file.close_tag("parameterList".to_string());
+        Ok(())
     }
 
     /// Compiles a subroutine's body.
-    fn compile_subroutine_body(&mut self, content: String) {
-        //self.xml_file.open_tag("subroutineBody".to_string());
-        //self.xml_file.write("symbol".to_string(), "{".to_string());
-
-        let body_content = content.get(content.find("{").unwrap() + 1..content.rfind("}").unwrap()).unwrap();
-        let mut stop_sign = "";
-        let lines = body_content.lines();
-        for line in lines {
-            if line != "" {
-                if let Some(value) = line.trim().split_whitespace().nth(0) {
-                    if value == "var" {
-                        self.compile_var_dec(line.trim().to_string());
-                    } else {
-                        stop_sign = value;
-                        break;
-                    }
-                }
-            }
+    fn compile_subroutine_body(&mut self, subroutine: &SubroutineDec) -> Result<(), CompileError> {
+        for var_dec in subroutine.var_decs.iter() {
+            self.compile_var_dec(var_dec)?;
         }
-
-        self.compile_statements(body_content.to_string().get(body_content.find(stop_sign).unwrap()..body_content.len() - 1).unwrap().to_string());
-
-        //self.xml_file.write("symbol".to_string(), "}".to_string());
-
-        //self.xml_file.close_tag("subroutineBody".to_string());
+        self.compile_statements(&subroutine.statements)?;
+        self.warnings.extend(definite_assignment::check(subroutine));
+        self.warn_unused();
+        self.symbol_dump.push(self.symbol_table.dump(&self.subroutine_name, subroutine.kind.keyword()));
+        self.symbol_table.end_subroutine();
+
+        // the vm code would run into the next function
+        if !always_ends(&subroutine.statements) {
+            return Err(CompileError::new(subroutine.name.span, format!("{} can reach its end without a return statement", self.subroutine_name)));
+        }
+        Ok(())
     }
 
     /// Compiles a var declaration.
-    fn compile_var_dec(&mut self, content: String) {
-        //self.output_file.open_tag("varDec".to_string());
-        //self.output_file.write("keyword".to_string(), "var".to_string());
-
-        let mut words = content.split_whitespace();
-
-        let data_type = words.nth(1).unwrap();
-        if DATA_TYPES.contains(&data_type) {
-            //self.output_file.write("keyword".to_string(), data_type.to_string());
-        } else {
-            //self.output_file.write("identifier".to_string(), data_type.to_string());
+    fn compile_var_dec(&mut self, var_dec: &VarDec) -> Result<(), CompileError> {
+        for name in var_dec.names.iter() {
+            self.define(name, &var_dec.data_type.name, Kind::VAR)?;
         }
+        Ok(())
+    }
 
-        let mut comma = content.find(",");
-
-        if let Some(_value) = comma {
-
-            // TO DO: handle more than one var name
-            // EXAMPLE: "var int i, sum;"
-            let mut var_name = words.next().unwrap();
-
-            while let Some(_value) = comma {
-                //self.output_file.write("identifier".to_string(), var_name.get(0..var_name.find(',').unwrap()).unwrap().to_string());
-                //self.output_file.write("symbol".to_string(), ",".to_string());
-
-                self.subroutine_symbol_table.define(var_name.get(0..var_name.find(',').unwrap()).unwrap().to_string(), data_type.to_string(), Kind::VAR);
-                var_name = words.next().unwrap();
-
-                comma = var_name.find(",");
+    /// Defines a variable in the scope of its kind. Declaring a name twice in a scope is an error,
+    /// and an argument or local variable hiding a static or field is warned about.
+    fn define(&mut self, name: &Name, data_type: &str, kind: Kind) -> Result<(), CompileError> {
+        let scope = match kind {
+            Kind::STATIC | Kind::FIELD => { format!("the class {}", self.class_name) }
+            _ => { self.subroutine_name.to_string() }
+        };
+        // the class variable the new variable hides, if any
+        let hidden = self.symbol_table.resolve(&name.name)
+            .filter(|symbol| match symbol.get_kind() {
+                // a function can't use fields, so there is nothing to hide
+                Kind::FIELD => { self.subroutine_kind != SubroutineKind::Function }
+                Kind::STATIC => { true }
+                _ => { false }
+            })
+            .map(|symbol| (symbol.get_kind(), symbol.get_span()));
+
+        match self.symbol_table.define(name.name.to_string(), data_type.to_string(), kind, name.span) {
+            Ok(()) => {}
+            Err(DefineError::Redeclared(first)) => {
+                return Err(CompileError::new(name.span, format!("'{}' is already declared in {}", name.name, scope))
+                    .with_note(first, format!("'{}' is first declared here", name.name)));
+            }
+            Err(DefineError::NoKind) => {
+                return Err(CompileError::new(name.span, format!("'{}' is not a static, field, argument or local variable", name.name)));
             }
-            self.subroutine_symbol_table.define(var_name.get(0..var_name.len() - 1).unwrap().to_string(), data_type.to_string(), Kind::VAR);
-
-            //self.output_file.write("identifier".to_string(), var_name.get(0..var_name.find(';').unwrap()).unwrap().to_string());
-            //self.output_file.write("symbol".to_string(), ";".to_string());
-        } else {
-            let var_name = words.nth(0).unwrap();
-            self.subroutine_symbol_table.define(var_name.get(0..var_name.len() - 1).unwrap().to_string(), data_type.to_string(), Kind::VAR);
-
-            //self.output_file.write("identifier".to_string(), var_name.get(0..var_name.len() - 1).unwrap().to_string());
+        }
 
-            //self.output_file.write("symbol".to_string(), ";".to_string());
+        if let Some((hidden_kind, hidden)) = hidden.filter(|_| matches!(kind, Kind::ARG | Kind::VAR)) {
+            self.warnings.push(Warning::new(name.span, format!(
+                "the {} '{}' shadows the {} of the same name", kind.describe(), name.name, hidden_kind.describe()
+            )).with_note(hidden, format!("the {} is declared here", hidden_kind.describe())));
         }
+        Ok(())
+    }
 
-        //self.output_file.close_tag("varDec".to_string());
+    /// Warns about the variables of the innermost scope the code never uses: statics and fields that are never
+    /// used in the class, arguments never read, and local variables never read
+    fn warn_unused(&mut self) {
+        for symbol in self.symbol_table.symbols().iter() {
+            let message = match symbol.get_kind() {
+                Kind::STATIC | Kind::FIELD | Kind::ARG if symbol.is_read() || symbol.is_written() => { continue; }
+                // the object of a method is used by calling the other methods
+                Kind::ARG if symbol.get_name() == "this" => { continue; }
+                Kind::VAR if symbol.is_read() => { continue; }
+                Kind::VAR if symbol.is_written() => { format!("the local variable '{}' is assigned, but never read", symbol.get_name()) }
+                kind => { format!("the {} '{}' is never used", kind.describe(), symbol.get_name()) }
+            };
+            self.warnings.push(Warning::new(symbol.get_span(), message));
+        }
     }
 
     /// Compiles a sequence of statements.
-    /// Does not handle the enclosing "()".
-    fn compile_statements(&mut self, content: String) {
-        //self.xml_file.open_tag("statements".to_string());
-
-        let mut previous_statements = Vec::new();
-        let temp = content.clone();
-        let mut lines = temp.lines();
-        let mut first_word = "";
-        let mut tmp;
-        let mut advance = true;
-        let mut current_line = "";
-        if lines.clone().count() == 1 {
-            let line = content.to_string();
-
-            tmp = line.trim().split_whitespace().nth(0);
-            match tmp {
-                None => {}
-                Some(value) => { first_word = value; }
+    fn compile_statements(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+        if let Some(position) = statements.iter().position(ends) {
+            if let Some(unreachable) = statements.get(position + 1) {
+                self.warnings.push(Warning::new(unreachable.span(), "this statement is never reached".to_string()));
             }
-            if first_word == "let"
-            {
-                self.compile_let(line.trim().to_string());
-            } else if first_word == "do"
-            {
-                self.compile_do(line.trim().to_string());
-            } else if line.contains("return")
-            {
-                self.compile_return(line.trim().to_string());
-            }
-        } else {
-            for index in 0..lines.clone().count() {
-                if advance {
-                    tmp = lines.next();
-                    if tmp.is_some() {
-                        current_line = tmp.unwrap().trim();
-                    } else { current_line = ""; }
-                }
-                advance = true;
-                tmp = current_line.trim().split_whitespace().nth(0);
-                match tmp {
-                    None => {}
-                    Some(value) => { first_word = value; }
-                }
-                if first_word == "while"
-                {
-                    let mut start_statement = "";
-                    let mut lines_clone = temp.lines();
-                    for index_clone in 1..lines_clone.clone().count() {
-                        let line_clone = lines_clone.next().unwrap();
-                        if line_clone.contains("while") && previous_statements.is_empty() {
-                            start_statement = line_clone;
-                            previous_statements.push(index_clone);
-                            break;
-                        }
-                        if line_clone.contains("while") && !previous_statements.contains(&index_clone) && index_clone >= index {
-                            start_statement = line_clone;
-                            previous_statements.push(index_clone);
-                            break;
-                        }
-                    }
-
-                    let mut open_count = 0;
-                    let mut close_count = 0;
-
-                    let mut while_lines = Vec::new();
-                    while_lines.push(start_statement);
-                    for line in lines.clone() {
-                        while_lines.push(line);
-                    }
-
-                    let mut while_statement = "".to_string();
-                    for while_line in while_lines {
-                        if !while_line.is_empty() {
-                            current_line = lines.next().unwrap();
-                            while_statement.push_str(while_line);
-                            while_statement.push_str("\n");
-                            open_count += while_line.matches("{").count();
-                            close_count += while_line.matches("}").count();
-
-                            if open_count == close_count && open_count != 0 {
-                                advance = false;
-                                break;
-                            } else if open_count < close_count {
-                                panic!("ERROR IN THE JACK CODE!")
-                            }
-                        }
-                    }
-                    if while_statement.matches("{").count() == while_statement.matches("}").count() {
-                        while_statement.push_str("}");
-                    }
-                    if !while_statement.is_empty() {
-                        self.compile_while(while_statement.to_string());
-                    }
-                    for new_index in index..while_statement.lines().count() + index {
-                        previous_statements.push(new_index);
-                    }
-                }
-                if first_word == "if"
-                {
-                    let mut start_statement = current_line;
-                    let mut lines_clone = temp.lines();
-                    for index_clone in 1..lines_clone.clone().count() {
-                        let line_clone = lines_clone.next().unwrap();
-                        if line_clone.contains("if") && previous_statements.len() == 0 {
-                            start_statement = line_clone;
-                            previous_statements.push(index_clone);
-                            break;
-                        }
-                        if line_clone.contains("if") && !previous_statements.contains(&index_clone) && index >= index_clone {
-                            start_statement = line_clone;
-                            previous_statements.push(index_clone);
-                            break;
-                        }
-                    }
-
-                    let mut if_lines = Vec::new();
-                    if_lines.push(start_statement);
-                    if start_statement.trim() != current_line.trim() {
-                        if_lines.push(current_line);
-                    }
-                    for line in lines.clone() {
-                        if_lines.push(line);
-                    }
-
-                    let mut if_statement = "".to_string();
-
-                    let mut open_count = 0;
-                    let mut close_count = 0;
-                    let mut if_line = if_lines.get(0).unwrap();
-                    if if_lines.clone().len() == 2 {
-                        if_statement.push_str(start_statement);
-                    }
-                    for my_index in 1..if_lines.clone().len() {
-                        if !if_line.is_empty() {
-                            if_statement.push_str(if_line);
-                            if_statement.push_str("\n");
-                            open_count += if_line.matches("{").count();
-                            close_count += if_line.matches("}").count();
-                            if_line = if_lines.get(my_index).unwrap();
-
-                            current_line = lines.next().unwrap();
-
-                            if open_count == close_count && open_count != 0 && !if_line.contains("else") {
-                                advance = false;
-                                break;
-                            } else if open_count < close_count {
-                                panic!("ERROR IN THE JACK CODE!")
-                            }
-                        } else {
-                            if_line = if_lines.get(my_index).unwrap();
-                        }
-                    }
-                    if if_statement.matches("{").count() != if_statement.matches("}").count() && !if_statement.is_empty() {
-                        if_statement.push_str("}");
-                    }
-                    if !if_statement.is_empty() {
-                        self.compile_if(if_statement.to_string());
-                    }
-                    for new_index in 0..if_statement.lines().count() {
-                        previous_statements.push(1 + index + new_index);
-                    }
-                }
-                tmp = current_line.trim().split_whitespace().nth(0);
-                match tmp {
-                    None => {}
-                    Some(value) => { first_word = value; }
-                }
-                if first_word == "let"
-                {
-                    self.compile_let(current_line.trim().to_string());
-                    advance = true;
-                }
-                if first_word == "do"
-                {
-                    self.compile_do(current_line.trim().to_string());
-                    advance = true;
-                }
-
-                if current_line.contains("return")
-                {
-                    self.compile_return(current_line.trim().to_string());
-                    advance = true;
-                }
-                first_word = "";
+        }
+        for statement in statements.iter() {
+            match statement {
+                Statement::Let { .. } => { self.compile_let(statement)?; }
+                Statement::If { .. } => { self.compile_if(statement)?; }
+                Statement::While { .. } => { self.compile_while(statement)?; }
+                Statement::Do { .. } => { self.compile_do(statement)?; }
+                Statement::Return { .. } => { self.compile_return(statement)?; }
             }
         }
-
-        //self.xml_file.close_tag("statements".to_string());
+        Ok(())
     }
 
     /// Compiles a let statement.
-    fn compile_let(&mut self, content: String) {
-        //self.xml_file.open_tag("letStatement".to_string());
-
-        //self.xml_file.write("keyword".to_string(), "let".to_string());
-        let assign_to = content.get(content.find(" ").unwrap()..content.find("=").unwrap()).unwrap().trim();
+    fn compile_let(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        let Statement::Let { name, index: array_index, value, .. } = statement else { return Ok(()); };
+        // storing into an array entry reads the address of the array
+        let (kind, index) = self.variable(name, array_index.is_none())?;
 
-
-        if assign_to.contains("[") {
+        if let Some(array_index) = array_index {
             // Array entry
-            //self.xml_file.write("identifier".to_string(), assign_to.split("[").nth(0).unwrap().trim().to_string());
-            //self.xml_file.write("symbol".to_string(), "[".to_string());
-
-            let var_name = assign_to.get(0..assign_to.find("[").unwrap()).unwrap().trim();
-            let mut kind = Kind::NONE;
-            let mut index = usize::MAX;
-            (kind, index) = self.get_kind_index(var_name.to_string());
+            self.compile_expression(array_index)?;
+            self.vm_writer.write_push(kind, "".to_string(), index)?;// push arr
+            self.vm_writer.write_arithmetic(ADD)?;
 
-            let arr_exp = assign_to.get(assign_to.find("[").unwrap() + 1..assign_to.rfind("]").unwrap()).unwrap().trim();
-            self.compile_expression(arr_exp.to_string());
-            self.vm_writer.write_push(kind, "".to_string(), index);// push arr
-            self.vm_writer.write_arithmetic(ADD);
+            self.compile_expression(value)?;
 
-            self.compile_expression(content.get(content.find("=").unwrap() + 1..content.find(";").unwrap()).unwrap().trim().to_string());
-
-            // pop temp 1 --- temp 0 is used for void functions return value
-            self.vm_writer.write_pop(Kind::NONE, "temp".to_string(), 1);
+            // pop temp 0 --- the value is saved while the address is set, as the reference compiler does
+            self.vm_writer.write_pop(Kind::NONE, "temp".to_string(), 0)?;
             // pop pointer 1
-            self.vm_writer.write_pop(Kind::NONE, "pointer".to_string(), 1);
-            // push temp 1 --- temp 0 is used for void functions return value
-            self.vm_writer.write_push(Kind::NONE, "temp".to_string(), 1);
+            self.vm_writer.write_pop(Kind::NONE, "pointer".to_string(), 1)?;
+            // push temp 0
+            self.vm_writer.write_push(Kind::NONE, "temp".to_string(), 0)?;
             // pop that 0
-            self.vm_writer.write_pop(Kind::NONE, "that".to_string(), 0);
-            //self.xml_file.write("symbol".to_string(), "]".to_string());
+            self.vm_writer.write_pop(Kind::NONE, "that".to_string(), 0)?;
         } else {
             // simple variable
-            //self.xml_file.write("identifier".to_string(), assign_to.trim().to_string());
-
-            // negative * tmp.intValue()
-            // translates to
-            // call negative * tmp.intValue 0
-
-            let exp = content.get(content.find("=").unwrap() + 1..content.find(";").unwrap()).unwrap().trim();
-
-            self.compile_expression(exp.to_string());
-
-
-
-            /*if exp.contains("(") && exp.find("(").unwrap() < exp.find(")").unwrap() && exp.matches("(").count()==1 && !content.contains(" (") {
-                if exp.contains(","){
-                    if exp.find("(").unwrap() < exp.find(",").unwrap() && exp.find(",").unwrap() < exp.find(")").unwrap() {
-                        self.compile_func_call(exp.to_string());
-                    }
-                } else {
-                    self.compile_func_call(exp.to_string());
-                }
-
-            } else {
-                self.compile_expression(exp.to_string());
-            }*/
-
-            let mut kind = Kind::NONE;
-            let mut index = usize::MAX;
-            (kind, index) = self.get_kind_index(assign_to.trim().to_string());
-            self.vm_writer.write_pop(kind, "".to_string(), index);
+            self.compile_expression(value)?;
+            self.vm_writer.write_pop(kind, "".to_string(), index)?;
         }
-        //self.xml_file.write("symbol".to_string(), "=".to_string());
-        //self.xml_file.write("symbol".to_string(), ";".to_string());
-        //self.xml_file.close_tag("letStatement".to_string());
+        Ok(())
     }
 
     /// Compiles an if statement, possible with a trailing else clause.
-    fn compile_if(&mut self, content: String) {
-        //self.xml_file.open_tag("ifStatement".to_string());
-
-        let temp = content.get(content.find("(").unwrap() + 1..content.find("{").unwrap()).unwrap();
-
-        let expression = temp.get(0..temp.rfind(")").unwrap()).unwrap();
-
-        // NEED TO CHANGE THIS CONDITION TO NOT RECOGNIZE NESTED IF-ELSE
-        // check for all occurrences of "else" if the count of "{" and "}" are equal
-        // if true, proceed into the if
-        // if false, go to the else clause
-        let elses = content.match_indices("else");
-        if elses.clone().count() > 0 {
-            let mut value = usize::MAX;
-            for myelse in elses {
-                let start = content.get(0..myelse.0).unwrap().matches("{").count();
-                let end = content.get(0..myelse.0).unwrap().matches("}").count();
-                if start == end {
-                    value = myelse.0;
-                    //self.xml_file.write("keyword".to_string(), "if".to_string());
-                    //self.xml_file.write("symbol".to_string(), "(".to_string());
-                    let if_true = format!("IF_TRUE{}", self.if_label_index);
-                    let if_false = format!("IF_FALSE{}", self.if_label_index);
-                    let if_end = format!("IF_END{}", self.if_label_index);
-                    self.if_label_index += 1;
-
-                    self.compile_expression(expression.to_string());
-                    self.vm_writer.write_if(if_true.to_string());
-                    self.vm_writer.write_goto(if_false.to_string());
-                    self.vm_writer.write_label(if_true.to_string());
-
-
-                    //self.xml_file.write("symbol".to_string(), ")".to_string());
-                    //self.xml_file.write("symbol".to_string(), "{".to_string());
-
-
-                    // if body statements
-                    self.compile_statements(content.get(content.find("{").unwrap() + 1..value - 1).unwrap().to_string());
-
-                    self.vm_writer.write_goto(if_end.to_string());
-
-                    //self.xml_file.write("symbol".to_string(), "}".to_string());
-                    //self.xml_file.write("keyword".to_string(), "else".to_string());
-                    //self.xml_file.write("symbol".to_string(), "{".to_string());
-
-                    self.vm_writer.write_label(if_false.to_string());
-
-                    // else body statements
-                    let my_else = content.get(value + 3..content.rfind("}").unwrap()).unwrap();
-                    self.compile_statements(my_else.get(my_else.find("{").unwrap() + 1..my_else.len()).unwrap().to_string());
-
-                    self.vm_writer.write_label(if_end.to_string());
-
-                    //self.xml_file.write("symbol".to_string(), "}".to_string());
-                    return;
-                }
-            }
-        }
-        //self.xml_file.write("keyword".to_string(), "if".to_string());
-        //self.xml_file.write("symbol".to_string(), "(".to_string());
-
+    fn compile_if(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        let Statement::If { condition, statements, else_statements, .. } = statement else { return Ok(()); };
 
         let if_true = format!("IF_TRUE{}", self.if_label_index);
         let if_false = format!("IF_FALSE{}", self.if_label_index);
+        let if_end = format!("IF_END{}", self.if_label_index);
         self.if_label_index += 1;
 
-        self.compile_expression(expression.to_string());
-        self.vm_writer.write_if(if_true.to_string());
-        self.vm_writer.write_goto(if_false.to_string());
-        self.vm_writer.write_label(if_true.to_string());
-
-
-        //self.xml_file.write("symbol".to_string(), ")".to_string());
-        //self.xml_file.write("symbol".to_string(), "{".to_string());
-
+        self.compile_expression(condition)?;
+        self.vm_writer.write_if(if_true.to_string())?;
+        self.vm_writer.write_goto(if_false.to_string())?;
+        self.vm_writer.write_label(if_true.to_string())?;
 
         // if body statements
-        self.compile_statements(content.get(content.find("{").unwrap() + 1..content.rfind("}").unwrap()).unwrap().trim().to_string());
+        self.compile_statements(statements)?;
 
-        self.vm_writer.write_label(if_false.to_string());
+        if let Some(else_statements) = else_statements {
+            self.vm_writer.write_goto(if_end.to_string())?;
+            self.vm_writer.write_label(if_false.to_string())?;
 
-        //self.xml_file.write("symbol".to_string(), "}".to_string());
+            // else body statements
+            self.compile_statements(else_statements)?;
 
-
-        //self.xml_file.close_tag("ifStatement".to_string());
+            self.vm_writer.write_label(if_end.to_string())?;
+        } else {
+            self.vm_writer.write_label(if_false.to_string())?;
+        }
+        Ok(())
     }
 
     /// Compiles a while statement.
-    fn compile_while(&mut self, content: String) {
-        //self.xml_file.open_tag("whileStatement".to_string());
-
-        let temp = content.get(content.find("(").unwrap() + 1..content.find("{").unwrap()).unwrap();
+    fn compile_while(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        let Statement::While { condition, statements, .. } = statement else { return Ok(()); };
 
-        let expression = temp.get(0..temp.rfind(")").unwrap()).unwrap();
-
-        //self.xml_file.write("keyword".to_string(), "while".to_string());
-
-        //self.xml_file.write("symbol".to_string(), "(".to_string());
         let while_exp = format!("WHILE_EXP{}", self.while_label_index);
-
         let while_end = format!("WHILE_END{}", self.while_label_index);
         self.while_label_index += 1;
 
-        self.vm_writer.write_label(while_exp.to_string());
-        self.compile_expression(expression.to_string());
-        self.vm_writer.write_arithmetic(NOT);
-        self.vm_writer.write_if(while_end.to_string());
+        self.vm_writer.write_label(while_exp.to_string())?;
+        self.compile_expression(condition)?;
+        self.vm_writer.write_arithmetic(NOT)?;
+        self.vm_writer.write_if(while_end.to_string())?;
 
-        //self.xml_file.write("symbol".to_string(), ")".to_string());
-        //self.xml_file.write("symbol".to_string(), "{".to_string());
+        self.compile_statements(statements)?;
 
-        self.compile_statements(content.get(content.find("{").unwrap() + 1..content.rfind("}").unwrap()).unwrap().to_string());
-
-        self.vm_writer.write_goto(while_exp.to_string());
-        self.vm_writer.write_label(while_end.to_string());
-        //self.xml_file.write("symbol".to_string(), "}".to_string());
-        //self.xml_file.close_tag("whileStatement".to_string());
+        self.vm_writer.write_goto(while_exp.to_string())?;
+        self.vm_writer.write_label(while_end.to_string())?;
+        Ok(())
     }
 
     /// Compiles a do statement.
-    fn compile_do(&mut self, content: String) {
-        //self.xml_file.open_tag("doStatement".to_string());
-
-        //self.xml_file.write("keyword".to_string(), "do".to_string());
+    fn compile_do(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        let Statement::Do { call, .. } = statement else { return Ok(()); };
 
-        let do_content = content.get(content.trim().find(" ").unwrap() + 1..content.trim().len() - 1).unwrap();
+        self.compile_func_call(call)?;
 
-        self.compile_func_call(do_content.to_string());
-
-        self.vm_writer.write_pop(Kind::NONE, "temp".to_string(), 0);
-
-        //self.xml_file.write("symbol".to_string(), "(".to_string());
-        //self.xml_file.write("symbol".to_string(), ")".to_string());
-        //self.xml_file.write("symbol".to_string(), ";".to_string());
-        //self.xml_file.close_tag("doStatement".to_string());
+        self.vm_writer.write_pop(Kind::NONE, "temp".to_string(), 0)?;
+        Ok(())
     }
 
     /// Compiles a return statement.
-    fn compile_return(&mut self, content: String) {
-        //self.xml_file.open_tag("returnStatement".to_string());
-        //self.xml_file.write("keyword".to_string(), "return".to_string());
+    fn compile_return(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        let Statement::Return { value, span } = statement else { return Ok(()); };
 
-        if !content.contains("return;") {
-            self.compile_expression(content.get(content.trim().find(" ").unwrap()..content.find(";").unwrap()).unwrap().trim().to_string());
-        } else {
-            self.vm_writer.write_push(Kind::NONE, "".to_string(), 0);
+        match (value, self.return_type == "void") {
+            (Some(value), true) => {
+                return Err(CompileError::new(value.span(), format!("{} is declared void, so it can't return a value", self.subroutine_name)));
+            }
+            (None, false) => {
+                return Err(CompileError::new(*span, format!("{} returns {}, so the return statement needs a value", self.subroutine_name, self.return_type)));
+            }
+            _ => {}
+        }
+        let returns_this = value.as_ref()
+            .is_some_and(|value| value.rest.is_empty() && matches!(value.term, Term::KeywordConstant { keyword: KeywordConstant::This, .. }));
+        // jack only expects constructors to return this, and the reference compiler accepts anything
+        if self.subroutine_kind == SubroutineKind::Constructor && !returns_this {
+            self.warnings.push(Warning::new(*span, format!("{} is a constructor, so it should return this", self.subroutine_name)));
         }
 
-        self.vm_writer.write_return();
-        //self.xml_file.write("symbol".to_string(), ";".to_string());
-        //self.xml_file.close_tag("returnStatement".to_string());
+        match value {
+            Some(value) => { self.compile_expression(value)?; }
+            None => { self.vm_writer.write_push(Kind::NONE, "".to_string(), 0)?; }
+        }
+
+        self.vm_writer.write_return()?;
+        Ok(())
     }
 
     /// Compiles an expression.
-    fn compile_expression(&mut self, expression: String) {
-
-        //self.xml_file.open_tag("expression".to_string());
-
-        let mut index = usize::MAX;
-        let mut tmp;
-        let mut arr: Vec<usize> = Vec::new();
-        for op in OP {
-            let temp = expression.trim().match_indices(op);
-            for tmp in temp {
-                if tmp.0 != usize::MAX {
-                    arr.push(tmp.0);
-                }
-            }
+    /// The operators are applied from left to right, as the jack language defines,
+    /// unless the standard precedence was asked for.
+    fn compile_expression(&mut self, expression: &Expression) -> Result<(), CompileError> {
+        if self.precedence == Precedence::LeftToRight {
+            self.check_mixed_precedence(expression);
         }
-        index = usize::MAX;
-        // makes sure that the operation we are working on is not inside round brackets "()"
-        for val in arr {
-            let start = expression.get(0..val).unwrap().trim();
-            let end = expression.get(val + 1..expression.len()).unwrap().trim();
-
-            let start_open = start.find("(");
-            let start_end = start.rfind(")");
-            let end_open = end.find("(");
-            let end_end = end.rfind(")");
-            if (start_open == Some(0) && start_end == Some(start.len() - 1)) || (end_open == Some(0) && end_end == Some(end.len() - 1)) {
-                index = if start_end.is_some() { start_end.unwrap() + 1 } else if end_open.is_some() { val } else { 0 };
-                let mut s = expression.get(val..index + 1);
-                while s == Some(" ") && s.is_some() {
-                    index = index + 1;
-                    s = expression.get(index..index + 1);
-                }
-
-                break;
-            }
-            if val < index {
-                index = val;
+        for evaluation in expression.evaluation_order(self.precedence) {
+            match evaluation {
+                Evaluation::Term(term) => { self.compile_term(term)?; }
+                Evaluation::Op(op, _) => { self.compile_op(op)?; }
             }
         }
-        let reg = Regex::new(r"(?m)\(.*\) ([+\-*/&|<>=]) \(.*\)|\(.*\)([+\-*/&|<>=])\(.*\)").unwrap();
-        if reg.is_match(expression.trim()) {
-            for op in OP {
-                let checks = expression.split(op);
-
-                if checks.clone().count() > 1 {
-                    for item in checks.clone() {
-                        let exp = item.trim();
-
-                        if exp.find("(") == Some(0) && exp.rfind(")") == Some(exp.len() - 1) && exp.matches("(").count() == exp.matches(")").count() {
-                            let start = item.get(0..expression.find(op).unwrap()).unwrap().trim();
-                            let end = expression.get(expression.find(op).unwrap() + 1..expression.len()).unwrap().trim();
-                            if start.matches("(").count() != start.matches(")").count() && end.matches("(").count() != end.matches(")").count() { continue; }
-                            self.compile_term(start.to_string());
-
-                            let mut rest_of_exp_has_op = false;
-                            for op in OP {
-                                if op != "=" && op != "~" && op != "-" {
-                                    tmp = end.trim().find(op);
-                                    match tmp {
-                                        None => {}
-                                        Some(_) => {
-                                            rest_of_exp_has_op = true;
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-
-                            if rest_of_exp_has_op && !(end.find("(") == Some(0) && (end.find("~") == Some(1) || end.find("-") == Some(1)) && end.rfind(")") == Some(end.len()-1)) {
-                                self.compile_expression(end.to_string());
-                            } else if !rest_of_exp_has_op && end.find("(") == Some(0) && (end.find("~") == Some(1) || end.find("-") == Some(1)) && end.rfind(")") == Some(end.len()-1){
-                                self.compile_term(end.to_string());
-                            } else {
-                                if rest_of_exp_has_op {
-                                    self.compile_expression(end.to_string());
-                                } else {
-                                    self.compile_term(end.trim().to_string());
-                                }
-                            }
-
-
-
-                            if item != checks.clone().last().unwrap() {
-                                match op {
-                                    "*" => self.vm_writer.write_call("Math.multiply".to_string(), 2),
-                                    "/" => self.vm_writer.write_call("Math.divide".to_string(), 2),
-                                    "+" => self.vm_writer.write_arithmetic(ADD),
-                                    "-" => self.vm_writer.write_arithmetic(SUB),
-                                    "=" => self.vm_writer.write_arithmetic(EQ),
-                                    ">" => self.vm_writer.write_arithmetic(GT),
-                                    "<" => self.vm_writer.write_arithmetic(LT),
-                                    "&" => self.vm_writer.write_arithmetic(AND),
-                                    "|" => self.vm_writer.write_arithmetic(OR),
-                                    "~" => self.vm_writer.write_arithmetic(NOT),
-                                    &_ => {}
-                                };
-                                break;
-                            } else {
-                                //self.xml_file.close_tag("expression".to_string());
-                                return;
-                            }
-                        } else { continue; }
-                    }
-                }
-            }
-        } else if expression.find("\"") == Some(0) && expression.rfind("\"") == Some(expression.len() - 1) {
-            self.compile_term(expression);
-        } else if index == usize::MAX {
-            self.compile_term(expression.trim().to_string());
-        } else if index == 0 {
-            self.compile_term(expression.trim().to_string());
-        } else if expression.get(0..1).unwrap() == "~" || expression.get(0..1).unwrap() == "-" {
-            self.compile_term(expression.trim().to_string());
-        } else if expression.trim().get(0..index).unwrap().find("(") == Some(0) && expression.trim().get(0..index).unwrap().find(")") == Some(expression.len() - 1) {
-            self.compile_term(expression.get(expression.find("(").unwrap()..expression.rfind(")").unwrap() + 1).unwrap().trim().to_string());
-        } else if expression.trim().find("(") == Some(0) && expression.trim().find(")") == Some(expression.len() - 1) {
-            self.compile_term(expression.trim().to_string());
-        } else if expression.trim().find("(").is_some() && expression.trim().find(")").is_some() && index == usize::MAX {
-            self.compile_term(expression);
-        } else {
-            let start_of_exp = expression.get(0..index).unwrap().trim();
-            let mut start_of_exp_has_op = false;
-            for op in OP {
-                tmp = start_of_exp.trim().find(op);
-                match tmp {
-                    None => {}
-                    Some(_) => {
-                        start_of_exp_has_op = true;
-                        break;
-                    }
-                }
-            }
-            if start_of_exp_has_op{
-                self.compile_expression(start_of_exp.to_string());
-
-            } else {
-                self.compile_term(start_of_exp.to_string());
-            }
-
-            let mut symbol = expression.get(index..index + 1).unwrap().trim();
-            if symbol == "" {
-                symbol = expression.get(index + 1..index + 2).unwrap().trim();
-                if symbol == "" {
-                    symbol = expression.get(index + 2..index + 3).unwrap().trim();
-                }
-            }
-
-
-            let rest_of_exp = expression.get(index + 2..expression.len()).unwrap().trim();
-            let mut rest_of_exp_has_op = false;
-            for op in OP {
-                tmp = rest_of_exp.trim().find(op);
-                match tmp {
-                    None => {}
-                    Some(_) => {
-                        rest_of_exp_has_op = true;
-                        break;
-                    }
-                }
-            }
+        Ok(())
+    }
 
-            if rest_of_exp_has_op {
-                self.compile_expression(rest_of_exp.to_string());
-            } else {
-                self.compile_term(rest_of_exp.trim().to_string());
+    /// Warns about an expression whose value depends on whether operators are ordered by precedence,
+    /// which happens when an operator follows one of a lower precedence level without parentheses.
+    fn check_mixed_precedence(&mut self, expression: &Expression) {
+        for pair in expression.rest.windows(2) {
+            let (first, second) = (pair[0].0, pair[1].0);
+            if second.precedence() > first.precedence() {
+                self.warnings.push(Warning::new(expression.span(), format!(
+                    "'{}' is applied before '{}', since jack applies operators from left to right; add parentheses to make the order explicit",
+                    first.symbol(), second.symbol()
+                )));
+                return;
             }
-            match symbol {
-                "*" => self.vm_writer.write_call("Math.multiply".to_string(), 2),
-                "/" => self.vm_writer.write_call("Math.divide".to_string(), 2),
-                "+" => self.vm_writer.write_arithmetic(ADD),
-                "-" => self.vm_writer.write_arithmetic(SUB),
-                "=" => self.vm_writer.write_arithmetic(EQ),
-                ">" => self.vm_writer.write_arithmetic(GT),
-                "<" => self.vm_writer.write_arithmetic(LT),
-                "&" => self.vm_writer.write_arithmetic(AND),
-                "|" => self.vm_writer.write_arithmetic(OR),
-                "~" => self.vm_writer.write_arithmetic(NOT),
-                &_ => {}
-            };
         }
-        //self.xml_file.close_tag("expression".to_string());
+    }
+
+    /// Writes the vm code of a binary operator, applied to the two values on top of the stack
+    fn compile_op(&mut self, op: Op) -> std::io::Result<()> {
+        match op {
+            Op::Mul => self.vm_writer.write_call("Math.multiply".to_string(), 2)?,
+            Op::Div => self.vm_writer.write_call("Math.divide".to_string(), 2)?,
+            Op::Add => self.vm_writer.write_arithmetic(ADD)?,
+            Op::Sub => self.vm_writer.write_arithmetic(SUB)?,
+            Op::Eq => self.vm_writer.write_arithmetic(EQ)?,
+            Op::Gt => self.vm_writer.write_arithmetic(GT)?,
+            Op::Lt => self.vm_writer.write_arithmetic(LT)?,
+            Op::And => self.vm_writer.write_arithmetic(AND)?,
+            Op::Or => self.vm_writer.write_arithmetic(OR)?,
+        };
+        Ok(())
     }
 
     /// Compiles a term.
-    /// If the current token is an identifier, the routine must distinguish between a variable,
-    /// an array-entry, or a subroutine-call.
-    /// A single look-ahead token, which may be one of "[" , "(" or ".",
-    /// suffices to distinguish between the possibilities
-    /// Any other token is not part of this term and should not be advanced over.
-    fn compile_term(&mut self, term: String) {
-        //self.xml_file.open_tag("term".to_string());
-        for keyword in KEYWORD_CONSTANT {
-            if term == keyword.to_string() {
+    fn compile_term(&mut self, term: &Term) -> Result<(), CompileError> {
+        match term {
+            Term::IntegerConstant { value, .. } => {
+                self.vm_writer.write_push(Kind::NONE, "".to_string(), *value as usize)?;
+            }
+            Term::StringConstant { value, .. } => {
+                // Create the string object
+                // push constant string_constant.len()
+                // call String.new 1
+                self.vm_writer.write_push(Kind::NONE, "".to_string(), value.chars().count())?;
+                self.vm_writer.write_call("String.new".to_string(), 1)?;
+
+                // Push the string contents to the new string object
+                for ch in value.chars() {
+                    // push constant ch            -- for each char in string_constant
+                    // call String.appendChar 2    -- for each char in string_constant
+                    self.vm_writer.write_push(Kind::NONE, "".to_string(), ch as usize)?;
+                    self.vm_writer.write_call("String.appendChar".to_string(), 2)?;
+                }
+            }
+            Term::KeywordConstant { keyword, .. } => {
                 match keyword {
-                    "true" => {
-                        self.vm_writer.write_push(Kind::NONE, "".to_string(), 0);
-                        self.vm_writer.write_arithmetic(NOT);
+                    KeywordConstant::True => {
+                        self.vm_writer.write_push(Kind::NONE, "".to_string(), 0)?;
+                        self.vm_writer.write_arithmetic(NOT)?;
+                    }
+                    KeywordConstant::False | KeywordConstant::Null => { self.vm_writer.write_push(Kind::NONE, "".to_string(), 0)?; }
+                    KeywordConstant::This => {
+                        if self.subroutine_kind == SubroutineKind::Function {
+                            return Err(CompileError::new(term.span(), "'this' can't be used in a function, which has no object".to_string()));
+                        }
+                        self.vm_writer.write_push(Kind::NONE, "pointer".to_string(), 0)?;
                     }
-                    "null" | "false" => { self.vm_writer.write_push(Kind::NONE, "".to_string(), 0); }
-                    "this" => { self.vm_writer.write_push(Kind::NONE, "pointer".to_string(), 0); }
-                    _ => {}
                 }
-                //self.xml_file.write("keyword".to_string(), term.to_string());
-                //self.xml_file.close_tag("term".to_string());
-                return;
-            }
-        }
-        let mut unary_op = false;
-        for op in UNARY_OP {
-            if term.find(op) == Some(0) { unary_op = true; }
-        }
-        if unary_op {
-            //self.xml_file.write("symbol".to_string(), term.get(0..1).unwrap().to_string());
-
-            self.compile_term(term.get(1..term.len()).unwrap().to_string());
-            match term.get(0..1).unwrap() {
-                "-" => self.vm_writer.write_arithmetic(NEG),
-                "~" => self.vm_writer.write_arithmetic(NOT),
-                &_ => {}
-            }
-        } else if term.find("(") == Some(0) && term.rfind(")") == Some(term.len() - 1) {
-            //self.xml_file.write("symbol".to_string(), "(".to_string());
-            if term.find("-") == Some(1) {
-                self.compile_expression(term.get(1..term.len() - 1).unwrap().to_string());
-            } else if term.find("~") == Some(1) {
-                self.compile_expression(term.get(1..term.len() - 1).unwrap().to_string());
-            } else {
-                self.compile_expression(term.get(1..term.len() - 1).unwrap().to_string());
             }
-            //self.xml_file.write("symbol".to_string(), ")".to_string());
-        } else if term.find("\"") == Some(0) && term.rfind("\"") == Some(term.len() - 1) {
-            let string_constant = term.get(term.find("\"").unwrap() + 1..term.rfind("\"").unwrap()).unwrap();
-
-            // Create the string object
-            // push constant string_constant.len()
-            // call String.new 1
-            self.vm_writer.write_push(Kind::NONE, "".to_string(), string_constant.len());
-            self.vm_writer.write_call("String.new".to_string(), 1);
-
-            // Push the string contents to the new string object
-            for ch in string_constant.chars() {
-                // push constant ch            -- for each char in string_constant
-                // call String.appendChar 2    -- for each char in string_constant
-
-                self.vm_writer.write_push(Kind::NONE, "".to_string(), ch as usize);
-                self.vm_writer.write_call("String.appendChar".to_string(), 2);
+            Term::Variable(name) => {
+                let (kind, index) = self.variable(name, false)?;
+                self.vm_writer.write_push(kind, "".to_string(), index)?;
             }
-            //self.xml_file.write("stringConstant".to_string(), term.get(term.find("\"").unwrap() + 1..term.rfind("\"").unwrap()).unwrap().to_string());
-        } else if term.chars().all(char::is_numeric) {
-            // check for integer constant
-            self.vm_writer.write_push(Kind::NONE, "".to_string(), term.parse().unwrap());
-            //self.xml_file.write("integerConstant".to_string(), term.to_string());
-        } else if term.find("[").is_some() {
-            //self.xml_file.write("identifier".to_string(), term.get(0..term.find("[").unwrap()).unwrap().to_string());
-            //self.xml_file.write("symbol".to_string(), "[".to_string());
-
-            let arr_name = term.get(0..term.find("[").unwrap()).unwrap();
-            let arr_entry = term.get(term.find("[").unwrap() + 1..term.rfind("]").unwrap()).unwrap();
-
-            let mut kind = Kind::NONE;
-            let mut index = usize::MAX;
-            (kind, index) = self.get_kind_index(arr_name.to_string());
-
-            self.compile_expression(arr_entry.to_string());
-            self.vm_writer.write_push(kind, "".to_string(), index);// push arr
-
-            self.vm_writer.write_arithmetic(ADD);
-
-            self.vm_writer.write_pop(Kind::NONE, "pointer".to_string(), 1);
-            self.vm_writer.write_push(Kind::NONE, "that".to_string(), 0);
-
-
-            //self.xml_file.write("symbol".to_string(), "]".to_string());
-        } else if term.find(".").is_some() {
-            let class_name = term.get(0..term.find(".").unwrap()).unwrap();
-            //self.xml_file.write("identifier".to_string(), term.get(0..term.find(".").unwrap()).unwrap().to_string());
-            //self.xml_file.write("symbol".to_string(), ".".to_string());
-
-            let subroutine_name = term.get(term.find(".").unwrap() + 1..term.find("(").unwrap()).unwrap();
-
-
-            //self.xml_file.write("identifier".to_string(), identifier.to_string());
-            //self.xml_file.write("symbol".to_string(), "(".to_string());
-            let mut kind = Kind::NONE;
-            let mut index = usize::MAX;
-            (kind, index) = self.get_kind_index(class_name.to_string());
-            let mut data_type = self.subroutine_symbol_table.type_of(class_name.to_string());
-            if data_type == "".to_string() { data_type = self.class_symbol_table.type_of(class_name.to_string()); }
-
-            let expressions = term.get(term.find("(").unwrap() + 1..term.rfind(")").unwrap()).unwrap();
-
-            let expression_count = if expressions.is_empty() { 0 } else { expressions.matches(",").count() + 1 };
-
-            // generates "call SquareGame.run 1" instead of "call square.run 1"
-            if subroutine_name == "new" {
-                // Constructor call
-                self.compile_expression_list(expressions.to_string());
+            Term::ArrayEntry { name, index: array_index } => {
+                let (kind, index) = self.variable(name, false)?;
 
-                self.vm_writer.write_call(format!("{}.{}", class_name, subroutine_name), expression_count);
-            } else if kind != Kind::NONE {
-                self.vm_writer.write_push(kind, "".to_string(), index);
-                self.compile_expression_list(expressions.to_string());
+                self.compile_expression(array_index)?;
+                self.vm_writer.write_push(kind, "".to_string(), index)?;// push arr
 
-                self.vm_writer.write_call(format!("{}.{}", data_type, subroutine_name), expression_count + 1);
-            } else {
-                self.compile_expression_list(expressions.to_string());
+                self.vm_writer.write_arithmetic(ADD)?;
 
-                self.vm_writer.write_call(format!("{}.{}", class_name, subroutine_name), expression_count);
+                self.vm_writer.write_pop(Kind::NONE, "pointer".to_string(), 1)?;
+                self.vm_writer.write_push(Kind::NONE, "that".to_string(), 0)?;
             }
-            //self.xml_file.write("symbol".to_string(), ")".to_string());
-        } else {
-            // var name or expression
-
-            let mut kind = Kind::NONE;
-            let mut index = 0;
-            (kind, index) = self.get_kind_index(term.to_string());
-            if index != usize::MAX {
-                self.vm_writer.write_push(kind, "".to_string(), index);
-            } else {
-                self.vm_writer.write_push(Kind::NONE, "".to_string(), index);
+            Term::Call(call) => { self.compile_func_call(call)?; }
+            Term::Parenthesized { expression, .. } => { self.compile_expression(expression)?; }
+            Term::Unary { op, term, .. } => {
+                self.compile_term(term)?;
+                match op {
+                    UnaryOp::Neg => self.vm_writer.write_arithmetic(NEG)?,
+                    UnaryOp::Not => self.vm_writer.write_arithmetic(NOT)?,
+                }
             }
-
-            //self.xml_file.write("identifier".to_string(), term.to_string());
         }
-        //self.xml_file.close_tag("term".to_string());
+        Ok(())
     }
 
     /// Compiles a (possibly empty) comma-seperated list of expressions.
-    fn compile_expression_list(&mut self, content: String) {
-        //self.xml_file.open_tag("expressionList".to_string());
-
-        if !content.is_empty() {
-            if content.contains("(") && content.contains(")") && content.matches("(").count() == 1 && !content.contains(" (") && content.find("(").unwrap() < content.find(")").unwrap() {
-                if content.contains(","){
-                    if content.find("(").unwrap() < content.find(",").unwrap() && content.find(",").unwrap() < content.find(")").unwrap() {
-                        self.compile_func_call(content.to_string());
-                        return;
-                    }
-                } else {
-                    self.compile_func_call(content.trim().to_string());
-                    return;
-                }
-
-
-            }
-            if content.find("\"") == Some(0) && content.rfind("\"") == Some(content.len() - 1) {
-                self.compile_expression(content);
-                return;
-            }
-
-            let commas = content.matches(",").count();
-            let mut current = 0;
-            let expressions = content.split(",");
-            for expression in expressions {
-                self.compile_expression(expression.trim().to_string());
-                if current < commas {
-                    current += 1;
-
-                    //self.xml_file.write("symbol".to_string(), ",".to_string());
-                }
-            }
+    fn compile_expression_list(&mut self, expressions: &[Expression]) -> Result<(), CompileError> {
+        for expression in expressions.iter() {
+            self.compile_expression(expression)?;
         }
-        //self.xml_file.close_tag("expressionList".to_string());
+        Ok(())
     }
 
-    /// Gets the Kind and type of a variable if exists
-    fn get_kind_index(&self, name: String) -> (Kind, usize) {
-        let mut kind = self.subroutine_symbol_table.kind_of(name.to_string());
-        let index;
-        if kind == Kind::NONE {
-            kind = self.class_symbol_table.kind_of(name.to_string());
-            index = self.class_symbol_table.index_of(name.to_string());
-        } else {
-            index = self.subroutine_symbol_table.index_of(name.to_string());
-        }
-        (kind, index)
+    /// Finds the kind and index of a variable the code uses
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable, as written in the code
+    /// * `assigned` - Whether the code assigns to the variable, rather than reads it
+    ///
+    /// # Returns
+    ///
+    /// * The kind and index of the variable, or an error if the name isn't a variable the subroutine can use
+    fn variable(&mut self, name: &Name, assigned: bool) -> Result<(Kind, usize), CompileError> {
+        self.symbol_table.mark_used(&name.name, assigned);
+        let problem = match self.symbol_table.resolve(&name.name) {
+            None if self.signatures.subroutine(&self.class_name, &name.name).is_some() => { format!("'{}' is a subroutine, not a variable", name.name) }
+            None if name.name == self.class_name => { format!("'{}' is a class, not a variable", name.name) }
+            None => { format!("the variable '{}' is not defined", name.name) }
+            Some(symbol) if symbol.get_kind() == Kind::FIELD && self.subroutine_kind == SubroutineKind::Function => {
+                format!("the field '{}' can't be used in a function, which has no object", name.name)
+            }
+            Some(symbol) => { return Ok((symbol.get_kind(), symbol.get_index())); }
+        };
+        let message = if assigned { format!("can't assign to '{}': {}", name.name, problem) } else { problem };
+        Err(CompileError::new(name.span, message))
     }
 
     /// Compiles a function call
-    fn compile_func_call(&mut self, call:String) {
-        let dot = call.find('.');
-
-        if let Some(value) = dot {
-            // another class's method
-            let class_name = call.get(0..value).unwrap();
-            let expression_list = call.get(call.find("(").unwrap() + 1..call.rfind(")").unwrap()).unwrap();
-            let mut param_count = 0;
-
-
-            if !expression_list.is_empty() {
-                param_count += expression_list.matches(",").count() + 1;
-            }
-            for comma in expression_list.match_indices(','){
-                let mut temp = expression_list.match_indices("\"");
-                for _index in 0..(expression_list.match_indices("\"").count()/2) {
-                    if temp.nth(0).unwrap().0 < comma.0 && comma.0 < temp.nth(0).unwrap().0 {
-                        param_count-=1;
-                    }
+    fn compile_func_call(&mut self, call: &SubroutineCall) -> Result<(), CompileError> {
+        let argument_count = call.arguments.len();
+
+        match &call.receiver {
+            Some(receiver) => {
+                if let Some(data_type) = self.symbol_table.resolve(&receiver.name).map(|symbol| symbol.get_data_type().to_string()) {
+                    // a method of an object, which is passed as the first argument
+                    let (kind, index) = self.variable(receiver, false)?;
+                    self.check_call(&data_type, call, Receiver::Object)?;
+
+                    self.vm_writer.write_push(kind, "".to_string(), index)?;
+                    self.compile_expression_list(&call.arguments)?;
+                    self.vm_writer.write_call(format!("{}.{}", data_type, call.name.name), argument_count + 1)?;
+                } else {
+                    // a function or constructor of another class
+                    self.check_call(&receiver.name, call, Receiver::Class)?;
+                    self.compile_expression_list(&call.arguments)?;
+                    self.vm_writer.write_call(format!("{}.{}", receiver.name, call.name.name), argument_count)?;
                 }
-
             }
-            let mut kind = Kind::NONE;
-            let mut index = usize::MAX;
-            (kind, index) = self.get_kind_index(class_name.to_string());
-            if kind != Kind::NONE {
-                self.vm_writer.write_push(kind, "".to_string(), index);
-                param_count += 1;
+            None => {
+                // this class's method
+                self.check_call(&self.class_name, call, Receiver::This)?;
+                self.vm_writer.write_push(Kind::NONE, "pointer".to_string(), 0)?;
+                self.compile_expression_list(&call.arguments)?;
+                self.vm_writer.write_call(format!("{}.{}", self.class_name, call.name.name), argument_count + 1)?;
             }
+        }
+        Ok(())
+    }
 
-            self.compile_expression_list(expression_list.to_string());
-
-            if BUILT_IN_CLASSES.contains(&class_name) {
-                self.vm_writer.write_call(call.get(0..call.find("(").unwrap()).unwrap().to_string(), param_count);
-            } else {
-                let mut call_1 = self.subroutine_symbol_table.type_of(class_name.to_string());
-                if call_1.is_empty() { call_1 = self.class_symbol_table.type_of(class_name.to_string()); }
-                if !call_1.is_empty() {
-                    self.vm_writer.write_call((call_1 + call.get(call.find(".").unwrap()..call.find("(").unwrap()).unwrap()).to_string(), param_count);
-                } else {
-                    self.vm_writer.write_call((class_name.to_string() + call.get(call.find(".").unwrap()..call.find("(").unwrap()).unwrap()).to_string(), param_count);
-                }
+    /// Checks that a call goes to an existing subroutine of the right kind, with the right number of arguments
+    ///
+    /// # Arguments
+    ///
+    /// * `class_name` - The class of the called subroutine, the type of the object for method calls
+    /// * `call` - The call
+    /// * `receiver` - What the subroutine is called on
+    fn check_call(&self, class_name: &str, call: &SubroutineCall, receiver: Receiver) -> Result<(), CompileError> {
+        let full_name = format!("{}.{}", class_name, call.name.name);
+        let receiver_name = call.receiver.as_ref().map_or("this", |receiver| receiver.name.as_str());
+        if DATA_TYPES.contains(&class_name) {
+            return Err(CompileError::new(call.span(), format!("'{}' is of type {}, which has no subroutines", receiver_name, class_name)));
+        }
+        match self.signatures.has_class(class_name) {
+            Some(true) => {}
+            Some(false) => { return Ok(()); }
+            None if receiver == Receiver::Class && class_name.starts_with(|ch: char| ch.is_ascii_lowercase()) => {
+                return Err(CompileError::new(call.span(), format!("the variable '{}' is not defined", class_name)));
             }
+            None => { return Err(CompileError::new(call.span(), format!("the class '{}' is not defined", class_name))); }
+        }
+        let Some(signature) = self.signatures.subroutine(class_name, &call.name.name) else {
+            return Err(CompileError::new(call.name.span, format!("the class {} has no subroutine named '{}'", class_name, call.name.name)));
+        };
 
-        } else {
-            // this class's method
-
-            self.vm_writer.write_push(Kind::NONE, "pointer".to_string(), 0);
-
-            let expression_list = call.get(call.find("(").unwrap() + 1..call.rfind(")").unwrap()).unwrap();
-
-            let mut param_count = 1;
-            if !expression_list.is_empty() {
-                param_count += expression_list.matches(",").count() + 1;
+        let problem = match (receiver, signature.kind) {
+            (Receiver::Object, SubroutineKind::Method) | (Receiver::Class, SubroutineKind::Function | SubroutineKind::Constructor) => { None }
+            (Receiver::This, SubroutineKind::Method) if self.subroutine_kind == SubroutineKind::Function => {
+                Some(format!("the method {} can't be called from a function, which has no object", full_name))
             }
-
-            self.compile_expression_list(expression_list.to_string());
-
-            self.vm_writer.write_call((self.class_name.to_string() + "." + call.get(0..call.find("(").unwrap()).unwrap()).to_string(), param_count);
-
-
+            (Receiver::This, SubroutineKind::Method) => { None }
+            (Receiver::Class, SubroutineKind::Method) => { Some(format!("{} is a method, so it must be called on an object", full_name)) }
+            (Receiver::Object | Receiver::This, kind) => {
+                Some(format!("{} is a {}, so it must be called as {}(...)", full_name, kind.keyword(), full_name))
+            }
+        };
+        if let Some(problem) = problem {
+            return Err(CompileError::new(call.span(), problem));
         }
+        if signature.parameters.len() != call.arguments.len() {
+            return Err(CompileError::new(call.name.span, format!(
+                "{} takes {} argument(s), but is called with {}", full_name, signature.parameters.len(), call.arguments.len()
+            )));
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+}
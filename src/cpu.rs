@@ -0,0 +1,269 @@
+/// This file contains the emulator of the hack CPU, which runs the machine instructions of a .hack
+/// or .asm file on a model of the hack computer, as the CPUEmulator of nand2tetris does
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+use crate::assembler::{assemble, from_hack_text};
+use crate::key_script::KeyEvent;
+use crate::vm::{screen_pbm, KEYBOARD, RAM_SIZE};
+
+/// Why the emulator stopped running
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stop {
+    /// The program reached the endless loop hack programs end with, such as `(END) @END 0;JMP`
+    EndLoop,
+    /// The program counter went past the last instruction of the program
+    EndOfProgram,
+    /// The maximum number of cycles was reached while the program was still running
+    CycleLimit,
+}
+
+/// The hack computer: the CPU with its A, D and PC registers, the ROM holding the program,
+/// and the RAM with the screen and keyboard memory maps.
+pub struct Cpu {
+    rom: Vec<u16>,
+    ram: Vec<i16>,
+    a: i16,
+    d: i16,
+    pc: u16,
+    cycles: u64,
+    /// The scripted key events that didn't happen yet
+    key_events: VecDeque<KeyEvent>,
+}
+
+impl Cpu {
+    /// Puts a program in the ROM of a computer with a cleared RAM
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - The machine instructions of the program
+    pub fn new(rom: Vec<u16>) -> Self {
+        Cpu {
+            rom,
+            ram: vec![0; RAM_SIZE],
+            a: 0,
+            d: 0,
+            pc: 0,
+            cycles: 0,
+            key_events: VecDeque::new(),
+        }
+    }
+
+    /// Loads the program of a .hack file, or assembles the program of an .asm file
+    ///
+    /// # Returns
+    ///
+    /// * The computer, ready to run the program, or the rendered error of the first bad line
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let display_path = path.display().to_string();
+        let source = fs::read_to_string(path).map_err(|e| format!("error: could not read {}: {}", display_path, e))?;
+        let rom = if path.extension().is_some_and(|extension| extension == "asm") {
+            assemble(&source)
+        } else {
+            from_hack_text(&source)
+        };
+        rom.map(Cpu::new).map_err(|e| e.render(&display_path, &source))
+    }
+
+    /// Runs until the program stops or the maximum number of cycles is reached.
+    /// Every instruction takes a single cycle.
+    ///
+    /// # Returns
+    ///
+    /// * Why the program stopped, or the message of the runtime error that stopped it
+    pub fn run(&mut self, max_cycles: u64) -> Result<Stop, String> {
+        while self.cycles < max_cycles {
+            if let Some(stop) = self.step()? {
+                return Ok(stop);
+            }
+        }
+        Ok(Stop::CycleLimit)
+    }
+
+    /// Runs a single instruction
+    ///
+    /// # Returns
+    ///
+    /// * Why the program stopped if it did, or the message of the runtime error
+    pub fn step(&mut self) -> Result<Option<Stop>, String> {
+        let pc = self.pc;
+        let Some(&instruction) = self.rom.get(pc as usize) else { return Ok(Some(Stop::EndOfProgram)); };
+        while let Some(event) = self.key_events.front().filter(|event| event.step <= self.cycles) {
+            self.ram[KEYBOARD] = event.key;
+            self.key_events.pop_front();
+        }
+        self.cycles += 1;
+
+        if instruction & 0x8000 == 0 {
+            self.a = instruction as i16;
+            self.pc = pc + 1;
+            return Ok(None);
+        }
+
+        let reads_memory = instruction & 0x1000 != 0;
+        let y = if reads_memory { self.ram[self.memory_address(pc)?] } else { self.a };
+        let out = alu(self.d, y, (instruction >> 6) & 0b111111);
+
+        let address = self.a;
+        if instruction & 0b001000 != 0 {
+            let target = self.memory_address(pc)?;
+            self.ram[target] = out;
+        }
+        if instruction & 0b100000 != 0 {
+            self.a = out;
+        }
+        if instruction & 0b010000 != 0 {
+            self.d = out;
+        }
+
+        let jump = instruction & 0b111;
+        let jumps = (jump & 0b100 != 0 && out < 0) || (jump & 0b010 != 0 && out == 0) || (jump & 0b001 != 0 && out > 0);
+        if !jumps {
+            self.pc = pc + 1;
+            return Ok(None);
+        }
+        self.pc = address as u16 & 0x7fff;
+
+        // An instruction that only jumps back to the instruction loading its own address loops forever
+        let only_jumps = instruction & 0b111000 == 0;
+        if only_jumps && pc > 0 && self.pc == pc - 1 && self.rom[self.pc as usize] == self.pc {
+            return Ok(Some(Stop::EndLoop));
+        }
+        Ok(None)
+    }
+
+    /// Checks that the A register holds an address of the RAM, the screen or the keyboard
+    fn memory_address(&self, pc: u16) -> Result<usize, String> {
+        let address = self.a as u16 as usize;
+        if address > KEYBOARD {
+            return Err(format!("runtime error: the address {} is outside of the memory\n --> ROM[{}]", address, pc));
+        }
+        Ok(address)
+    }
+
+    /// The number of cycles run so far
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// The A register
+    pub fn a(&self) -> i16 {
+        self.a
+    }
+
+    /// The D register
+    pub fn d(&self) -> i16 {
+        self.d
+    }
+
+    /// The address of the next instruction
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Sets the A register
+    pub fn set_a(&mut self, value: i16) {
+        self.a = value;
+    }
+
+    /// Sets the D register
+    pub fn set_d(&mut self, value: i16) {
+        self.d = value;
+    }
+
+    /// Sets the address of the next instruction
+    pub fn set_pc(&mut self, value: u16) {
+        self.pc = value & 0x7fff;
+    }
+
+    /// The whole RAM
+    pub fn ram(&self) -> &[i16] {
+        &self.ram
+    }
+
+    /// Reads a word of the RAM
+    pub fn peek(&self, address: usize) -> i16 {
+        self.ram[address % RAM_SIZE]
+    }
+
+    /// Writes a word of the RAM
+    pub fn poke(&mut self, address: usize, value: i16) {
+        self.ram[address % RAM_SIZE] = value;
+    }
+
+    /// Takes a snapshot of the screen memory map, as a binary PBM image of 512x256 pixels
+    pub fn screen_pbm(&self) -> Vec<u8> {
+        screen_pbm(&self.ram)
+    }
+
+    /// Drives the keyboard memory map by a script instead of a keyboard. Every event writes its key
+    /// to the keyboard memory map once the program has run the event's number of cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - The key events made by `key_script::parse`, in the order they happen
+    pub fn script_keyboard(&mut self, events: Vec<KeyEvent>) {
+        self.key_events = events.into();
+    }
+}
+
+/// Computes the output of the hack ALU
+///
+/// # Arguments
+///
+/// * `x` - The D register
+/// * `y` - The A register, or the memory word it points at
+/// * `control` - The bits zx, nx, zy, ny, f and no, zx being the highest
+pub fn alu(x: i16, y: i16, control: u16) -> i16 {
+    let bit = |index: u16| control & (1 << (5 - index)) != 0;
+    let x = if bit(0) { 0 } else { x };
+    let x = if bit(1) { !x } else { x };
+    let y = if bit(2) { 0 } else { y };
+    let y = if bit(3) { !y } else { y };
+    let out = if bit(4) { x.wrapping_add(y) } else { x & y };
+    if bit(5) { !out } else { out }
+}
+
+/// Compares the RAM with a compare file, whose columns are RAM words such as `RAM[256]`.
+/// A value of `*` matches anything. When the file has more than one row, the RAM is compared with the last.
+///
+/// # Arguments
+///
+/// * `ram` - The whole RAM of the hack computer
+/// * `cmp` - The contents of the compare file, as written by the nand2tetris test scripts
+///
+/// # Returns
+///
+/// * Nothing if every word matches, or a message listing the differences
+pub fn compare_ram(ram: &[i16], cmp: &str) -> Result<(), String> {
+    let mut rows = cmp.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.trim_matches('|').split('|').map(|cell| cell.trim().to_string()).collect::<Vec<_>>());
+    let header = rows.next().ok_or_else(|| "the compare file is empty".to_string())?;
+    let Some(values) = rows.next_back() else { return Err("the compare file has no values".to_string()); };
+    if values.len() != header.len() {
+        return Err(format!("the compare file has {} columns, but {} values", header.len(), values.len()));
+    }
+
+    let mut differences = Vec::new();
+    for (column, value) in header.iter().zip(values.iter()) {
+        let address = column.strip_prefix("RAM[").and_then(|rest| rest.strip_suffix(']'))
+            .and_then(|address| address.parse::<usize>().ok())
+            .filter(|address| *address < ram.len())
+            .ok_or_else(|| format!("'{}' is not a RAM word such as RAM[256]", column))?;
+        if value == "*" {
+            continue;
+        }
+        let expected: i16 = value.parse().map_err(|_| format!("'{}' is not a value for {}", value, column))?;
+        if ram[address] != expected {
+            differences.push(format!("{}: expected {}, found {}", column, expected, ram[address]));
+        }
+    }
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(differences.join("\n"))
+    }
+}
@@ -0,0 +1,127 @@
+/// This file contains the definite assignment analysis, which finds the local variables a subroutine
+/// may read before assigning them on every path that leads to the read
+use std::collections::HashSet;
+
+use crate::ast::{Expression, Name, Statement, SubroutineCall, SubroutineDec, Term};
+use crate::compilation_engine::always_ends;
+use crate::error::Warning;
+
+/// Walks the statements of a subroutine, following the locals assigned on every path so far
+struct DefiniteAssignment {
+    /// The names of the local variables of the subroutine
+    locals: HashSet<String>,
+    /// The locals already warned about, so every local is reported once
+    reported: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+/// Warns about every local variable of a subroutine that may be read before it is assigned
+///
+/// # Arguments
+///
+/// * `subroutine` - The subroutine to check
+///
+/// # Returns
+///
+/// * A warning at the first such read of every local
+pub fn check(subroutine: &SubroutineDec) -> Vec<Warning> {
+    let mut analysis = DefiniteAssignment {
+        locals: subroutine.var_decs.iter().flat_map(|var_dec| var_dec.names.iter().map(|name| name.name.to_string())).collect(),
+        reported: HashSet::new(),
+        warnings: Vec::new(),
+    };
+    analysis.statements(&subroutine.statements, HashSet::new());
+    analysis.warnings
+}
+
+impl DefiniteAssignment {
+    /// Checks a sequence of statements
+    ///
+    /// # Arguments
+    ///
+    /// * `assigned` - The locals assigned on every path before the statements
+    ///
+    /// # Returns
+    ///
+    /// * The locals assigned on every path after the statements
+    fn statements(&mut self, statements: &[Statement], mut assigned: HashSet<String>) -> HashSet<String> {
+        for statement in statements.iter() {
+            match statement {
+                Statement::Let { name, index, value, .. } => {
+                    if let Some(index) = index {
+                        self.expression(index, &assigned);
+                        self.read(name, &assigned);
+                    }
+                    self.expression(value, &assigned);
+                    if index.is_none() {
+                        assigned.insert(name.name.to_string());
+                    }
+                }
+                Statement::If { condition, statements, else_statements, .. } => {
+                    self.expression(condition, &assigned);
+                    let after_if = self.statements(statements, assigned.clone());
+                    let else_statements = else_statements.as_deref().unwrap_or_default();
+                    let after_else = self.statements(else_statements, assigned.clone());
+                    // a branch that returns doesn't lead past the if statement
+                    assigned = match (always_ends(statements), always_ends(else_statements)) {
+                        (true, _) => { after_else }
+                        (false, true) => { after_if }
+                        (false, false) => { after_if.intersection(&after_else).cloned().collect() }
+                    };
+                }
+                Statement::While { condition, statements, .. } => {
+                    // the body may not run at all
+                    self.expression(condition, &assigned);
+                    self.statements(statements, assigned.clone());
+                }
+                Statement::Do { call, .. } => { self.call(call, &assigned); }
+                Statement::Return { value, .. } => {
+                    if let Some(value) = value {
+                        self.expression(value, &assigned);
+                    }
+                }
+            }
+        }
+        assigned
+    }
+
+    /// Checks the reads of an expression
+    fn expression(&mut self, expression: &Expression, assigned: &HashSet<String>) {
+        self.term(&expression.term, assigned);
+        for (_, term) in expression.rest.iter() {
+            self.term(term, assigned);
+        }
+    }
+
+    /// Checks the reads of a term
+    fn term(&mut self, term: &Term, assigned: &HashSet<String>) {
+        match term {
+            Term::Variable(name) => { self.read(name, assigned); }
+            Term::ArrayEntry { name, index } => {
+                self.expression(index, assigned);
+                self.read(name, assigned);
+            }
+            Term::Call(call) => { self.call(call, assigned); }
+            Term::Parenthesized { expression, .. } => { self.expression(expression, assigned); }
+            Term::Unary { term, .. } => { self.term(term, assigned); }
+            Term::IntegerConstant { .. } | Term::StringConstant { .. } | Term::KeywordConstant { .. } => {}
+        }
+    }
+
+    /// Checks the reads of the arguments of a call, and of the object it is called on
+    fn call(&mut self, call: &SubroutineCall, assigned: &HashSet<String>) {
+        if let Some(receiver) = &call.receiver {
+            self.read(receiver, assigned);
+        }
+        for argument in call.arguments.iter() {
+            self.expression(argument, assigned);
+        }
+    }
+
+    /// Warns about a read of a local that isn't assigned on every path
+    fn read(&mut self, name: &Name, assigned: &HashSet<String>) {
+        if self.locals.contains(&name.name) && !assigned.contains(&name.name) && self.reported.insert(name.name.to_string()) {
+            self.warnings.push(Warning::new(name.span, format!("the local variable '{}' may be read before it is assigned", name.name)));
+        }
+    }
+}
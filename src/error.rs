@@ -0,0 +1,147 @@
+use std::fmt;
+
+/// A location in a jack file. Lines and columns are counted from 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+impl Span {
+    /// Constructor for Span. For cleaner code.
+    pub fn new(line: usize, column: usize, length: usize) -> Self {
+        Span {
+            line,
+            column,
+            length,
+        }
+    }
+}
+
+/// An error in a jack file, with the place in the code that caused it
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompileError {
+    pub span: Span,
+    pub message: String,
+    /// Other places of the code the message refers to, with what they are
+    pub notes: Vec<(Span, String)>,
+}
+
+impl CompileError {
+    /// Constructor for CompileError. For cleaner code.
+    pub fn new(span: Span, message: String) -> Self {
+        CompileError {
+            span,
+            message,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Adds another place of the code the message refers to, such as an earlier declaration
+    pub fn with_note(mut self, span: Span, note: String) -> Self {
+        self.notes.push((span, note));
+        self
+    }
+
+    /// Renders the error with the line of code it happened in, and a caret pointing at the mistake
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the jack file, as shown to the user
+    /// * `source` - The contents of the jack file
+    ///
+    /// # Returns
+    ///
+    /// * The error message, ready for printing
+    pub fn render(&self, path: &str, source: &str) -> String {
+        render("error", &self.message, self.span, &self.notes, path, source)
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.span.line, self.span.column, self.message)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl From<std::io::Error> for CompileError {
+    /// An error writing the compiled code, which has no place in the jack file
+    fn from(error: std::io::Error) -> Self {
+        CompileError::new(Span::default(), format!("could not write the vm code: {}", error))
+    }
+}
+
+/// Code in a jack file that compiles, but is probably a mistake
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    pub span: Span,
+    pub message: String,
+    /// Other places of the code the message refers to, with what they are
+    pub notes: Vec<(Span, String)>,
+}
+
+impl Warning {
+    /// Constructor for Warning. For cleaner code.
+    pub fn new(span: Span, message: String) -> Self {
+        Warning {
+            span,
+            message,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Adds another place of the code the message refers to, such as an earlier declaration
+    pub fn with_note(mut self, span: Span, note: String) -> Self {
+        self.notes.push((span, note));
+        self
+    }
+
+    /// Renders the warning the same way errors are rendered
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the jack file, as shown to the user
+    /// * `source` - The contents of the jack file
+    ///
+    /// # Returns
+    ///
+    /// * The warning message, ready for printing
+    pub fn render(&self, path: &str, source: &str) -> String {
+        render("warning", &self.message, self.span, &self.notes, path, source)
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.span.line, self.span.column, self.message)
+    }
+}
+
+/// Renders a message with the line of code it is about, and a caret pointing at the span,
+/// followed by the notes rendered the same way
+fn render(level: &str, message: &str, span: Span, notes: &[(Span, String)], path: &str, source: &str) -> String {
+    let mut rendered = render_snippet(level, message, span, path, source);
+    for (span, note) in notes.iter() {
+        rendered.push_str(&render_snippet("note", note, *span, path, source));
+    }
+    rendered
+}
+
+/// Renders a single message with the line of code it is about
+fn render_snippet(level: &str, message: &str, span: Span, path: &str, source: &str) -> String {
+    let line_number = span.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+    let mut rendered = format!("{}: {}\n{}--> {}:{}:{}\n", level, message, gutter, path, span.line, span.column);
+
+    if let Some(line) = source.lines().nth(span.line.wrapping_sub(1)) {
+        let line = line.replace('\t', " ");
+        let caret_count = span.length.max(1);
+        rendered.push_str(&format!("{} |\n", gutter));
+        rendered.push_str(&format!("{} | {}\n", line_number, line));
+        rendered.push_str(&format!("{} | {}{}\n", gutter, " ".repeat(span.column.saturating_sub(1)), "^".repeat(caret_count)));
+    }
+    rendered
+}
@@ -0,0 +1,596 @@
+/// This file contains the native implementation of the jack OS, which the vm interpreter calls
+/// for every OS function the program doesn't define in its own vm files
+use std::collections::HashMap;
+
+use crate::signatures::OS_API;
+use crate::vm::{HEAP_BASE, KEYBOARD, RAM_SIZE, SCREEN};
+
+/// The RAM address right after the heap, where the screen memory starts
+pub const HEAP_END: u16 = SCREEN as u16;
+/// The width and height of the screen in pixels
+pub const SCREEN_WIDTH: i16 = 512;
+pub const SCREEN_HEIGHT: i16 = 256;
+/// The number of text rows and columns of the Output class
+pub const OUTPUT_ROWS: i16 = 23;
+pub const OUTPUT_COLUMNS: i16 = 64;
+
+/// The key codes of the special characters
+pub const NEW_LINE: i16 = 128;
+pub const BACKSPACE: i16 = 129;
+pub const DOUBLE_QUOTE: i16 = 34;
+/// The number of steps Sys.wait lets pass for every millisecond it waits
+pub const STEPS_PER_MILLISECOND: u64 = 1;
+
+/// The number of arguments of a native OS function, methods counting `this`, or None if there is no native implementation.
+/// The functions are those of OS_API, but Sys.init, since the interpreter writes it in vm code, as it calls Main.main.
+fn argument_count(name: &str) -> Option<usize> {
+    if name == "Sys.init" {
+        return None;
+    }
+    OS_API.iter()
+        .find(|(_, _, function, _)| *function == name)
+        .map(|(kind, _, _, parameters)| parameters.len() + usize::from(*kind == "method"))
+}
+
+/// What a native OS function did
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Native {
+    /// The function is done, and returned the value
+    Return(i16),
+    /// The function waits for something, such as a key press, and must be called again on the next step
+    Wait,
+    /// The program asked to stop
+    Halt,
+}
+
+/// The state of the keyboard functions, which wait for keys over many steps
+#[derive(Clone, Debug, Default)]
+struct KeyboardState {
+    /// The key that is pressed, waiting to be released
+    pressed: Option<i16>,
+    /// The characters read so far by readLine, once its message is printed
+    line: Option<Vec<i16>>,
+}
+
+/// The state of the native OS, kept outside of the RAM
+#[derive(Clone, Debug)]
+pub struct JackOs {
+    /// The free blocks of the heap, as (address, size), sorted by address
+    free_blocks: Vec<(u16, u16)>,
+    /// The size of every allocated block, by its address
+    allocated: HashMap<u16, u16>,
+    /// Whether Screen draws in black
+    color: bool,
+    cursor_row: i16,
+    cursor_column: i16,
+    /// Everything printed by the Output class, as text
+    output: String,
+    /// The characters on every row of the screen, where the cursor put them
+    text: Vec<Vec<char>>,
+    keyboard: KeyboardState,
+    /// The steps Sys.wait still has to let pass, while it waits
+    waiting: Option<u64>,
+}
+
+impl Default for JackOs {
+    fn default() -> Self {
+        JackOs::new()
+    }
+}
+
+impl JackOs {
+    /// Creates the OS with an empty heap, a black pen and the cursor at the top left of the screen
+    pub fn new() -> Self {
+        JackOs {
+            free_blocks: vec![(HEAP_BASE, HEAP_END - HEAP_BASE)],
+            allocated: HashMap::new(),
+            color: true,
+            cursor_row: 0,
+            cursor_column: 0,
+            output: String::new(),
+            text: vec![vec![' '; OUTPUT_COLUMNS as usize]; OUTPUT_ROWS as usize],
+            keyboard: KeyboardState::default(),
+            waiting: None,
+        }
+    }
+
+    /// Whether there is a native implementation of a function
+    pub fn has_function(name: &str) -> bool {
+        argument_count(name).is_some()
+    }
+
+    /// Everything printed by the Output class so far
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// The text on the screen, one line per row, without the spaces at the end of the rows
+    /// and the empty rows at the bottom. Moving the cursor and printing over a character
+    /// replace it, as they do on the real screen.
+    pub fn text_screen(&self) -> String {
+        let mut rows: Vec<String> = self.text.iter().map(|row| row.iter().collect::<String>().trim_end().to_string()).collect();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        rows.iter().map(|row| format!("{}\n", row)).collect()
+    }
+
+    /// Calls a native OS function
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The full name of the function, such as Math.multiply
+    /// * `arguments` - The arguments of the call, `this` first for methods
+    /// * `ram` - The RAM of the interpreter
+    ///
+    /// # Returns
+    ///
+    /// * What the function did, or the message of the OS error it ran into
+    pub fn call(&mut self, name: &str, arguments: &[i16], ram: &mut [i16]) -> Result<Native, String> {
+        let Some(argument_count) = argument_count(name) else {
+            return Err(format!("the function {} is not defined", name));
+        };
+        if arguments.len() != argument_count {
+            return Err(format!("{} takes {} argument(s), but was called with {}", name, argument_count, arguments.len()));
+        }
+        let argument = |index: usize| arguments[index];
+
+        let value = match name {
+            "Array.new" => {
+                if argument(0) <= 0 {
+                    return Err(os_error(2));
+                }
+                self.alloc(argument(0))?
+            }
+            "Array.dispose" | "Memory.deAlloc" | "String.dispose" => {
+                self.de_alloc(argument(0));
+                0
+            }
+            "Keyboard.init" | "Math.init" | "Memory.init" | "Output.init" | "Screen.init" => { 0 }
+            "Keyboard.keyPressed" => { ram[KEYBOARD] }
+            "Keyboard.readChar" => {
+                match self.read_char(ram) {
+                    Some(key) => { key }
+                    None => { return Ok(Native::Wait); }
+                }
+            }
+            "Keyboard.readLine" | "Keyboard.readInt" => {
+                match self.read_line(argument(0), ram)? {
+                    Some(line) if name == "Keyboard.readInt" => {
+                        let value = int_value(&string_chars(line, ram));
+                        self.de_alloc(line);
+                        value
+                    }
+                    Some(line) => { line }
+                    None => { return Ok(Native::Wait); }
+                }
+            }
+            "Math.abs" => { argument(0).wrapping_abs() }
+            "Math.multiply" => { argument(0).wrapping_mul(argument(1)) }
+            "Math.divide" => {
+                if argument(1) == 0 {
+                    return Err(os_error(3));
+                }
+                argument(0).wrapping_div(argument(1))
+            }
+            "Math.min" => { argument(0).min(argument(1)) }
+            "Math.max" => { argument(0).max(argument(1)) }
+            "Math.sqrt" => {
+                if argument(0) < 0 {
+                    return Err(os_error(4));
+                }
+                (argument(0) as f64).sqrt() as i16
+            }
+            "Memory.peek" => { ram[address(argument(0))] }
+            "Memory.poke" => {
+                ram[address(argument(0))] = argument(1);
+                0
+            }
+            "Memory.alloc" => {
+                if argument(0) <= 0 {
+                    return Err(os_error(5));
+                }
+                self.alloc(argument(0))?
+            }
+            "Output.moveCursor" => {
+                if !(0..OUTPUT_ROWS).contains(&argument(0)) || !(0..OUTPUT_COLUMNS).contains(&argument(1)) {
+                    return Err(os_error(20));
+                }
+                // Text printed somewhere else on the screen starts a new line of the transcript
+                let moved = (self.cursor_row, self.cursor_column) != (argument(0), argument(1));
+                if moved && !self.output.is_empty() && !self.output.ends_with('\n') {
+                    self.output.push('\n');
+                }
+                self.cursor_row = argument(0);
+                self.cursor_column = argument(1);
+                0
+            }
+            "Output.printChar" => {
+                self.print_char(argument(0));
+                0
+            }
+            "Output.printString" => {
+                for ch in string_chars(argument(0), ram) {
+                    self.print_char(ch);
+                }
+                0
+            }
+            "Output.printInt" => {
+                for ch in argument(0).to_string().chars() {
+                    self.print_char(ch as i16);
+                }
+                0
+            }
+            "Output.println" => {
+                self.println();
+                0
+            }
+            "Output.backSpace" => {
+                self.back_space();
+                0
+            }
+            "Screen.clearScreen" => {
+                ram[SCREEN..KEYBOARD].fill(0);
+                for row in self.text.iter_mut() {
+                    row.fill(' ');
+                }
+                0
+            }
+            "Screen.setColor" => {
+                self.color = argument(0) != 0;
+                0
+            }
+            "Screen.drawPixel" => {
+                if !on_screen(argument(0), argument(1)) {
+                    return Err(os_error(7));
+                }
+                self.draw_pixel(argument(0), argument(1), ram);
+                0
+            }
+            "Screen.drawLine" => {
+                if !on_screen(argument(0), argument(1)) || !on_screen(argument(2), argument(3)) {
+                    return Err(os_error(8));
+                }
+                self.draw_line(argument(0), argument(1), argument(2), argument(3), ram);
+                0
+            }
+            "Screen.drawRectangle" => {
+                let (x1, y1, x2, y2) = (argument(0), argument(1), argument(2), argument(3));
+                if !on_screen(x1, y1) || !on_screen(x2, y2) || x1 > x2 || y1 > y2 {
+                    return Err(os_error(9));
+                }
+                for y in y1..=y2 {
+                    self.draw_line(x1, y, x2, y, ram);
+                }
+                0
+            }
+            "Screen.drawCircle" => {
+                let (x, y, r) = (argument(0), argument(1), argument(2));
+                if !on_screen(x, y) {
+                    return Err(os_error(12));
+                }
+                if !(0..=181).contains(&r) || !on_screen(x - r, y - r) || !on_screen(x + r, y + r) {
+                    return Err(os_error(13));
+                }
+                for dy in -r..=r {
+                    let dx = ((r as i32 * r as i32 - dy as i32 * dy as i32) as f64).sqrt() as i16;
+                    self.draw_line(x - dx, y + dy, x + dx, y + dy, ram);
+                }
+                0
+            }
+            "String.new" => {
+                if argument(0) < 0 {
+                    return Err(os_error(14));
+                }
+                let string = self.alloc(argument(0).saturating_add(2))?;
+                ram[address(string)] = argument(0);
+                ram[address(string) + 1] = 0;
+                string
+            }
+            "String.length" => { ram[string_address(argument(0), ram)? + 1] }
+            "String.charAt" => {
+                let (string, index) = (string_address(argument(0), ram)?, argument(1));
+                if index < 0 || index >= ram[string + 1].min(ram[string]) {
+                    return Err(os_error(15));
+                }
+                ram[string + 2 + index as usize]
+            }
+            "String.setCharAt" => {
+                let (string, index) = (string_address(argument(0), ram)?, argument(1));
+                if index < 0 || index >= ram[string + 1].min(ram[string]) {
+                    return Err(os_error(16));
+                }
+                ram[string + 2 + index as usize] = argument(2);
+                0
+            }
+            "String.appendChar" => {
+                let string = string_address(argument(0), ram)?;
+                let length = ram[string + 1].max(0);
+                if length >= ram[string] {
+                    return Err(os_error(17));
+                }
+                ram[string + 2 + length as usize] = argument(1);
+                ram[string + 1] = length + 1;
+                argument(0)
+            }
+            "String.eraseLastChar" => {
+                let string = string_address(argument(0), ram)?;
+                if ram[string + 1] <= 0 {
+                    return Err(os_error(18));
+                }
+                ram[string + 1] -= 1;
+                0
+            }
+            "String.intValue" => { int_value(&string_chars(argument(0), ram)) }
+            "String.setInt" => {
+                let string = string_address(argument(0), ram)?;
+                let digits: Vec<i16> = argument(1).to_string().chars().map(|ch| ch as i16).collect();
+                if digits.len() as i16 > ram[string] {
+                    return Err(os_error(19));
+                }
+                ram[string + 2..string + 2 + digits.len()].copy_from_slice(&digits);
+                ram[string + 1] = digits.len() as i16;
+                0
+            }
+            "String.backSpace" => { BACKSPACE }
+            "String.doubleQuote" => { DOUBLE_QUOTE }
+            "String.newLine" => { NEW_LINE }
+            "Sys.halt" => { return Ok(Native::Halt); }
+            "Sys.error" => { return Err(format!("the program called Sys.error({})", argument(0))); }
+            "Sys.wait" => {
+                if argument(0) < 0 {
+                    return Err(os_error(1));
+                }
+                let waiting = self.waiting.get_or_insert(argument(0) as u64 * STEPS_PER_MILLISECOND);
+                if *waiting > 0 {
+                    *waiting -= 1;
+                    return Ok(Native::Wait);
+                }
+                self.waiting = None;
+                0
+            }
+            _ => { return Err(format!("the function {} is not defined", name)); }
+        };
+        Ok(Native::Return(value))
+    }
+
+    /// Allocates a block of the heap, first fit
+    fn alloc(&mut self, size: i16) -> Result<i16, String> {
+        let size = size as u16;
+        let Some(position) = self.free_blocks.iter().position(|(_, free)| *free >= size) else {
+            return Err(os_error(6));
+        };
+        let (block, free) = self.free_blocks[position];
+        if free == size {
+            self.free_blocks.remove(position);
+        } else {
+            self.free_blocks[position] = (block + size, free - size);
+        }
+        self.allocated.insert(block, size);
+        Ok(block as i16)
+    }
+
+    /// Returns an allocated block to the heap, merging it with the free blocks around it.
+    /// Addresses that weren't allocated are ignored.
+    fn de_alloc(&mut self, block: i16) {
+        let block = block as u16;
+        let Some(size) = self.allocated.remove(&block) else { return; };
+        let position = self.free_blocks.partition_point(|(free, _)| *free < block);
+        self.free_blocks.insert(position, (block, size));
+        if position + 1 < self.free_blocks.len() && block + size == self.free_blocks[position + 1].0 {
+            self.free_blocks[position].1 += self.free_blocks.remove(position + 1).1;
+        }
+        if position > 0 && self.free_blocks[position - 1].0 + self.free_blocks[position - 1].1 == block {
+            self.free_blocks[position - 1].1 += self.free_blocks.remove(position).1;
+        }
+    }
+
+    /// Prints a character at the cursor and moves the cursor forward.
+    /// New line and backspace move the cursor instead.
+    fn print_char(&mut self, ch: i16) {
+        match ch {
+            NEW_LINE => { self.println(); }
+            BACKSPACE => { self.back_space(); }
+            _ => {
+                let ch = char::from_u32(ch as u16 as u32).unwrap_or('?');
+                self.output.push(ch);
+                self.text[self.cursor_row as usize][self.cursor_column as usize] = ch;
+                self.cursor_column += 1;
+                if self.cursor_column == OUTPUT_COLUMNS {
+                    self.println();
+                }
+            }
+        }
+    }
+
+    /// Moves the cursor to the start of the next line, back to the top after the last line
+    fn println(&mut self) {
+        self.output.push('\n');
+        self.cursor_column = 0;
+        self.cursor_row = (self.cursor_row + 1) % OUTPUT_ROWS;
+    }
+
+    /// Moves the cursor one column back, erasing the last printed character
+    fn back_space(&mut self) {
+        if !self.output.ends_with('\n') {
+            self.output.pop();
+        }
+        if self.cursor_column > 0 {
+            self.cursor_column -= 1;
+        } else if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+            self.cursor_column = OUTPUT_COLUMNS - 1;
+        }
+        self.text[self.cursor_row as usize][self.cursor_column as usize] = ' ';
+    }
+
+    /// Waits for a key to be pressed and released, and prints it
+    ///
+    /// # Returns
+    ///
+    /// * The key, or None while still waiting
+    fn read_char(&mut self, ram: &[i16]) -> Option<i16> {
+        let key = self.read_key(ram)?;
+        self.print_char(key);
+        Some(key)
+    }
+
+    /// Waits for a key to be pressed and released, without printing it
+    ///
+    /// # Returns
+    ///
+    /// * The key, or None while still waiting
+    fn read_key(&mut self, ram: &[i16]) -> Option<i16> {
+        let key = ram[KEYBOARD];
+        match self.keyboard.pressed {
+            Some(pressed) if key == 0 => {
+                self.keyboard.pressed = None;
+                Some(pressed)
+            }
+            Some(_) => { None }
+            None => {
+                if key != 0 {
+                    self.keyboard.pressed = Some(key);
+                }
+                None
+            }
+        }
+    }
+
+    /// Prints the message, and reads characters until a new line, handling backspace.
+    /// A backspace is only printed when it erases a character of the line, so it never erases the message.
+    ///
+    /// # Returns
+    ///
+    /// * A new string of the characters read, or None while still waiting
+    fn read_line(&mut self, message: i16, ram: &mut [i16]) -> Result<Option<i16>, String> {
+        if self.keyboard.line.is_none() {
+            for ch in string_chars(message, ram) {
+                self.print_char(ch);
+            }
+            self.keyboard.line = Some(Vec::new());
+        }
+        let Some(key) = self.read_key(ram) else { return Ok(None); };
+        let line = self.keyboard.line.get_or_insert_with(Vec::new);
+        match key {
+            NEW_LINE => { self.print_char(key); }
+            BACKSPACE => {
+                if line.pop().is_some() {
+                    self.print_char(key);
+                }
+                return Ok(None);
+            }
+            _ => {
+                line.push(key);
+                self.print_char(key);
+                return Ok(None);
+            }
+        }
+
+        let line = self.keyboard.line.take().unwrap_or_default();
+        let string = self.alloc(line.len() as i16 + 2)?;
+        let string_address = address(string);
+        ram[string_address] = line.len() as i16;
+        ram[string_address + 1] = line.len() as i16;
+        ram[string_address + 2..string_address + 2 + line.len()].copy_from_slice(&line);
+        Ok(Some(string))
+    }
+
+    /// Paints a single pixel in the current color
+    fn draw_pixel(&self, x: i16, y: i16, ram: &mut [i16]) {
+        let word = SCREEN + y as usize * 32 + x as usize / 16;
+        let bit = 1i16 << (x % 16);
+        if self.color {
+            ram[word] |= bit;
+        } else {
+            ram[word] &= !bit;
+        }
+    }
+
+    /// Draws a line between two points on the screen, including both ends
+    fn draw_line(&self, x1: i16, y1: i16, x2: i16, y2: i16, ram: &mut [i16]) {
+        let (dx, dy) = ((x2 - x1).abs(), -(y2 - y1).abs());
+        let (step_x, step_y) = (if x1 < x2 { 1 } else { -1 }, if y1 < y2 { 1 } else { -1 });
+        let (mut x, mut y, mut error) = (x1, y1, dx + dy);
+        loop {
+            self.draw_pixel(x, y, ram);
+            if x == x2 && y == y2 {
+                break;
+            }
+            if 2 * error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if 2 * error <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+}
+
+/// The message of an OS error, with its code as the real OS reports it with Sys.error
+pub fn os_error(code: i16) -> String {
+    let description = match code {
+        1 => { "Sys.wait: duration must be positive" }
+        2 => { "Array.new: array size must be positive" }
+        3 => { "Math.divide: division by zero" }
+        4 => { "Math.sqrt: cannot compute square root of a negative number" }
+        5 => { "Memory.alloc: allocated memory size must be positive" }
+        6 => { "Memory.alloc: heap overflow" }
+        7 => { "Screen.drawPixel: illegal pixel coordinates" }
+        8 => { "Screen.drawLine: illegal line coordinates" }
+        9 => { "Screen.drawRectangle: illegal rectangle coordinates" }
+        12 => { "Screen.drawCircle: illegal center coordinates" }
+        13 => { "Screen.drawCircle: illegal radius" }
+        14 => { "String.new: maximum length must be non-negative" }
+        15 => { "String.charAt: string index out of bounds" }
+        16 => { "String.setCharAt: string index out of bounds" }
+        17 => { "String.appendChar: string is full" }
+        18 => { "String.eraseLastChar: string is empty" }
+        19 => { "String.setInt: insufficient string capacity" }
+        20 => { "Output.moveCursor: illegal cursor location" }
+        _ => { "unknown error" }
+    };
+    format!("OS error {}: {}", code, description)
+}
+
+/// Whether a point is on the screen
+fn on_screen(x: i16, y: i16) -> bool {
+    (0..SCREEN_WIDTH).contains(&x) && (0..SCREEN_HEIGHT).contains(&y)
+}
+
+/// Turns a value into a RAM address
+fn address(value: i16) -> usize {
+    value as u16 as usize % RAM_SIZE
+}
+
+/// The RAM address of a string object, which holds its maximum length, its length, and then its characters
+fn string_address(string: i16, ram: &[i16]) -> Result<usize, String> {
+    let string = address(string);
+    if string + 2 > RAM_SIZE || string + 2 + ram[string].max(0) as usize > RAM_SIZE {
+        return Err(format!("{} is not the address of a string", string));
+    }
+    Ok(string)
+}
+
+/// The characters of a string object
+fn string_chars(string: i16, ram: &[i16]) -> Vec<i16> {
+    let Ok(string) = string_address(string, ram) else { return Vec::new(); };
+    let length = ram[string + 1].clamp(0, ram[string].max(0)) as usize;
+    ram[string + 2..string + 2 + length].to_vec()
+}
+
+/// The integer value of a string of digits, which may start with a minus sign.
+/// Reading stops at the first character that isn't a digit.
+fn int_value(chars: &[i16]) -> i16 {
+    let negative = chars.first() == Some(&('-' as i16));
+    let mut value: i16 = 0;
+    for &ch in chars.iter().skip(negative as usize) {
+        if !(('0' as i16)..=('9' as i16)).contains(&ch) {
+            break;
+        }
+        value = value.wrapping_mul(10).wrapping_add(ch - '0' as i16);
+    }
+    if negative { value.wrapping_neg() } else { value }
+}
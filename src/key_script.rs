@@ -0,0 +1,171 @@
+/// This file contains the parser of key scripts, which press keys on the keyboard of the vm interpreter
+/// at given steps, so programs that read the keyboard can run without anyone at the keyboard.
+///
+/// A key script has a command on every line, and comments starting with `//`:
+///
+/// * `wait N` - Lets the program run N more steps before the next event
+/// * `at N` - Lets the program run until it made N steps in total
+/// * `press KEY` - Holds a key down, until it's released or another key is pressed
+/// * `release` - Lets go of the key
+/// * `type "TEXT"` - Presses and releases every character of the text in turn, `\n` being the enter key
+/// * `pace N` - The number of steps every typed key is held down, and then released, for
+///
+/// A key is a single character, the name of a special key such as `ENTER`, `LEFT` or `F1`, or its key code.
+///
+/// Every vm command is a step, and Sys.wait lets `jack_os::STEPS_PER_MILLISECOND` steps pass for every
+/// millisecond it waits, so a key typed at the default pace is held down for as long as `Sys.wait(1000)` takes.
+use crate::error::{CompileError, Span};
+use crate::jack_os::{BACKSPACE, NEW_LINE};
+
+/// The number of steps a typed key is held down and released for, unless the script sets its pace
+pub const DEFAULT_PACE: u64 = 1000;
+
+/// The key codes of the keys that aren't a character
+pub static KEY_NAMES: [(&str, i16); 28] = [
+    ("SPACE", 32), ("ENTER", NEW_LINE), ("NEWLINE", NEW_LINE), ("BACKSPACE", BACKSPACE),
+    ("LEFT", 130), ("UP", 131), ("RIGHT", 132), ("DOWN", 133), ("HOME", 134), ("END", 135),
+    ("PAGEUP", 136), ("PAGEDOWN", 137), ("INSERT", 138), ("DELETE", 139), ("ESC", 140),
+    ("F1", 141), ("F2", 142), ("F3", 143), ("F4", 144), ("F5", 145), ("F6", 146),
+    ("F7", 147), ("F8", 148), ("F9", 149), ("F10", 150), ("F11", 151), ("F12", 152),
+    ("DOUBLEQUOTE", 34),
+];
+
+/// A change of the keyboard memory map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyEvent {
+    /// The number of steps the program has made when the key changes
+    pub step: u64,
+    /// The key code of the pressed key, 0 when it's released
+    pub key: i16,
+}
+
+/// Parses a key script into the events it makes, in the order they happen
+///
+/// # Arguments
+///
+/// * `source` - The contents of the key script
+///
+/// # Returns
+///
+/// * The key events, or the first error found
+pub fn parse(source: &str) -> Result<Vec<KeyEvent>, CompileError> {
+    let mut events = Vec::new();
+    let mut step: u64 = 0;
+    let mut pace = DEFAULT_PACE;
+    for (line_index, line) in source.lines().enumerate() {
+        let code = strip_comment(line).trim_end();
+        let column = code.len() - code.trim_start().len() + 1;
+        let code = code.trim_start();
+        if code.is_empty() {
+            continue;
+        }
+        let span = Span::new(line_index + 1, column, code.chars().count());
+        let (command, argument) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+        let argument = argument.trim();
+        match command {
+            "wait" => { step = add_steps(step, parse_steps(argument, span)?, span)?; }
+            "at" => {
+                let at = parse_steps(argument, span)?;
+                if at < step {
+                    return Err(CompileError::new(span, format!("step {} is before step {}, which the script already reached", at, step)));
+                }
+                step = at;
+            }
+            "pace" => {
+                pace = parse_steps(argument, span)?;
+                if pace == 0 {
+                    return Err(CompileError::new(span, "the pace must be at least 1 step".to_string()));
+                }
+            }
+            "press" => { events.push(KeyEvent { step, key: parse_key(argument, span)? }); }
+            "release" => {
+                if !argument.is_empty() {
+                    return Err(CompileError::new(span, format!("unexpected '{}' after release", argument)));
+                }
+                events.push(KeyEvent { step, key: 0 });
+            }
+            "type" => {
+                for key in parse_text(argument, span)? {
+                    events.push(KeyEvent { step, key });
+                    step = add_steps(step, pace, span)?;
+                    events.push(KeyEvent { step, key: 0 });
+                    step = add_steps(step, pace, span)?;
+                }
+            }
+            _ => { return Err(CompileError::new(span, format!("unknown key script command '{}'", command))); }
+        }
+    }
+    Ok(events)
+}
+
+/// Removes the `//` comment of a line, unless the `//` is inside the quoted text of a type command
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, ch) in line.char_indices() {
+        match ch {
+            _ if escaped => { escaped = false; }
+            '\\' if quoted => { escaped = true; }
+            '"' => { quoted = !quoted; }
+            '/' if !quoted && line[index + 1..].starts_with('/') => { return &line[..index]; }
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Parses a number of steps
+fn parse_steps(argument: &str, span: Span) -> Result<u64, CompileError> {
+    argument.parse().map_err(|_| CompileError::new(span, format!("expected a number of steps, found '{}'", argument)))
+}
+
+/// Adds steps to the step the script reached, failing if the sum doesn't fit a number of steps
+fn add_steps(step: u64, steps: u64, span: Span) -> Result<u64, CompileError> {
+    step.checked_add(steps).ok_or_else(|| CompileError::new(span, format!("the script goes past the last step, {}", u64::MAX)))
+}
+
+/// Parses a key, written as a character, the name of a special key, or a key code
+fn parse_key(argument: &str, span: Span) -> Result<i16, CompileError> {
+    let mut chars = argument.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        if (' '..='~').contains(&ch) {
+            return Ok(ch as i16);
+        }
+    }
+    if let Some(&(_, key)) = KEY_NAMES.iter().find(|(name, _)| name.eq_ignore_ascii_case(argument)) {
+        return Ok(key);
+    }
+    match argument.parse::<i16>() {
+        Ok(key) if key > 0 => { Ok(key) }
+        _ => { Err(CompileError::new(span, format!("expected a key, found '{}'", argument))) }
+    }
+}
+
+/// Parses the quoted text of a type command into the key codes of its characters
+fn parse_text(argument: &str, span: Span) -> Result<Vec<i16>, CompileError> {
+    let Some(text) = argument.strip_prefix('"').and_then(|text| text.strip_suffix('"')).filter(|_| argument.len() > 1) else {
+        return Err(CompileError::new(span, "expected the text to type in double quotes".to_string()));
+    };
+    let mut keys = Vec::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        let key = match ch {
+            '\\' => {
+                match chars.next() {
+                    Some('n') => { NEW_LINE }
+                    Some('b') => { BACKSPACE }
+                    Some('"') => { '"' as i16 }
+                    Some('\\') => { '\\' as i16 }
+                    other => {
+                        let escape: String = other.into_iter().collect();
+                        return Err(CompileError::new(span, format!("unknown escape '\\{}' in the text", escape)));
+                    }
+                }
+            }
+            ' '..='~' => { ch as i16 }
+            _ => { return Err(CompileError::new(span, format!("the character '{}' is not on the keyboard", ch))); }
+        };
+        keys.push(key);
+    }
+    Ok(keys)
+}
@@ -0,0 +1,24 @@
+extern crate lazy_static;
+extern crate regex;
+
+pub mod ast;
+pub mod error;
+pub mod xmlwriter;
+pub mod tokenizer;
+pub mod parser;
+pub mod parse_tree_writer;
+pub mod compilation_engine;
+pub mod definite_assignment;
+pub mod vm_writer;
+pub mod vm_command;
+pub mod vm2asm;
+pub mod assembler;
+pub mod cpu;
+pub mod vm;
+pub mod jack_os;
+pub mod key_script;
+pub mod test_script;
+pub mod signatures;
+pub mod symbol_table;
+pub mod type_checker;
+pub mod utility;
@@ -1,69 +1,566 @@
-extern crate core;
-extern crate lazy_static;
-extern crate regex;
+use std::{env, fs, process};
+use std::path::{Path, PathBuf};
 
-use std::{env, fs};
-use std::env::var;
+use jack2vm::assembler::{assemble, to_hack_text};
+use jack2vm::ast::Class;
+use jack2vm::compilation_engine::CompilationEngine;
+use jack2vm::cpu::{self, compare_ram, Cpu};
+use jack2vm::key_script::{self, KeyEvent};
+use jack2vm::parse_tree_writer::ParseTreeWriter;
+use jack2vm::parser::Parser;
+use jack2vm::signatures::Signatures;
+use jack2vm::symbol_table;
+use jack2vm::test_script;
+use jack2vm::tokenizer::Tokenizer;
+use jack2vm::vm::{ARG, LCL, SP, Stop, THAT, THIS, Vm};
+use jack2vm::vm2asm::{check_program, translate_program};
+use jack2vm::vm_command;
+use jack2vm::vm_writer::VMWriter;
+use jack2vm::xmlwriter::XmlWriter;
 
-use compilation_engine::CompilationEngine;
+use crate::cli::{Emit, Options, RUN_USAGE, RunOptions, TEST_USAGE, TestOptions, USAGE, Verbosity};
 
-use crate::tokenizer::tokenizer;
+mod cli;
 
-mod xmlwriter;
-mod tokenizer;
-mod compilation_engine;
-mod vm_writer;
-mod symbol_table;
-mod utility;
+/// A jack file to compile, along with the directory its output files are written to
+struct Job {
+    source: PathBuf,
+    out_dir: PathBuf,
+    program: usize,
+}
+
+/// The classes that are translated together into a single assembly file
+struct Program {
+    asm_path: PathBuf,
+    /// The directory of the jack files, where already compiled .vm files (such as the OS) are also taken from
+    library_dir: Option<PathBuf>,
+    /// The name and vm code of every compiled class
+    classes: Vec<(String, String)>,
+    failed: bool,
+}
 
 fn main() {
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "run") {
+        args.next();
+        run(args);
+        return;
+    }
+    if args.peek().is_some_and(|arg| arg == "test") {
+        args.next();
+        test(args);
+        return;
+    }
 
-    //let args: Vec<String> = env::args().collect();
+    let options = match Options::parse(args) {
+        Ok(options) => { options }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let (jobs, mut programs) = match collect_jobs(&options) {
+        Ok(collected) => { collected }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            process::exit(1);
+        }
+    };
+
+    // the first pass parses every class, so calls to the other classes of a program can be checked
+    let mut failed = 0;
+    let mut parsed_jobs = Vec::new();
+    for job in jobs {
+        if options.verbosity >= Verbosity::Normal {
+            println!("Compiling {}", job.source.display());
+        }
+        match parse_job(&job, &options) {
+            Ok(parsed) => { parsed_jobs.push((job, parsed)); }
+            Err(message) => {
+                eprintln!("{}", message);
+                programs[job.program].failed = true;
+                failed += 1;
+            }
+        }
+    }
 
+    let mut signatures: Vec<Signatures> = Vec::new();
+    for (index, program) in programs.iter().enumerate() {
+        let mut program_signatures = Signatures::new();
+        for (_, parsed) in parsed_jobs.iter().filter(|(job, _)| job.program == index) {
+            program_signatures.add_class(&parsed.class);
+        }
+        // only a whole directory is a whole program, along with the .vm files next to its classes
+        if let Some(library_dir) = &program.library_dir {
+            for path in search_files(library_dir, "vm", false).unwrap_or_default() {
+                program_signatures.add_opaque_class(&path.file_stem().unwrap_or_default().to_string_lossy());
+            }
+            program_signatures.set_complete(!program.failed);
+        }
+        signatures.push(program_signatures);
+    }
 
-    let mut file_path:Vec<String> = Vec::new();
-    //let temp = args[1].to_string();
-    let compile_all = false;
+    for (job, parsed) in parsed_jobs {
+        match compile_job(&job, parsed, &signatures[job.program], &options) {
+            Ok(Some(vm_code)) => {
+                let stem = job.source.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                programs[job.program].classes.push((stem, vm_code));
+            }
+            Ok(None) => {}
+            Err(message) => {
+                eprintln!("{}", message);
+                programs[job.program].failed = true;
+                failed += 1;
+            }
+        }
+    }
 
-    if compile_all {
-        file_path.append(&mut search_jack_files("/home/shoham/nand2tetris/projects/11/Seven/"));
-        file_path.append(&mut search_jack_files("/home/shoham/nand2tetris/projects/11/Average/"));
-        file_path.append(&mut search_jack_files("/home/shoham/nand2tetris/projects/11/ConvertToBin/"));
-        file_path.append(&mut search_jack_files("/home/shoham/nand2tetris/projects/11/ComplexArrays/"));
-        file_path.append(&mut search_jack_files("/home/shoham/nand2tetris/projects/11/Square/"));
-        file_path.append(&mut search_jack_files("/home/shoham/nand2tetris/projects/11/Circle/"));
-        file_path.append(&mut search_jack_files("/home/shoham/nand2tetris/projects/11/Pong/"));
+    if options.emit.contains(&Emit::Asm) || options.emit.contains(&Emit::Hack) {
+        for program in programs.iter().filter(|program| !program.failed) {
+            if let Err(message) = write_program(program, &options) {
+                eprintln!("{}", message);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!("error: could not compile {} file(s)", failed);
+        process::exit(1);
+    }
+}
 
-        for file in file_path {
-            println!("file : {}", file);
-            //tokenizer(file.to_string());
-            let mut compilation_engine: CompilationEngine = CompilationEngine::new(&file);
-            compilation_engine.compile();
+/// The run command: runs a compiled program in the vm interpreter, or a .hack or .asm file
+/// in the CPU emulator, and prints its final state
+fn run<I: Iterator<Item=String>>(args: I) {
+    let options = match RunOptions::parse(args) {
+        Ok(options) => { options }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, RUN_USAGE);
+            process::exit(2);
         }
+    };
+    if options.help {
+        println!("{}", RUN_USAGE);
+        return;
+    }
+
+    let path = Path::new(&options.input);
+    let failed = if path.extension().is_some_and(|extension| extension == "hack" || extension == "asm") {
+        run_cpu(path, &options)
     } else {
-        let temp ="/home/shoham/Main.jack".to_string();
+        run_vm(path, &options)
+    };
+    if failed {
+        process::exit(1);
+    }
+}
 
-        if !temp.contains(".jack") { file_path = search_jack_files(temp.as_str()); } else {
-            file_path = Vec::new();
-            file_path.push(temp.to_string())
+/// The test command: runs nand2tetris test scripts and compares their output with their compare files
+fn test<I: Iterator<Item=String>>(args: I) {
+    let options = match TestOptions::parse(args) {
+        Ok(options) => { options }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, TEST_USAGE);
+            process::exit(2);
         }
-        for file in file_path {
-            println!("file : {}", file);
-            tokenizer(file.to_string());
-            let mut compilation_engine: CompilationEngine = CompilationEngine::new(&file);
-            compilation_engine.compile();
+    };
+    if options.help {
+        println!("{}", TEST_USAGE);
+        return;
+    }
+
+    let mut failed = 0;
+    for script in options.scripts.iter() {
+        match test_script::run_file(Path::new(script), options.max_steps) {
+            Ok(report) => {
+                for echo in report.echoes.iter() {
+                    println!("{}", echo);
+                }
+                if report.compared {
+                    println!("{}: comparison ended successfully", script);
+                } else {
+                    println!("{}: end of script, {} line(s) written", script, report.output.len());
+                }
+            }
+            Err(message) => {
+                eprint!("{}", message);
+                failed += 1;
+            }
         }
     }
+    if failed > 0 {
+        eprintln!("error: {} of {} test script(s) failed", failed, options.scripts.len());
+        process::exit(1);
+    }
 }
 
+/// Runs a program in the vm interpreter
+///
+/// # Returns
+///
+/// * Whether the program ran into a runtime error, or its RAM doesn't match the compare file
+fn run_vm(path: &Path, options: &RunOptions) -> bool {
+    let mut vm = match Vm::load(path) {
+        Ok(vm) => { vm }
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+    };
+    let function = match &options.function {
+        Some(function) => { function.to_string() }
+        None if vm.has_function("Sys.init") => { "Sys.init".to_string() }
+        None => {
+            eprintln!("error: Sys.init is not defined, choose the function to start from with --function");
+            process::exit(1);
+        }
+    };
+    if let Some(path) = &options.key_script {
+        vm.script_keyboard(read_key_script(path));
+    }
 
-fn search_jack_files(file_path: &str) -> Vec<String> {
-    let mut jack_files: Vec<String> = Vec::new();
-    let paths = fs::read_dir(file_path).unwrap();
-    for path in paths {
-        if path.as_ref().unwrap().path().display().to_string().contains(".jack") {
-            jack_files.push(path.unwrap().path().display().to_string())
+    let result = vm.start(&function).and_then(|_| vm.run(options.max_steps));
+    match &result {
+        Ok(Stop::Returned) => { println!("{} returned after {} steps", function, vm.steps()); }
+        Ok(Stop::Halted) => { println!("halted after {} steps", vm.steps()); }
+        Ok(Stop::StepLimit) => {
+            println!("stopped after {} steps, in {}", vm.steps(), vm.current_function().unwrap_or("?"));
         }
+        Err(message) => { eprintln!("{}", message); }
     }
-    jack_files
-}
\ No newline at end of file
+    print_state(vm.ram(), options);
+    println!("stack: {:?}", vm.stack());
+
+    write_run_files(&[
+        (&options.output_file, vm.output().as_bytes().to_vec()),
+        (&options.text_screen_file, vm.text_screen().into_bytes()),
+        (&options.screen_file, vm.screen_pbm()),
+    ]);
+    let compared = compare(vm.ram(), options);
+    result.is_err() || !compared
+}
+
+/// Runs a .hack or .asm program in the CPU emulator
+///
+/// # Returns
+///
+/// * Whether the program ran into a runtime error, or its RAM doesn't match the compare file
+fn run_cpu(path: &Path, options: &RunOptions) -> bool {
+    if options.function.is_some() || options.output_file.is_some() || options.text_screen_file.is_some() {
+        eprintln!("error: --function, --output and --text-screen only work with vm programs");
+        process::exit(2);
+    }
+    let mut cpu = match Cpu::load(path) {
+        Ok(cpu) => { cpu }
+        Err(message) => {
+            eprint!("{}", message);
+            process::exit(1);
+        }
+    };
+    if let Some(path) = &options.key_script {
+        cpu.script_keyboard(read_key_script(path));
+    }
+
+    let result = cpu.run(options.max_steps);
+    match &result {
+        Ok(cpu::Stop::EndLoop) => { println!("reached the end loop after {} cycles", cpu.cycles()); }
+        Ok(cpu::Stop::EndOfProgram) => { println!("ran past the end of the program after {} cycles", cpu.cycles()); }
+        Ok(cpu::Stop::CycleLimit) => { println!("stopped after {} cycles", cpu.cycles()); }
+        Err(message) => { eprintln!("{}", message); }
+    }
+    println!("A={} D={} PC={}", cpu.a(), cpu.d(), cpu.pc());
+    print_state(cpu.ram(), options);
+
+    write_run_files(&[(&options.screen_file, cpu.screen_pbm())]);
+    let compared = compare(cpu.ram(), options);
+    result.is_err() || !compared
+}
+
+/// Reads and parses a key script, exiting if it has an error
+fn read_key_script(path: &str) -> Vec<KeyEvent> {
+    let events = fs::read_to_string(path)
+        .map_err(|error| format!("error: could not read {}: {}\n", path, error))
+        .and_then(|script| key_script::parse(&script).map_err(|e| e.render(path, &script)));
+    match events {
+        Ok(events) => { events }
+        Err(message) => {
+            eprint!("{}", message);
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the registers of the vm and the RAM ranges asked for
+fn print_state(ram: &[i16], options: &RunOptions) {
+    println!("SP={} LCL={} ARG={} THIS={} THAT={}", ram[SP], ram[LCL], ram[ARG], ram[THIS], ram[THAT]);
+    for (from, to) in options.ram_ranges.iter() {
+        for (offset, value) in ram[*from..*to].iter().enumerate() {
+            println!("RAM[{}] = {}", from + offset, value);
+        }
+    }
+}
+
+/// Writes the files asked for on the command line, exiting if one can't be written
+fn write_run_files(files: &[(&Option<String>, Vec<u8>)]) {
+    for (path, contents) in files.iter() {
+        if let Some(path) = path {
+            if let Err(error) = fs::write(path, contents) {
+                eprintln!("error: could not write {}: {}", path, error);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// Compares the RAM with the compare file given with --compare, if any
+///
+/// # Returns
+///
+/// * Whether the RAM matches
+fn compare(ram: &[i16], options: &RunOptions) -> bool {
+    let Some(path) = &options.compare_file else { return true; };
+    let result = fs::read_to_string(path)
+        .map_err(|error| format!("could not read {}: {}", path, error))
+        .and_then(|cmp| compare_ram(ram, &cmp));
+    match result {
+        Ok(()) => {
+            println!("the RAM matches {}", path);
+            true
+        }
+        Err(message) => {
+            eprintln!("error: the RAM doesn't match {}:\n{}", path, message);
+            false
+        }
+    }
+}
+
+/// A parsed jack file, waiting for the classes of its program to be parsed before it is compiled
+struct ParsedJob {
+    source: String,
+    class: Class,
+    /// The files written while parsing
+    written: Vec<PathBuf>,
+}
+
+/// Parses a single jack file, writing the tokens and parse tree if asked for by --emit
+///
+/// # Returns
+///
+/// * The parsed class, or the error message ready for printing
+fn parse_job(job: &Job, options: &Options) -> Result<ParsedJob, String> {
+    let display_path = job.source.display().to_string();
+    let source = fs::read_to_string(&job.source).map_err(|e| format!("error: could not read {}: {}", display_path, e))?;
+    fs::create_dir_all(&job.out_dir).map_err(|e| format!("error: could not create {}: {}", job.out_dir.display(), e))?;
+
+    let stem = job.source.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let mut written = Vec::new();
+
+    let tokenizer = Tokenizer::new(&source).map_err(|e| e.render(&display_path, &source))?;
+    if options.emit.contains(&Emit::Tokens) {
+        let tokens_path = job.out_dir.join(format!("{}T.xml", stem));
+        let mut xml_writer = XmlWriter::new(&tokens_path).map_err(|e| format!("error: could not create {}: {}", tokens_path.display(), e))?;
+        tokenizer.write_xml(&mut xml_writer).map_err(|e| format!("error: could not write {}: {}", tokens_path.display(), e))?;
+        written.push(tokens_path);
+    }
+    let class = Parser::new(tokenizer).parse_class().map_err(|e| e.render(&display_path, &source))?;
+
+    if options.emit.contains(&Emit::Xml) {
+        let xml_path = job.out_dir.join(format!("{}.xml", stem));
+        let xml_writer = XmlWriter::new(&xml_path).map_err(|e| format!("error: could not create {}: {}", xml_path.display(), e))?;
+        ParseTreeWriter::new(xml_writer).write_class(&class).map_err(|e| format!("error: could not write {}: {}", xml_path.display(), e))?;
+        written.push(xml_path);
+    }
+    Ok(ParsedJob { source, class, written })
+}
+
+/// Compiles a parsed jack file, writing the files asked for by --emit
+///
+/// # Arguments
+///
+/// * `signatures` - The subroutines of the classes of the job's program
+///
+/// # Returns
+///
+/// * The vm code of the class if it was compiled, or the error message ready for printing
+fn compile_job(job: &Job, parsed: ParsedJob, signatures: &Signatures, options: &Options) -> Result<Option<String>, String> {
+    let display_path = job.source.display().to_string();
+    let ParsedJob { source, class, mut written } = parsed;
+    let stem = job.source.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+    let mut vm_code = None;
+    if options.emit.iter().any(|emit| [Emit::Vm, Emit::Asm, Emit::Hack, Emit::Symbols].contains(emit)) {
+        let mut compilation_engine = CompilationEngine::new(VMWriter::from_writer(Vec::new()));
+        compilation_engine.set_precedence(options.precedence);
+        compilation_engine.set_signatures(signatures.clone());
+        compilation_engine.set_strict_types(options.strict_types);
+        let result = compilation_engine.compile(&class);
+        let warnings = compilation_engine.warnings();
+        if options.verbosity > Verbosity::Quiet || options.deny_warnings {
+            for warning in warnings {
+                eprint!("{}", warning.render(&display_path, &source));
+            }
+        }
+        result.map_err(|e| e.render(&display_path, &source))?;
+        if options.deny_warnings && !warnings.is_empty() {
+            return Err(format!("error: {} has {} warning(s), which --deny-warnings turns into errors", display_path, warnings.len()));
+        }
+        if options.emit.contains(&Emit::Symbols) {
+            let scopes = compilation_engine.symbol_dump();
+            for (path, dump) in [
+                (job.out_dir.join(format!("{}.symbols", stem)), symbol_table::dump_text(scopes)),
+                (job.out_dir.join(format!("{}.symbols.json", stem)), symbol_table::dump_json(scopes)),
+            ] {
+                fs::write(&path, dump).map_err(|e| format!("error: could not write {}: {}", path.display(), e))?;
+                written.push(path);
+            }
+        }
+        let code = String::from_utf8_lossy(&compilation_engine.into_vm_writer().into_inner()).to_string();
+
+        if options.emit.contains(&Emit::Vm) {
+            let vm_path = job.out_dir.join(format!("{}.vm", stem));
+            fs::write(&vm_path, &code).map_err(|e| format!("error: could not write {}: {}", vm_path.display(), e))?;
+            written.push(vm_path);
+        }
+        vm_code = Some(code);
+    }
+
+    if options.verbosity == Verbosity::Verbose {
+        for path in written {
+            println!("  wrote {}", path.display());
+        }
+    }
+    Ok(vm_code)
+}
+
+/// Translates the compiled classes of a program, along with the .vm files of its library directory,
+/// into hack assembly and assembles it into a rom image, writing the files asked for by --emit
+///
+/// # Returns
+///
+/// * Nothing, or the error message ready for printing
+fn write_program(program: &Program, options: &Options) -> Result<(), String> {
+    let mut sources: Vec<(String, String, String)> = program.classes.iter()
+        .map(|(name, code)| (name.to_string(), format!("{}.vm", name), code.to_string()))
+        .collect();
+    if let Some(library_dir) = &program.library_dir {
+        for path in search_files(library_dir, "vm", false).map_err(|e| format!("error: could not read {}: {}", library_dir.display(), e))? {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            if sources.iter().any(|(compiled, _, _)| *compiled == name) {
+                continue;
+            }
+            let code = fs::read_to_string(&path).map_err(|e| format!("error: could not read {}: {}", path.display(), e))?;
+            sources.push((name, path.display().to_string(), code));
+        }
+    }
+
+    let mut files = Vec::new();
+    for (name, display_path, code) in sources.iter() {
+        files.push((name.to_string(), vm_command::parse(code).map_err(|e| e.render(display_path, code))?));
+    }
+    check_program(&files).map_err(|errors| {
+        errors.iter().map(|(file_index, error)| {
+            match sources.get(*file_index) {
+                Some((_, display_path, code)) => { error.render(display_path, code) }
+                None => { format!("error: {}\n", error.message) }
+            }
+        }).collect::<String>()
+    })?;
+    let files: Vec<_> = files.into_iter()
+        .map(|(name, commands)| (name, commands.into_iter().map(|(command, _)| command).collect()))
+        .collect();
+    let asm = translate_program(&files);
+    let mut written = Vec::new();
+
+    if options.emit.contains(&Emit::Asm) {
+        fs::write(&program.asm_path, &asm).map_err(|e| format!("error: could not write {}: {}", program.asm_path.display(), e))?;
+        written.push(program.asm_path.to_path_buf());
+    }
+
+    if options.emit.contains(&Emit::Hack) {
+        let hack_path = program.asm_path.with_extension("hack");
+        let instructions = assemble(&asm).map_err(|e| e.render(&program.asm_path.display().to_string(), &asm))?;
+        fs::write(&hack_path, to_hack_text(&instructions)).map_err(|e| format!("error: could not write {}: {}", hack_path.display(), e))?;
+        written.push(hack_path);
+    }
+
+    if options.verbosity == Verbosity::Verbose {
+        for path in written {
+            println!("  wrote {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Turns the inputs given on the command line into the list of jack files to compile,
+/// and the programs they are translated into assembly as
+fn collect_jobs(options: &Options) -> Result<(Vec<Job>, Vec<Program>), String> {
+    let mut jobs = Vec::new();
+    let mut programs: Vec<Program> = Vec::new();
+    for input in options.inputs.iter() {
+        let path = Path::new(input);
+        if path.is_dir() {
+            let sources = search_files(path, "jack", options.recursive)
+                .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+            if sources.is_empty() {
+                return Err(format!("no .jack files found in {}", path.display()));
+            }
+            for source in sources {
+                let parent = source.parent().unwrap_or(path).to_path_buf();
+                let out_dir = match &options.out_dir {
+                    // keep the layout of the searched directory, so equally named files don't collide
+                    Some(out_dir) => { Path::new(out_dir).join(parent.strip_prefix(path).unwrap_or(Path::new(""))) }
+                    None => { parent.to_path_buf() }
+                };
+                // every directory is a program, named after the directory as in nand2tetris project 8
+                let program_name = parent.canonicalize().ok()
+                    .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()))
+                    .unwrap_or_else(|| "Main".to_string());
+                let asm_path = out_dir.join(format!("{}.asm", program_name));
+                let program = match programs.iter().position(|program| program.asm_path == asm_path) {
+                    Some(program) => { program }
+                    None => {
+                        programs.push(Program { asm_path, library_dir: Some(parent), classes: Vec::new(), failed: false });
+                        programs.len() - 1
+                    }
+                };
+                jobs.push(Job { source, out_dir, program });
+            }
+        } else if path.is_file() {
+            if path.extension().is_none_or(|extension| extension != "jack") {
+                return Err(format!("{} is not a .jack file", path.display()));
+            }
+            let out_dir = match &options.out_dir {
+                Some(out_dir) => { PathBuf::from(out_dir) }
+                None => { path.parent().unwrap_or(Path::new("")).to_path_buf() }
+            };
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            programs.push(Program { asm_path: out_dir.join(format!("{}.asm", stem)), library_dir: None, classes: Vec::new(), failed: false });
+            jobs.push(Job { source: path.to_path_buf(), out_dir, program: programs.len() - 1 });
+        } else {
+            return Err(format!("{}: no such file or directory", path.display()));
+        }
+    }
+    Ok((jobs, programs))
+}
+
+/// Lists the files of a directory with the given extension, sorted by name
+fn search_files(dir: &Path, extension: &str, recursive: bool) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut entries = fs::read_dir(dir)?.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                files.append(&mut search_files(&path, extension, recursive)?);
+            }
+        } else if path.extension().is_some_and(|file_extension| file_extension == extension) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,288 @@
+use std::fs::File;
+use std::io::{self, Write};
+
+use crate::ast::{Class, ClassVarDec, Expression, Name, Parameter, Statement, SubroutineCall, SubroutineDec, Term, VarDec};
+use crate::utility::{DATA_TYPES, Kind};
+use crate::xmlwriter::XmlWriter;
+
+/// Writes the parse tree of a class as xml, in the format of the nand2tetris project 10 compare files
+pub struct ParseTreeWriter<W: Write = File> {
+    xml_file: XmlWriter<W>,
+}
+
+impl<W: Write> ParseTreeWriter<W> {
+    /// Gets ready to write the parse tree to the given xml file
+    pub fn new(mut xml_file: XmlWriter<W>) -> Self {
+        xml_file.set_indentation(2);
+        ParseTreeWriter {
+            xml_file,
+        }
+    }
+
+    /// Returns the xml writer the parse tree was written to
+    pub fn into_xml_writer(self) -> XmlWriter<W> {
+        self.xml_file
+    }
+
+    /// Writes a complete class.
+    pub fn write_class(&mut self, class: &Class) -> io::Result<()> {
+        self.xml_file.open_tag("class".to_string())?;
+        self.keyword("class")?;
+        self.identifier(&class.name)?;
+        self.symbol("{")?;
+        for var_dec in class.var_decs.iter() {
+            self.write_class_var_dec(var_dec)?;
+        }
+        for subroutine in class.subroutines.iter() {
+            self.write_subroutine_dec(subroutine)?;
+        }
+        self.symbol("}")?;
+        self.xml_file.close_tag("class".to_string())?;
+        Ok(())
+    }
+
+    /// Writes a static variable declaration or field declaration.
+    fn write_class_var_dec(&mut self, var_dec: &ClassVarDec) -> io::Result<()> {
+        self.xml_file.open_tag("classVarDec".to_string())?;
+        self.keyword(if var_dec.kind == Kind::STATIC { "static" } else { "field" })?;
+        self.data_type(&var_dec.data_type)?;
+        self.var_names(&var_dec.names)?;
+        self.xml_file.close_tag("classVarDec".to_string())?;
+        Ok(())
+    }
+
+    /// Writes a complete method, function or constructor.
+    fn write_subroutine_dec(&mut self, subroutine: &SubroutineDec) -> io::Result<()> {
+        self.xml_file.open_tag("subroutineDec".to_string())?;
+        self.keyword(subroutine.kind.keyword())?;
+        self.data_type(&subroutine.return_type)?;
+        self.identifier(&subroutine.name)?;
+        self.symbol("(")?;
+        self.write_parameter_list(&subroutine.parameters)?;
+        self.symbol(")")?;
+        self.write_subroutine_body(subroutine)?;
+        self.xml_file.close_tag("subroutineDec".to_string())?;
+        Ok(())
+    }
+
+    /// Writes a (possibly empty) parameter list.
+    /// Does not write the enclosing "()".
+    fn write_parameter_list(&mut self, parameters: &[Parameter]) -> io::Result<()> {
+        self.xml_file.open_tag("parameterList".to_string())?;
+        for (index, parameter) in parameters.iter().enumerate() {
+            if index > 0 {
+                self.symbol(",")?;
+            }
+            self.data_type(&parameter.data_type)?;
+            self.identifier(&parameter.name)?;
+        }
+        self.xml_file.close_tag("parameterList".to_string())?;
+        Ok(())
+    }
+
+    /// Writes a subroutine's body.
+    fn write_subroutine_body(&mut self, subroutine: &SubroutineDec) -> io::Result<()> {
+        self.xml_file.open_tag("subroutineBody".to_string())?;
+        self.symbol("{")?;
+        for var_dec in subroutine.var_decs.iter() {
+            self.write_var_dec(var_dec)?;
+        }
+        self.write_statements(&subroutine.statements)?;
+        self.symbol("}")?;
+        self.xml_file.close_tag("subroutineBody".to_string())?;
+        Ok(())
+    }
+
+    /// Writes a var declaration.
+    fn write_var_dec(&mut self, var_dec: &VarDec) -> io::Result<()> {
+        self.xml_file.open_tag("varDec".to_string())?;
+        self.keyword("var")?;
+        self.data_type(&var_dec.data_type)?;
+        self.var_names(&var_dec.names)?;
+        self.xml_file.close_tag("varDec".to_string())?;
+        Ok(())
+    }
+
+    /// Writes a sequence of statements.
+    /// Does not write the enclosing "{}".
+    fn write_statements(&mut self, statements: &[Statement]) -> io::Result<()> {
+        self.xml_file.open_tag("statements".to_string())?;
+        for statement in statements.iter() {
+            match statement {
+                Statement::Let { name, index, value, .. } => {
+                    self.xml_file.open_tag("letStatement".to_string())?;
+                    self.keyword("let")?;
+                    self.identifier(name)?;
+                    if let Some(index) = index {
+                        self.symbol("[")?;
+                        self.write_expression(index)?;
+                        self.symbol("]")?;
+                    }
+                    self.symbol("=")?;
+                    self.write_expression(value)?;
+                    self.symbol(";")?;
+                    self.xml_file.close_tag("letStatement".to_string())?;
+                }
+                Statement::If { condition, statements, else_statements, .. } => {
+                    self.xml_file.open_tag("ifStatement".to_string())?;
+                    self.keyword("if")?;
+                    self.condition(condition)?;
+                    self.block(statements)?;
+                    if let Some(else_statements) = else_statements {
+                        self.keyword("else")?;
+                        self.block(else_statements)?;
+                    }
+                    self.xml_file.close_tag("ifStatement".to_string())?;
+                }
+                Statement::While { condition, statements, .. } => {
+                    self.xml_file.open_tag("whileStatement".to_string())?;
+                    self.keyword("while")?;
+                    self.condition(condition)?;
+                    self.block(statements)?;
+                    self.xml_file.close_tag("whileStatement".to_string())?;
+                }
+                Statement::Do { call, .. } => {
+                    self.xml_file.open_tag("doStatement".to_string())?;
+                    self.keyword("do")?;
+                    self.write_subroutine_call(call)?;
+                    self.symbol(";")?;
+                    self.xml_file.close_tag("doStatement".to_string())?;
+                }
+                Statement::Return { value, .. } => {
+                    self.xml_file.open_tag("returnStatement".to_string())?;
+                    self.keyword("return")?;
+                    if let Some(value) = value {
+                        self.write_expression(value)?;
+                    }
+                    self.symbol(";")?;
+                    self.xml_file.close_tag("returnStatement".to_string())?;
+                }
+            }
+        }
+        self.xml_file.close_tag("statements".to_string())?;
+        Ok(())
+    }
+
+    /// Writes an expression.
+    fn write_expression(&mut self, expression: &Expression) -> io::Result<()> {
+        self.xml_file.open_tag("expression".to_string())?;
+        self.write_term(&expression.term)?;
+        for (op, term) in expression.rest.iter() {
+            self.symbol(op.symbol())?;
+            self.write_term(term)?;
+        }
+        self.xml_file.close_tag("expression".to_string())?;
+        Ok(())
+    }
+
+    /// Writes a term.
+    fn write_term(&mut self, term: &Term) -> io::Result<()> {
+        self.xml_file.open_tag("term".to_string())?;
+        match term {
+            Term::IntegerConstant { value, .. } => { self.xml_file.write("integerConstant".to_string(), value.to_string())?; }
+            Term::StringConstant { value, .. } => { self.xml_file.write("stringConstant".to_string(), value.to_string())?; }
+            Term::KeywordConstant { keyword, .. } => { self.keyword(keyword.keyword())?; }
+            Term::Variable(name) => { self.identifier(name)?; }
+            Term::ArrayEntry { name, index } => {
+                self.identifier(name)?;
+                self.symbol("[")?;
+                self.write_expression(index)?;
+                self.symbol("]")?;
+            }
+            Term::Call(call) => { self.write_subroutine_call(call)?; }
+            Term::Parenthesized { expression, .. } => {
+                self.symbol("(")?;
+                self.write_expression(expression)?;
+                self.symbol(")")?;
+            }
+            Term::Unary { op, term, .. } => {
+                self.symbol(op.symbol())?;
+                self.write_term(term)?;
+            }
+        }
+        self.xml_file.close_tag("term".to_string())?;
+        Ok(())
+    }
+
+    /// Writes a subroutine call. It has no tag of its own.
+    fn write_subroutine_call(&mut self, call: &SubroutineCall) -> io::Result<()> {
+        if let Some(receiver) = &call.receiver {
+            self.identifier(receiver)?;
+            self.symbol(".")?;
+        }
+        self.identifier(&call.name)?;
+        self.symbol("(")?;
+        self.write_expression_list(&call.arguments)?;
+        self.symbol(")")?;
+        Ok(())
+    }
+
+    /// Writes a (possibly empty) comma-seperated list of expressions.
+    fn write_expression_list(&mut self, expressions: &[Expression]) -> io::Result<()> {
+        self.xml_file.open_tag("expressionList".to_string())?;
+        for (index, expression) in expressions.iter().enumerate() {
+            if index > 0 {
+                self.symbol(",")?;
+            }
+            self.write_expression(expression)?;
+        }
+        self.xml_file.close_tag("expressionList".to_string())?;
+        Ok(())
+    }
+
+    /// Writes '(' expression ')'
+    fn condition(&mut self, condition: &Expression) -> io::Result<()> {
+        self.symbol("(")?;
+        self.write_expression(condition)?;
+        self.symbol(")")?;
+        Ok(())
+    }
+
+    /// Writes '{' statements '}'
+    fn block(&mut self, statements: &[Statement]) -> io::Result<()> {
+        self.symbol("{")?;
+        self.write_statements(statements)?;
+        self.symbol("}")?;
+        Ok(())
+    }
+
+    /// Writes varName (',' varName)* ';'
+    fn var_names(&mut self, names: &[Name]) -> io::Result<()> {
+        for (index, name) in names.iter().enumerate() {
+            if index > 0 {
+                self.symbol(",")?;
+            }
+            self.identifier(name)?;
+        }
+        self.symbol(";")?;
+        Ok(())
+    }
+
+    /// Writes a type, which is a keyword for the primitive types and an identifier for class names
+    fn data_type(&mut self, data_type: &Name) -> io::Result<()> {
+        if DATA_TYPES.contains(&data_type.name.as_str()) {
+            self.keyword(&data_type.name)?;
+        } else {
+            self.identifier(data_type)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a keyword leaf
+    fn keyword(&mut self, keyword: &str) -> io::Result<()> {
+        self.xml_file.write("keyword".to_string(), keyword.to_string())?;
+        Ok(())
+    }
+
+    /// Writes a symbol leaf
+    fn symbol(&mut self, symbol: &str) -> io::Result<()> {
+        self.xml_file.write("symbol".to_string(), symbol.to_string())?;
+        Ok(())
+    }
+
+    /// Writes an identifier leaf
+    fn identifier(&mut self, name: &Name) -> io::Result<()> {
+        self.xml_file.write("identifier".to_string(), name.name.to_string())?;
+        Ok(())
+    }
+}
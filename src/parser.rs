@@ -0,0 +1,424 @@
+use crate::ast::{Class, ClassVarDec, Expression, KeywordConstant, Name, Op, Parameter, Statement, SubroutineCall, SubroutineDec, SubroutineKind, Term, UnaryOp, VarDec};
+use crate::error::{CompileError, Span};
+use crate::tokenizer::{Token, Tokenizer, TokenType};
+use crate::utility::Kind;
+
+/// Tokenizes and parses the code of a jack file
+///
+/// # Arguments
+///
+/// * `source` - The contents of the jack file
+///
+/// # Returns
+///
+/// * The syntax tree of the class, or the first error in the code
+pub fn parse(source: &str) -> Result<Class, CompileError> {
+    Parser::new(Tokenizer::new(source)?).parse_class()
+}
+
+/// A recursive descent parser, building the syntax tree of a class from its tokens.
+/// Every parse_xxx method expects the next token to be the first token of xxx,
+/// and leaves the tokenizer on the last token of xxx.
+pub struct Parser {
+    tokenizer: Tokenizer,
+}
+
+impl Parser {
+    /// Creates a parser reading the tokens of the given tokenizer
+    pub fn new(tokenizer: Tokenizer) -> Self {
+        Parser {
+            tokenizer,
+        }
+    }
+
+    /// Parses a complete class, which must be the only thing in the file.
+    pub fn parse_class(&mut self) -> Result<Class, CompileError> {
+        let span = self.expect("class")?.span;
+        let name = self.expect_identifier("a class name")?;
+        self.expect("{")?;
+
+        let mut var_decs = Vec::new();
+        while self.next_is("static") || self.next_is("field") {
+            var_decs.push(self.parse_class_var_dec()?);
+        }
+
+        let mut subroutines = Vec::new();
+        while self.next_is("constructor") || self.next_is("function") || self.next_is("method") {
+            subroutines.push(self.parse_subroutine_dec()?);
+        }
+
+        if !self.next_is("}") {
+            return Err(self.unexpected("a class variable declaration, a subroutine declaration or '}'"));
+        }
+        self.tokenizer.advance();
+
+        if self.tokenizer.has_more_tokens() {
+            return Err(self.unexpected("the end of the file after the class"));
+        }
+
+        Ok(Class {
+            name,
+            var_decs,
+            subroutines,
+            span,
+        })
+    }
+
+    /// Parses a static variable declaration or field declaration.
+    fn parse_class_var_dec(&mut self) -> Result<ClassVarDec, CompileError> {
+        let keyword = self.next_token()?;
+        let kind = if keyword.text == "static" { Kind::STATIC } else { Kind::FIELD };
+        let data_type = self.parse_type(false)?;
+        let names = self.parse_var_names()?;
+
+        Ok(ClassVarDec {
+            kind,
+            data_type,
+            names,
+            span: keyword.span,
+        })
+    }
+
+    /// Parses a complete method, function or constructor.
+    fn parse_subroutine_dec(&mut self) -> Result<SubroutineDec, CompileError> {
+        let keyword = self.next_token()?;
+        let kind = match keyword.text.as_str() {
+            "constructor" => { SubroutineKind::Constructor }
+            "method" => { SubroutineKind::Method }
+            _ => { SubroutineKind::Function }
+        };
+        let return_type = self.parse_type(true)?;
+        let name = self.expect_identifier("a subroutine name")?;
+
+        self.expect("(")?;
+        let parameters = self.parse_parameter_list()?;
+        self.expect(")")?;
+
+        self.expect("{")?;
+        let mut var_decs = Vec::new();
+        while self.next_is("var") {
+            var_decs.push(self.parse_var_dec()?);
+        }
+        let statements = self.parse_statements()?;
+        self.expect("}")?;
+
+        Ok(SubroutineDec {
+            kind,
+            return_type,
+            name,
+            parameters,
+            var_decs,
+            statements,
+            span: keyword.span,
+        })
+    }
+
+    /// Parses a (possibly empty) parameter list.
+    /// Does not handle the enclosing "()".
+    fn parse_parameter_list(&mut self) -> Result<Vec<Parameter>, CompileError> {
+        let mut parameters = Vec::new();
+        if self.next_is(")") {
+            return Ok(parameters);
+        }
+        loop {
+            let data_type = self.parse_type(false)?;
+            let name = self.expect_identifier("a parameter name")?;
+            parameters.push(Parameter { data_type, name });
+            if !self.next_is(",") {
+                return Ok(parameters);
+            }
+            self.tokenizer.advance();
+        }
+    }
+
+    /// Parses a var declaration.
+    fn parse_var_dec(&mut self) -> Result<VarDec, CompileError> {
+        let span = self.expect("var")?.span;
+        let data_type = self.parse_type(false)?;
+        let names = self.parse_var_names()?;
+
+        Ok(VarDec {
+            data_type,
+            names,
+            span,
+        })
+    }
+
+    /// Parses varName (',' varName)* ';'
+    fn parse_var_names(&mut self) -> Result<Vec<Name>, CompileError> {
+        let mut names = vec![self.expect_identifier("a variable name")?];
+        while self.next_is(",") {
+            self.tokenizer.advance();
+            names.push(self.expect_identifier("a variable name")?);
+        }
+        self.expect(";")?;
+        Ok(names)
+    }
+
+    /// Parses a type: int, char, boolean or a class name, and void if allowed.
+    fn parse_type(&mut self, allow_void: bool) -> Result<Name, CompileError> {
+        let expected = if allow_void { "a type or 'void'" } else { "a type" };
+        let token = self.next_token_or(expected)?;
+        let is_type = match token.kind {
+            TokenType::Identifier => { true }
+            TokenType::Keyword => { ["int", "char", "boolean"].contains(&token.text.as_str()) || (allow_void && token.text == "void") }
+            _ => { false }
+        };
+        if !is_type {
+            return Err(Self::error_at(&token, expected));
+        }
+        Ok(Name { name: token.text, span: token.span })
+    }
+
+    /// Parses a sequence of statements.
+    /// Does not handle the enclosing "{}".
+    fn parse_statements(&mut self) -> Result<Vec<Statement>, CompileError> {
+        let mut statements = Vec::new();
+        loop {
+            let statement = match self.tokenizer.peek() {
+                Some(token) if token.is("let") => { self.parse_let()? }
+                Some(token) if token.is("if") => { self.parse_if()? }
+                Some(token) if token.is("while") => { self.parse_while()? }
+                Some(token) if token.is("do") => { self.parse_do()? }
+                Some(token) if token.is("return") => { self.parse_return()? }
+                _ => { return Ok(statements); }
+            };
+            statements.push(statement);
+        }
+    }
+
+    /// Parses a let statement.
+    fn parse_let(&mut self) -> Result<Statement, CompileError> {
+        let span = self.expect("let")?.span;
+        let name = self.expect_identifier("a variable name")?;
+        let mut index = None;
+        if self.next_is("[") {
+            self.tokenizer.advance();
+            index = Some(self.parse_expression()?);
+            self.expect("]")?;
+        }
+        self.expect("=")?;
+        let value = self.parse_expression()?;
+        self.expect(";")?;
+
+        Ok(Statement::Let { name, index, value, span })
+    }
+
+    /// Parses an if statement, possible with a trailing else clause.
+    fn parse_if(&mut self) -> Result<Statement, CompileError> {
+        let span = self.expect("if")?.span;
+        let condition = self.parse_condition()?;
+        let statements = self.parse_block()?;
+        let mut else_statements = None;
+        if self.next_is("else") {
+            self.tokenizer.advance();
+            else_statements = Some(self.parse_block()?);
+        }
+
+        Ok(Statement::If { condition, statements, else_statements, span })
+    }
+
+    /// Parses a while statement.
+    fn parse_while(&mut self) -> Result<Statement, CompileError> {
+        let span = self.expect("while")?.span;
+        let condition = self.parse_condition()?;
+        let statements = self.parse_block()?;
+
+        Ok(Statement::While { condition, statements, span })
+    }
+
+    /// Parses a do statement.
+    fn parse_do(&mut self) -> Result<Statement, CompileError> {
+        let span = self.expect("do")?.span;
+        let name = self.expect_identifier("a subroutine call")?;
+        let call = self.parse_subroutine_call(name)?;
+        self.expect(";")?;
+
+        Ok(Statement::Do { call, span })
+    }
+
+    /// Parses a return statement.
+    fn parse_return(&mut self) -> Result<Statement, CompileError> {
+        let span = self.expect("return")?.span;
+        let mut value = None;
+        if !self.next_is(";") {
+            value = Some(self.parse_expression()?);
+        }
+        self.expect(";")?;
+
+        Ok(Statement::Return { value, span })
+    }
+
+    /// Parses '(' expression ')'
+    fn parse_condition(&mut self) -> Result<Expression, CompileError> {
+        self.expect("(")?;
+        let condition = self.parse_expression()?;
+        self.expect(")")?;
+        Ok(condition)
+    }
+
+    /// Parses '{' statements '}'
+    fn parse_block(&mut self) -> Result<Vec<Statement>, CompileError> {
+        self.expect("{")?;
+        let statements = self.parse_statements()?;
+        self.expect("}")?;
+        Ok(statements)
+    }
+
+    /// Parses an expression.
+    fn parse_expression(&mut self) -> Result<Expression, CompileError> {
+        let term = self.parse_term()?;
+        let mut rest = Vec::new();
+        while let Some(op) = self.tokenizer.peek().filter(|token| token.kind == TokenType::Symbol).and_then(|token| Op::from_symbol(&token.text)) {
+            self.tokenizer.advance();
+            rest.push((op, self.parse_term()?));
+        }
+        Ok(Expression { term, rest })
+    }
+
+    /// Parses a term.
+    /// If the current token is an identifier, the routine must distinguish between a variable,
+    /// an array-entry, or a subroutine-call.
+    /// A single look-ahead token, which may be one of "[" , "(" or ".",
+    /// suffices to distinguish between the possibilities
+    fn parse_term(&mut self) -> Result<Term, CompileError> {
+        let token = self.next_token_or("an expression")?;
+        let span = token.span;
+        match token.kind {
+            TokenType::IntConst => {
+                let value = token.text.parse().map_err(|_| Self::error_at(&token, "an integer constant"))?;
+                Ok(Term::IntegerConstant { value, span })
+            }
+            TokenType::StringConst => { Ok(Term::StringConstant { value: token.text, span }) }
+            TokenType::Keyword => {
+                let keyword = match token.text.as_str() {
+                    "true" => { KeywordConstant::True }
+                    "false" => { KeywordConstant::False }
+                    "null" => { KeywordConstant::Null }
+                    "this" => { KeywordConstant::This }
+                    _ => { return Err(Self::error_at(&token, "an expression")); }
+                };
+                Ok(Term::KeywordConstant { keyword, span })
+            }
+            TokenType::Symbol => {
+                match token.text.as_str() {
+                    "(" => {
+                        let expression = self.parse_expression()?;
+                        self.expect(")")?;
+                        Ok(Term::Parenthesized { expression: Box::new(expression), span })
+                    }
+                    "-" | "~" => {
+                        let op = if token.text == "-" { UnaryOp::Neg } else { UnaryOp::Not };
+                        let term = self.parse_term()?;
+                        Ok(Term::Unary { op, term: Box::new(term), span })
+                    }
+                    _ => { Err(Self::error_at(&token, "an expression")) }
+                }
+            }
+            TokenType::Identifier => {
+                let name = Name { name: token.text, span };
+                if self.next_is("[") {
+                    self.tokenizer.advance();
+                    let index = self.parse_expression()?;
+                    self.expect("]")?;
+                    Ok(Term::ArrayEntry { name, index: Box::new(index) })
+                } else if self.next_is("(") || self.next_is(".") {
+                    Ok(Term::Call(self.parse_subroutine_call(name)?))
+                } else {
+                    Ok(Term::Variable(name))
+                }
+            }
+        }
+    }
+
+    /// Parses the rest of a subroutine call, after its first name
+    fn parse_subroutine_call(&mut self, first_name: Name) -> Result<SubroutineCall, CompileError> {
+        let (receiver, name) = if self.next_is(".") {
+            self.tokenizer.advance();
+            (Some(first_name), self.expect_identifier("a subroutine name")?)
+        } else {
+            (None, first_name)
+        };
+        self.expect("(")?;
+        let arguments = self.parse_expression_list()?;
+        self.expect(")")?;
+
+        Ok(SubroutineCall { receiver, name, arguments })
+    }
+
+    /// Parses a (possibly empty) comma-seperated list of expressions.
+    /// Does not handle the enclosing "()".
+    fn parse_expression_list(&mut self) -> Result<Vec<Expression>, CompileError> {
+        let mut expressions = Vec::new();
+        if self.next_is(")") {
+            return Ok(expressions);
+        }
+        expressions.push(self.parse_expression()?);
+        while self.next_is(",") {
+            self.tokenizer.advance();
+            expressions.push(self.parse_expression()?);
+        }
+        Ok(expressions)
+    }
+
+    /// Checks if the next token is the given keyword or symbol
+    fn next_is(&self, text: &str) -> bool {
+        self.tokenizer.peek().is_some_and(|token| token.is(text))
+    }
+
+    /// Advances and returns the new current token, or an error at the end of the file
+    fn next_token(&mut self) -> Result<Token, CompileError> {
+        self.next_token_or("more code")
+    }
+
+    /// Advances and returns the new current token.
+    /// At the end of the file, returns an error saying what was expected instead.
+    fn next_token_or(&mut self, expected: &str) -> Result<Token, CompileError> {
+        if !self.tokenizer.has_more_tokens() {
+            return Err(self.unexpected(expected));
+        }
+        self.tokenizer.advance();
+        Ok(self.tokenizer.current().cloned().expect("advanced past the last token"))
+    }
+
+    /// Advances over the given keyword or symbol, or returns an error if the next token is different
+    fn expect(&mut self, text: &str) -> Result<Token, CompileError> {
+        if !self.next_is(text) {
+            return Err(self.unexpected(&format!("'{}'", text)));
+        }
+        self.next_token()
+    }
+
+    /// Advances over an identifier, or returns an error if the next token is not an identifier
+    fn expect_identifier(&mut self, expected: &str) -> Result<Name, CompileError> {
+        let token = self.next_token_or(expected)?;
+        if token.kind != TokenType::Identifier {
+            return Err(Self::error_at(&token, expected));
+        }
+        Ok(Name { name: token.text, span: token.span })
+    }
+
+    /// Creates an error saying what was expected instead of the next token
+    fn unexpected(&self, expected: &str) -> CompileError {
+        match self.tokenizer.peek() {
+            Some(token) => { Self::error_at(token, expected) }
+            None => {
+                // points right after the last token of the file
+                let span = match self.tokenizer.tokens().last() {
+                    Some(last) => { Span::new(last.span.line, last.span.column + last.span.length, 1) }
+                    None => { Span::new(1, 1, 1) }
+                };
+                CompileError::new(span, format!("expected {}, found the end of the file", expected))
+            }
+        }
+    }
+
+    /// Creates an error saying what was expected instead of the given token
+    fn error_at(token: &Token, expected: &str) -> CompileError {
+        let found = match token.kind {
+            TokenType::StringConst => { format!("\"{}\"", token.text) }
+            _ => { format!("'{}'", token.text) }
+        };
+        CompileError::new(token.span, format!("expected {}, found {}", expected, found))
+    }
+}
@@ -0,0 +1,154 @@
+/// This file contains the subroutine signatures of every class of a program, collected in a first pass
+/// before any class is compiled, so calls to other classes can be checked
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Class, SubroutineKind};
+
+/// The subroutines of the jack OS, as (kind, return type, full name, parameter types).
+/// The native OS of the vm interpreter checks the number of arguments of its calls against it too.
+pub static OS_API: [(&str, &str, &str, &[&str]); 49] = [
+    ("function", "Array", "Array.new", &["int"]),
+    ("method", "void", "Array.dispose", &[]),
+    ("function", "void", "Keyboard.init", &[]),
+    ("function", "char", "Keyboard.keyPressed", &[]),
+    ("function", "char", "Keyboard.readChar", &[]),
+    ("function", "String", "Keyboard.readLine", &["String"]),
+    ("function", "int", "Keyboard.readInt", &["String"]),
+    ("function", "void", "Math.init", &[]),
+    ("function", "int", "Math.abs", &["int"]),
+    ("function", "int", "Math.multiply", &["int", "int"]),
+    ("function", "int", "Math.divide", &["int", "int"]),
+    ("function", "int", "Math.min", &["int", "int"]),
+    ("function", "int", "Math.max", &["int", "int"]),
+    ("function", "int", "Math.sqrt", &["int"]),
+    ("function", "void", "Memory.init", &[]),
+    ("function", "int", "Memory.peek", &["int"]),
+    ("function", "void", "Memory.poke", &["int", "int"]),
+    ("function", "Array", "Memory.alloc", &["int"]),
+    ("function", "void", "Memory.deAlloc", &["Array"]),
+    ("function", "void", "Output.init", &[]),
+    ("function", "void", "Output.moveCursor", &["int", "int"]),
+    ("function", "void", "Output.printChar", &["char"]),
+    ("function", "void", "Output.printString", &["String"]),
+    ("function", "void", "Output.printInt", &["int"]),
+    ("function", "void", "Output.println", &[]),
+    ("function", "void", "Output.backSpace", &[]),
+    ("function", "void", "Screen.init", &[]),
+    ("function", "void", "Screen.clearScreen", &[]),
+    ("function", "void", "Screen.setColor", &["boolean"]),
+    ("function", "void", "Screen.drawPixel", &["int", "int"]),
+    ("function", "void", "Screen.drawLine", &["int", "int", "int", "int"]),
+    ("function", "void", "Screen.drawRectangle", &["int", "int", "int", "int"]),
+    ("function", "void", "Screen.drawCircle", &["int", "int", "int"]),
+    ("constructor", "String", "String.new", &["int"]),
+    ("method", "void", "String.dispose", &[]),
+    ("method", "int", "String.length", &[]),
+    ("method", "char", "String.charAt", &["int"]),
+    ("method", "void", "String.setCharAt", &["int", "char"]),
+    ("method", "String", "String.appendChar", &["char"]),
+    ("method", "void", "String.eraseLastChar", &[]),
+    ("method", "int", "String.intValue", &[]),
+    ("method", "void", "String.setInt", &["int"]),
+    ("function", "char", "String.backSpace", &[]),
+    ("function", "char", "String.doubleQuote", &[]),
+    ("function", "char", "String.newLine", &[]),
+    ("function", "void", "Sys.init", &[]),
+    ("function", "void", "Sys.halt", &[]),
+    ("function", "void", "Sys.error", &["int"]),
+    ("function", "void", "Sys.wait", &["int"]),
+];
+
+/// How a subroutine is declared
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    pub kind: SubroutineKind,
+    pub return_type: String,
+    /// The types of the parameters, without `this` for methods
+    pub parameters: Vec<String>,
+}
+
+/// The signatures of the subroutines of every class known to the program, the OS included
+#[derive(Clone, Debug)]
+pub struct Signatures {
+    classes: HashMap<String, HashMap<String, Signature>>,
+    /// Classes that exist, such as already compiled .vm files, but whose subroutines are unknown
+    opaque_classes: HashSet<String>,
+    /// Whether every class of the program is known, so calls to any other class are mistakes
+    complete: bool,
+}
+
+impl Default for Signatures {
+    fn default() -> Self {
+        Signatures::new()
+    }
+}
+
+impl Signatures {
+    /// Creates the signatures of a program that knows only the OS, and may have other classes
+    pub fn new() -> Self {
+        let mut classes: HashMap<String, HashMap<String, Signature>> = HashMap::new();
+        for (kind, return_type, full_name, parameters) in OS_API.iter() {
+            let (class_name, name) = full_name.split_once('.').unwrap_or((full_name, ""));
+            let kind = match *kind {
+                "constructor" => { SubroutineKind::Constructor }
+                "method" => { SubroutineKind::Method }
+                _ => { SubroutineKind::Function }
+            };
+            let signature = Signature {
+                kind,
+                return_type: return_type.to_string(),
+                parameters: parameters.iter().map(|parameter| parameter.to_string()).collect(),
+            };
+            classes.entry(class_name.to_string()).or_default().insert(name.to_string(), signature);
+        }
+        Signatures {
+            classes,
+            opaque_classes: HashSet::new(),
+            complete: false,
+        }
+    }
+
+    /// Adds the subroutines of a parsed class, replacing the OS class of the same name
+    pub fn add_class(&mut self, class: &Class) {
+        let subroutines = class.subroutines.iter().map(|subroutine| {
+            let signature = Signature {
+                kind: subroutine.kind,
+                return_type: subroutine.return_type.name.to_string(),
+                parameters: subroutine.parameters.iter().map(|parameter| parameter.data_type.name.to_string()).collect(),
+            };
+            (subroutine.name.name.to_string(), signature)
+        }).collect();
+        self.classes.insert(class.name.name.to_string(), subroutines);
+    }
+
+    /// Adds a class whose subroutines are unknown, such as the class of an already compiled .vm file
+    pub fn add_opaque_class(&mut self, name: &str) {
+        self.opaque_classes.insert(name.to_string());
+    }
+
+    /// Sets whether every class of the program is known. Only then calls to unknown classes are errors.
+    pub fn set_complete(&mut self, complete: bool) {
+        self.complete = complete;
+    }
+
+    /// Whether the class is part of the program
+    ///
+    /// # Returns
+    ///
+    /// * Some(true) if its subroutines are known, Some(false) if it exists without known subroutines,
+    ///   and None if there is no such class
+    pub fn has_class(&self, name: &str) -> Option<bool> {
+        if self.classes.contains_key(name) {
+            Some(true)
+        } else if self.opaque_classes.contains(name) || !self.complete {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Finds the signature of a subroutine
+    pub fn subroutine(&self, class_name: &str, name: &str) -> Option<&Signature> {
+        self.classes.get(class_name)?.get(name)
+    }
+}
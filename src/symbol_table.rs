@@ -1,18 +1,58 @@
+use std::collections::HashMap;
+
+use crate::error::Span;
 use crate::utility::{Kind, Symbol};
 
+/// Why a name couldn't be defined in a symbol table
+#[derive(Clone, Debug, PartialEq)]
+pub enum DefineError {
+    /// Only statics, fields, arguments and local variables can be defined
+    NoKind,
+    /// The name is already defined in the same scope, declared at the span
+    Redeclared(Span),
+}
+
+/// The symbols of a class or subroutine scope, kept for the symbol dump once the scope is closed
+#[derive(Clone, Debug)]
+pub struct ScopeDump {
+    /// The name of the class, or the full name of the subroutine
+    pub name: String,
+    /// `class`, or the keyword the subroutine is declared with
+    pub kind: String,
+    pub symbols: Vec<Symbol>,
+}
+
+/// The symbols declared in a single scope, in the order they are declared
+#[derive(Default)]
+struct Scope {
+    symbols: Vec<Symbol>,
+    /// The position of every symbol in `symbols`, by name
+    positions: HashMap<String, usize>,
+}
+
+/// The symbols of a class, in nested scopes: the class scope with the statics and fields,
+/// the subroutine scope with the arguments and local variables, and any block scopes inside it.
+/// Names are resolved from the innermost scope outwards.
 pub struct SymbolTable {
-    table: Vec<Symbol>,
+    /// The open scopes, the class scope first and the innermost last
+    scopes: Vec<Scope>,
     static_index: usize,
     field_index: usize,
     arg_index: usize,
     var_index: usize,
 }
 
+impl Default for SymbolTable {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
 impl SymbolTable {
-    /// Creates a new Symbol Table
+    /// Creates a new Symbol Table, with an empty class scope
     pub fn new() -> Self {
         SymbolTable {
-            table: vec![],
+            scopes: vec![Scope::default()],
             static_index: 0,
             field_index: 0,
             arg_index: 0,
@@ -20,87 +60,173 @@ impl SymbolTable {
         }
     }
 
-    /// Starts a new subroutine scope
-    /// A.K.A. resets the table
+    /// Starts a new subroutine scope, closing the scopes of the previous subroutine.
+    /// The statics and fields of the class scope are kept.
     pub fn start_subroutine(&mut self) {
-        self.table = Vec::new();
-        self.static_index = 0;
-        self.field_index = 0;
+        self.scopes.truncate(1);
+        self.scopes.push(Scope::default());
         self.arg_index = 0;
         self.var_index = 0;
     }
 
-    /// Defines a new identifier og hte given name, type, and kind, and assigns it an index.
-    /// STATIC and FIELD identifiers have a class scope, while ARG and VAR identifiers have a subroutine scope
-    pub fn define(&mut self, name: String, data_type: String, kind: Kind) {
-        let index: usize;
-        match kind {
-            Kind::STATIC => {
-                index = self.static_index;
-                self.static_index += 1
-            }
-            Kind::FIELD => {
-                index = self.field_index;
-                self.field_index += 1
-            }
-            Kind::ARG => {
-                index = self.arg_index;
-                self.arg_index += 1
-            }
-            Kind::VAR => {
-                index = self.var_index;
-                self.var_index += 1
-            }
-            Kind::NONE => { panic!("invalid Kind of variable!!") }
+    /// Closes the scopes of the current subroutine, leaving the class scope
+    pub fn end_subroutine(&mut self) {
+        self.scopes.truncate(1);
+    }
+
+    /// Opens a scope inside the current one.
+    /// Jack declares every local variable at the top of its subroutine, so the compiler never opens block scopes;
+    /// this and pop_scope are reserved for statements that declare variables of their own.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    /// Closes the innermost scope. The class scope is never closed.
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// Defines a new identifier of the given name, type, and kind, and assigns it an index.
+    /// STATIC and FIELD identifiers go into the class scope, while ARG and VAR identifiers go into the innermost scope.
+    /// Returns an error if the kind is NONE, or if the name is already defined in that scope.
+    pub fn define(&mut self, name: String, data_type: String, kind: Kind, span: Span) -> Result<(), DefineError> {
+        let scope_index = match kind {
+            Kind::STATIC | Kind::FIELD => { 0 }
+            Kind::ARG | Kind::VAR => { self.scopes.len() - 1 }
+            Kind::NONE => { return Err(DefineError::NoKind); }
+        };
+        if let Some(&position) = self.scopes[scope_index].positions.get(&name) {
+            return Err(DefineError::Redeclared(self.scopes[scope_index].symbols[position].get_span()));
+        }
+
+        let counter = match kind {
+            Kind::STATIC => { &mut self.static_index }
+            Kind::FIELD => { &mut self.field_index }
+            Kind::ARG => { &mut self.arg_index }
+            _ => { &mut self.var_index }
         };
-        self.table.push(Symbol::new(name, data_type, kind, index));
+        let index = *counter;
+        *counter += 1;
+
+        let scope = &mut self.scopes[scope_index];
+        scope.positions.insert(name.to_string(), scope.symbols.len());
+        scope.symbols.push(Symbol::new(name, data_type, kind, index, span));
+        Ok(())
     }
 
     /// Returns the number of variable of the given kind already defined in current scope
+    /// There are never variables of kind NONE.
     pub fn var_count(&self, kind: Kind) -> usize {
         match kind {
             Kind::STATIC => { self.static_index }
             Kind::FIELD => { self.field_index }
             Kind::ARG => { self.arg_index }
             Kind::VAR => { self.var_index }
-            Kind::NONE => { panic!("invalid Kind of variable!!") }
+            Kind::NONE => { 0 }
         }
     }
 
-    /// Returns the kind of the names identifier in the current scope.
-    /// If the identifier is unknown in the current scope, returns NONE.
-    pub fn kind_of(&self, name: String) -> Kind {
-        let mut kind: Kind = Kind::NONE;
-        for symbol in self.table.iter() {
-            if symbol.get_name() == name {
-                kind = symbol.get_kind();
-                break;
+    /// Finds the symbol a name refers to, looking from the innermost scope outwards
+    ///
+    /// # Returns
+    ///
+    /// * The symbol, or None if the name isn't defined in any open scope
+    pub fn resolve(&self, name: &str) -> Option<&Symbol> {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.positions.get(name).map(|&position| &scope.symbols[position]))
+    }
+
+    /// Records a use of the symbol a name refers to.
+    /// Returns whether the name is defined.
+    pub fn mark_used(&mut self, name: &str, written: bool) -> bool {
+        let symbol = self.scopes.iter_mut().rev()
+            .find_map(|scope| scope.positions.get(name).copied().map(|position| &mut scope.symbols[position]));
+        match symbol {
+            Some(symbol) => {
+                symbol.mark_used(written);
+                true
             }
+            None => { false }
         }
-        kind
     }
 
-    /// Returns the type of the names identifier in the scope.
-    pub fn type_of(&self, name: String) -> String {
-        let mut data = "".to_string();
-        for symbol in self.table.iter() {
-            if symbol.get_name() == name {
-                data = symbol.get_data_type();
-                break;
-            }
+    /// Keeps the symbols of the innermost scope for the symbol dump
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the class, or the full name of the subroutine
+    /// * `kind` - `class`, or the keyword the subroutine is declared with
+    pub fn dump(&self, name: &str, kind: &str) -> ScopeDump {
+        ScopeDump {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            symbols: self.symbols().to_vec(),
         }
-        data
     }
 
-    /// Returns the index assigned to the named identifier.
-    pub fn index_of(&self, name: String) -> usize {
-        let mut index = usize::MAX;
-        for symbol in self.table.iter() {
-            if symbol.get_name() == name {
-                index = symbol.get_index();
-                break;
-            }
+    /// Returns every symbol of the innermost scope, in the order they are defined.
+    pub fn symbols(&self) -> &[Symbol] {
+        self.scopes.last().map_or(&[], |scope| &scope.symbols)
+    }
+}
+
+/// Writes the symbol tables of a class as text, a table of name, type, kind and index for the class
+/// and for every subroutine, laid out as in nand2tetris chapter 11
+///
+/// # Arguments
+///
+/// * `scopes` - The class scope followed by the subroutine scopes, as kept by the compilation engine
+pub fn dump_text(scopes: &[ScopeDump]) -> String {
+    let mut text = String::new();
+    for scope in scopes.iter() {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&format!("{} {}\n", scope.kind, scope.name));
+        let mut rows = vec![["name".to_string(), "type".to_string(), "kind".to_string(), "#".to_string()]];
+        rows.extend(scope.symbols.iter().map(|symbol| {
+            [symbol.get_name().to_string(), symbol.get_data_type().to_string(), symbol.get_kind().keyword().to_string(), symbol.get_index().to_string()]
+        }));
+        let widths: Vec<usize> = (0..4).map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0)).collect();
+        for row in rows.iter() {
+            let cells: Vec<String> = row.iter().zip(widths.iter()).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+            text.push_str(cells.join("  ").trim_end());
+            text.push('\n');
         }
-        index
     }
-}
\ No newline at end of file
+    text
+}
+
+/// Writes the symbol tables of a class as JSON: the class with its symbols, and every subroutine with its own
+///
+/// # Arguments
+///
+/// * `scopes` - The class scope followed by the subroutine scopes, as kept by the compilation engine
+pub fn dump_json(scopes: &[ScopeDump]) -> String {
+    let symbols_json = |symbols: &[Symbol], indent: &str| -> String {
+        if symbols.is_empty() {
+            return "[]".to_string();
+        }
+        let rows: Vec<String> = symbols.iter().map(|symbol| format!(
+            "{}  {{\"name\": \"{}\", \"type\": \"{}\", \"kind\": \"{}\", \"index\": {}}}",
+            indent, symbol.get_name(), symbol.get_data_type(), symbol.get_kind().keyword(), symbol.get_index()
+        )).collect();
+        format!("[\n{}\n{}]", rows.join(",\n"), indent)
+    };
+
+    let (class, subroutines) = match scopes.split_first() {
+        Some((class, subroutines)) => { (class, subroutines) }
+        None => { return "{}\n".to_string(); }
+    };
+    let subroutines: Vec<String> = subroutines.iter().map(|scope| format!(
+        "    {{\n      \"name\": \"{}\",\n      \"kind\": \"{}\",\n      \"symbols\": {}\n    }}",
+        scope.name, scope.kind, symbols_json(&scope.symbols, "      ")
+    )).collect();
+    let subroutines = if subroutines.is_empty() { "[]".to_string() } else { format!("[\n{}\n  ]", subroutines.join(",\n")) };
+    format!(
+        "{{\n  \"class\": \"{}\",\n  \"symbols\": {},\n  \"subroutines\": {}\n}}\n",
+        class.name, symbols_json(&class.symbols, "  "), subroutines
+    )
+}
@@ -0,0 +1,626 @@
+/// This file contains the runner of the .tst test scripts of nand2tetris. A script loads a program into
+/// the vm interpreter or the CPU emulator, sets up the RAM, runs the program, and writes the values it
+/// lists into an .out file, which is compared line by line with the .cmp file of the test.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cpu::Cpu;
+use crate::error::{CompileError, Span};
+use crate::vm::{Vm, ARG, LCL, RAM_SIZE, SP, THAT, THIS};
+use crate::vm2asm::TEMP_BASE;
+
+/// A variable of the vm interpreter or the CPU emulator, such as `sp`, `D` or `RAM[256]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub index: Option<usize>,
+}
+
+/// A column of the output list, with its format, such as `RAM[256]%D1.6.1`
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputColumn {
+    pub variable: Variable,
+    /// The name of the column, as written in the script
+    pub header: String,
+    /// `D` for decimal, `X` for hexadecimal, `B` for binary, `S` for text
+    pub format: char,
+    pub left_padding: usize,
+    pub width: usize,
+    pub right_padding: usize,
+}
+
+/// A condition of a while loop, such as `RAM[0] <> 0`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub variable: Variable,
+    pub operator: String,
+    pub value: i16,
+}
+
+/// A command of a test script
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Loads a .vm file, a directory of .vm files, or a .hack or .asm file. Without a name, the directory of the script.
+    Load(Option<String>),
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<OutputColumn>),
+    Set { variable: Variable, value: i16 },
+    /// Runs the body a number of times, or forever without a count
+    Repeat { count: Option<u64>, body: Vec<(Command, Span)> },
+    While { condition: Condition, body: Vec<(Command, Span)> },
+    VmStep,
+    TickTock,
+    Output,
+    Echo(String),
+    ClearEcho,
+}
+
+/// The operators of while conditions
+static OPERATORS: [&str; 6] = ["=", "<>", "<", ">", "<=", ">="];
+
+/// A word, a string or a punctuation mark of a test script
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Symbol(char),
+}
+
+/// Splits a test script into tokens, skipping whitespace and comments
+fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, CompileError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let (mut line, mut column) = (1, 1);
+    let mut index = 0;
+    while index < chars.len() {
+        let ch = chars[index];
+        let length = if ch.is_whitespace() {
+            1
+        } else if chars[index..].starts_with(&['/', '/']) {
+            chars[index..].iter().position(|ch| *ch == '\n').unwrap_or(chars.len() - index)
+        } else if chars[index..].starts_with(&['/', '*']) {
+            match chars[index + 2..].windows(2).position(|window| window == ['*', '/']) {
+                Some(position) => { position + 4 }
+                None => { return Err(CompileError::new(Span::new(line, column, 2), "the comment is never closed".to_string())); }
+            }
+        } else if ",;{}".contains(ch) {
+            tokens.push((Token::Symbol(ch), Span::new(line, column, 1)));
+            1
+        } else if ch == '"' {
+            let Some(length) = chars[index + 1..].iter().position(|ch| *ch == '"' || *ch == '\n').filter(|end| chars[index + 1 + end] == '"') else {
+                return Err(CompileError::new(Span::new(line, column, 1), "the text is never closed".to_string()));
+            };
+            let text: String = chars[index + 1..index + 1 + length].iter().collect();
+            tokens.push((Token::Text(text), Span::new(line, column, length + 2)));
+            length + 2
+        } else {
+            let length = chars[index..].iter().position(|ch| ch.is_whitespace() || ",;{}\"".contains(*ch)).unwrap_or(chars.len() - index);
+            let word: String = chars[index..index + length].iter().collect();
+            tokens.push((Token::Word(word), Span::new(line, column, length)));
+            length
+        };
+        for ch in chars[index..index + length].iter() {
+            if *ch == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        index += length;
+    }
+    Ok(tokens)
+}
+
+/// Parses a test script into its commands
+///
+/// # Arguments
+///
+/// * `source` - The contents of a .tst file
+///
+/// # Returns
+///
+/// * The commands with their place in the script, or the first error found
+pub fn parse(source: &str) -> Result<Vec<(Command, Span)>, CompileError> {
+    let tokens = tokenize(source)?;
+    let mut position = 0;
+    let commands = parse_block(&tokens, &mut position)?;
+    if let Some((_, span)) = tokens.get(position) {
+        return Err(CompileError::new(*span, "unexpected '}'".to_string()));
+    }
+    Ok(commands)
+}
+
+/// Parses commands until the end of the script or a closing brace
+fn parse_block(tokens: &[(Token, Span)], position: &mut usize) -> Result<Vec<(Command, Span)>, CompileError> {
+    let mut commands = Vec::new();
+    while let Some((token, span)) = tokens.get(*position) {
+        let span = *span;
+        let name = match token {
+            Token::Symbol('}') => { break; }
+            Token::Symbol(',') | Token::Symbol(';') => {
+                *position += 1;
+                continue;
+            }
+            Token::Word(name) => { name.as_str() }
+            _ => { return Err(CompileError::new(span, "expected a command".to_string())); }
+        };
+        *position += 1;
+
+        // The words up to the end of the command
+        let mut arguments: Vec<(&Token, Span)> = Vec::new();
+        while let Some((token, span)) = tokens.get(*position) {
+            if matches!(token, Token::Symbol(_)) {
+                break;
+            }
+            arguments.push((token, *span));
+            *position += 1;
+        }
+        let words: Vec<&str> = arguments.iter().filter_map(|(token, _)| match token {
+            Token::Word(word) => { Some(word.as_str()) }
+            _ => { None }
+        }).collect();
+        let argument_span = |index: usize| arguments.get(index).map_or(span, |(_, span)| *span);
+        let expect_count = |count: usize| {
+            if arguments.len() == count && words.len() == count {
+                Ok(())
+            } else {
+                Err(CompileError::new(span, format!("{} takes {} argument(s)", name, count)))
+            }
+        };
+
+        let command = match name {
+            "load" => {
+                if arguments.len() > 1 || words.len() != arguments.len() {
+                    return Err(CompileError::new(span, "load takes a single file name".to_string()));
+                }
+                Command::Load(words.first().map(|word| word.to_string()))
+            }
+            "output-file" => {
+                expect_count(1)?;
+                Command::OutputFile(words[0].to_string())
+            }
+            "compare-to" => {
+                expect_count(1)?;
+                Command::CompareTo(words[0].to_string())
+            }
+            "output-list" => {
+                if words.len() != arguments.len() {
+                    return Err(CompileError::new(span, "expected the columns of the output list".to_string()));
+                }
+                let columns = words.iter().enumerate()
+                    .map(|(index, word)| parse_column(word, argument_span(index)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Command::OutputList(columns)
+            }
+            "set" => {
+                expect_count(2)?;
+                Command::Set { variable: parse_variable(words[0], argument_span(0))?, value: parse_value(words[1], argument_span(1))? }
+            }
+            "repeat" => {
+                if words.len() > 1 || words.len() != arguments.len() {
+                    return Err(CompileError::new(span, "repeat takes a number of times".to_string()));
+                }
+                let count = match words.first() {
+                    None => { None }
+                    Some(count) => {
+                        let count = count.parse::<u64>()
+                            .map_err(|_| CompileError::new(argument_span(0), format!("expected a number of times, found '{}'", count)))?;
+                        Some(count)
+                    }
+                };
+                Command::Repeat { count, body: parse_body(tokens, position, name, span)? }
+            }
+            "while" => {
+                if words.len() != 3 || words.len() != arguments.len() || !OPERATORS.contains(&words[1]) {
+                    return Err(CompileError::new(span, "expected a condition such as 'RAM[0] <> 0' after while".to_string()));
+                }
+                let condition = Condition {
+                    variable: parse_variable(words[0], argument_span(0))?,
+                    operator: words[1].to_string(),
+                    value: parse_value(words[2], argument_span(2))?,
+                };
+                Command::While { condition, body: parse_body(tokens, position, name, span)? }
+            }
+            "vmstep" => {
+                expect_count(0)?;
+                Command::VmStep
+            }
+            "ticktock" => {
+                expect_count(0)?;
+                Command::TickTock
+            }
+            "output" => {
+                expect_count(0)?;
+                Command::Output
+            }
+            "echo" => {
+                match arguments.as_slice() {
+                    [(Token::Text(text), _)] => { Command::Echo(text.to_string()) }
+                    _ => { return Err(CompileError::new(span, "echo takes a text in double quotes".to_string())); }
+                }
+            }
+            "clear-echo" => {
+                expect_count(0)?;
+                Command::ClearEcho
+            }
+            _ => { return Err(CompileError::new(span, format!("unknown command '{}'", name))); }
+        };
+        commands.push((command, span));
+    }
+    Ok(commands)
+}
+
+/// Parses the commands between the braces of a repeat or while loop
+fn parse_body(tokens: &[(Token, Span)], position: &mut usize, name: &str, span: Span) -> Result<Vec<(Command, Span)>, CompileError> {
+    if tokens.get(*position).map(|(token, _)| token) != Some(&Token::Symbol('{')) {
+        return Err(CompileError::new(span, format!("expected '{{' after {}", name)));
+    }
+    *position += 1;
+    let body = parse_block(tokens, position)?;
+    if tokens.get(*position).map(|(token, _)| token) != Some(&Token::Symbol('}')) {
+        return Err(CompileError::new(span, format!("the {} is never closed with '}}'", name)));
+    }
+    *position += 1;
+    Ok(body)
+}
+
+/// Parses a variable such as `sp`, `A` or `RAM[256]`
+fn parse_variable(word: &str, span: Span) -> Result<Variable, CompileError> {
+    let bad_variable = || CompileError::new(span, format!("'{}' is not a variable", word));
+    match word.split_once('[') {
+        Some((name, rest)) => {
+            let index = rest.strip_suffix(']').and_then(|index| index.parse::<usize>().ok()).ok_or_else(bad_variable)?;
+            Ok(Variable { name: name.to_string(), index: Some(index) })
+        }
+        None if !word.is_empty() && word.chars().all(|ch| ch.is_ascii_alphabetic()) => {
+            Ok(Variable { name: word.to_string(), index: None })
+        }
+        None => { Err(bad_variable()) }
+    }
+}
+
+/// Parses a value such as `-1`, `%X7FFF`, `%B101` or `%D12`
+fn parse_value(word: &str, span: Span) -> Result<i16, CompileError> {
+    let value = match word.strip_prefix('%') {
+        Some(rest) if rest.starts_with('X') => { i32::from_str_radix(&rest[1..], 16).ok() }
+        Some(rest) if rest.starts_with('B') => { i32::from_str_radix(&rest[1..], 2).ok() }
+        Some(rest) if rest.starts_with('D') => { rest[1..].parse::<i32>().ok() }
+        Some(_) => { None }
+        None => { word.parse::<i32>().ok() }
+    };
+    match value {
+        Some(value) if (-32768..=65535).contains(&value) => { Ok(value as i16) }
+        _ => { Err(CompileError::new(span, format!("'{}' is not a 16-bit value", word))) }
+    }
+}
+
+/// Parses a column of the output list, such as `RAM[0]%D2.6.2`. Without a format, it is `%D1.6.1`.
+fn parse_column(word: &str, span: Span) -> Result<OutputColumn, CompileError> {
+    let (name, format) = word.split_once('%').unwrap_or((word, "D1.6.1"));
+    let bad_format = || CompileError::new(span, format!("'{}' is not a format such as %D1.6.1", format));
+    let mut chars = format.chars();
+    let kind = chars.next().filter(|kind| "DXBS".contains(*kind)).ok_or_else(bad_format)?;
+    let sizes = chars.as_str().split('.').map(|size| size.parse::<usize>().ok()).collect::<Option<Vec<_>>>().ok_or_else(bad_format)?;
+    let [left_padding, width, right_padding] = sizes[..] else { return Err(bad_format()); };
+    Ok(OutputColumn {
+        variable: parse_variable(name, span)?,
+        header: name.to_string(),
+        format: kind,
+        left_padding,
+        width,
+        right_padding,
+    })
+}
+
+/// The program under test
+enum Machine {
+    Vm(Box<Vm>),
+    Cpu(Cpu),
+}
+
+/// What a test script did
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TestReport {
+    /// The lines written to the output file
+    pub output: Vec<String>,
+    /// Whether the script had a compare file
+    pub compared: bool,
+    /// The texts of the echo commands, since the last clear-echo command
+    pub echoes: Vec<String>,
+}
+
+/// Runs the commands of a test script
+pub struct TestRunner {
+    /// The directory of the script, which the file names of the script are relative to
+    directory: PathBuf,
+    machine: Option<Machine>,
+    output_list: Vec<OutputColumn>,
+    output_file: Option<PathBuf>,
+    compare_lines: Option<Vec<String>>,
+    report: TestReport,
+    steps: u64,
+    /// The number of times the bodies of repeat and while loops ran, which is held to the same
+    /// limit as the steps, so a loop that takes no step can't run forever
+    iterations: u64,
+    max_steps: u64,
+}
+
+impl TestRunner {
+    /// Constructor for TestRunner
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory of the script, which the file names of the script are relative to
+    /// * `max_steps` - The number of vm commands or instructions after which the script is stopped
+    pub fn new(directory: &Path, max_steps: u64) -> Self {
+        TestRunner {
+            directory: directory.to_path_buf(),
+            machine: None,
+            output_list: Vec::new(),
+            output_file: None,
+            compare_lines: None,
+            report: TestReport::default(),
+            steps: 0,
+            iterations: 0,
+            max_steps,
+        }
+    }
+
+    /// Runs the commands, comparing every output line with the compare file as it is written
+    ///
+    /// # Returns
+    ///
+    /// * Nothing, or the error that stopped the script, which a comparison failure is
+    pub fn run(&mut self, commands: &[(Command, Span)]) -> Result<(), CompileError> {
+        for (command, span) in commands.iter() {
+            self.execute(command, *span)?;
+        }
+        Ok(())
+    }
+
+    /// What the script did so far
+    pub fn report(&self) -> &TestReport {
+        &self.report
+    }
+
+    /// The output file the script asked for, if any
+    pub fn output_file(&self) -> Option<&Path> {
+        self.output_file.as_deref()
+    }
+
+    /// Counts a run of the body of a loop, stopping the script once there were more than max_steps
+    fn count_iteration(&mut self, span: Span) -> Result<(), CompileError> {
+        self.iterations += 1;
+        if self.iterations > self.max_steps {
+            return Err(CompileError::new(span, format!("the loops of the script ran more than {} times", self.max_steps)));
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, command: &Command, span: Span) -> Result<(), CompileError> {
+        let error = |message: String| CompileError::new(span, message);
+        match command {
+            Command::Load(name) => {
+                let path = name.as_ref().map_or(self.directory.to_path_buf(), |name| self.directory.join(name));
+                let is_cpu_program = path.extension().is_some_and(|extension| extension == "hack" || extension == "asm");
+                let machine = if is_cpu_program {
+                    Machine::Cpu(Cpu::load(&path).map_err(|message| error(message.trim_start_matches("error: ").to_string()))?)
+                } else {
+                    let mut vm = Vm::load(&path).map_err(|message| error(message.trim_start_matches("error: ").to_string()))?;
+                    vm.start_without_call();
+                    Machine::Vm(Box::new(vm))
+                };
+                self.machine = Some(machine);
+            }
+            Command::OutputFile(name) => { self.output_file = Some(self.directory.join(name)); }
+            Command::CompareTo(name) => {
+                let path = self.directory.join(name);
+                let cmp = fs::read_to_string(&path).map_err(|e| error(format!("could not read {}: {}", path.display(), e)))?;
+                self.compare_lines = Some(cmp.lines().map(|line| line.trim_end().to_string()).collect());
+                self.report.compared = true;
+            }
+            Command::OutputList(columns) => {
+                self.output_list = columns.to_vec();
+                let header = columns.iter().map(|column| {
+                    let width = column.left_padding + column.width + column.right_padding;
+                    let name: String = column.header.chars().take(width).collect();
+                    let left = (width - name.chars().count()) / 2;
+                    format!("{}{}{}", " ".repeat(left), name, " ".repeat(width - left - name.chars().count()))
+                }).collect::<Vec<_>>();
+                self.write_line(format!("|{}|", header.join("|")), span)?;
+            }
+            Command::Set { variable, value } => { self.set(variable, *value).map_err(error)?; }
+            Command::Repeat { count, body } => {
+                let mut done = 0;
+                while count.is_none_or(|count| done < count) {
+                    self.count_iteration(span)?;
+                    self.run(body)?;
+                    done += 1;
+                }
+            }
+            Command::While { condition, body } => {
+                while self.holds(condition).map_err(error)? {
+                    self.count_iteration(span)?;
+                    self.run(body)?;
+                }
+            }
+            Command::VmStep => {
+                let Some(Machine::Vm(vm)) = &mut self.machine else {
+                    return Err(error("vmstep needs a vm program to be loaded".to_string()));
+                };
+                self.steps += 1;
+                if self.steps > self.max_steps {
+                    return Err(error(format!("the script ran more than {} steps", self.max_steps)));
+                }
+                // Once the program is done, steps do nothing, as in the VMEmulator
+                vm.step().map_err(error)?;
+            }
+            Command::TickTock => {
+                let Some(Machine::Cpu(cpu)) = &mut self.machine else {
+                    return Err(error("ticktock needs a .hack or .asm program to be loaded".to_string()));
+                };
+                self.steps += 1;
+                if self.steps > self.max_steps {
+                    return Err(error(format!("the script ran more than {} steps", self.max_steps)));
+                }
+                cpu.step().map_err(error)?;
+            }
+            Command::Output => {
+                let values = self.output_list.iter().map(|column| {
+                    let value = self.get(&column.variable)?;
+                    let text = match column.format {
+                        'X' => { last_digits(&format!("{:04X}", value as u16), column.width) }
+                        'B' => { last_digits(&format!("{:016b}", value as u16), column.width) }
+                        _ => { format!("{:>width$}", value, width = column.width) }
+                    };
+                    Ok(format!("{}{}{}", " ".repeat(column.left_padding), text, " ".repeat(column.right_padding)))
+                }).collect::<Result<Vec<_>, String>>().map_err(error)?;
+                self.write_line(format!("|{}|", values.join("|")), span)?;
+            }
+            Command::Echo(text) => { self.report.echoes.push(text.to_string()); }
+            Command::ClearEcho => { self.report.echoes.clear(); }
+        }
+        Ok(())
+    }
+
+    /// Adds a line to the output, and compares it with the same line of the compare file
+    fn write_line(&mut self, line: String, span: Span) -> Result<(), CompileError> {
+        let line_number = self.report.output.len() + 1;
+        self.report.output.push(line);
+        let Some(compare_lines) = &self.compare_lines else { return Ok(()); };
+        let line = &self.report.output[line_number - 1];
+        let expected = compare_lines.get(line_number - 1);
+        // A cell of the compare file holding only `*` matches any value
+        let matches = expected.is_some_and(|expected| {
+            expected.split('|').count() == line.split('|').count()
+                && expected.split('|').zip(line.split('|')).all(|(expected, found)| expected.trim() == "*" || expected == found)
+        });
+        if matches {
+            return Ok(());
+        }
+        Err(CompileError::new(span, format!(
+            "comparison failure at line {}\nexpected: {}\n   found: {}",
+            line_number,
+            expected.map_or("the end of the compare file", |expected| expected.as_str()),
+            line,
+        )))
+    }
+
+    /// Reads a variable of the loaded program
+    fn get(&self, variable: &Variable) -> Result<i16, String> {
+        let address = self.address(variable)?;
+        match &self.machine {
+            Some(Machine::Vm(vm)) => { Ok(vm.peek(address.ok_or_else(|| unknown_variable(variable))?)) }
+            Some(Machine::Cpu(cpu)) => {
+                match (address, variable.name.as_str()) {
+                    (Some(address), _) => { Ok(cpu.peek(address)) }
+                    (None, "A") => { Ok(cpu.a()) }
+                    (None, "D") => { Ok(cpu.d()) }
+                    (None, "PC") => { Ok(cpu.pc() as i16) }
+                    _ => { Err(unknown_variable(variable)) }
+                }
+            }
+            None => { Err("no program is loaded".to_string()) }
+        }
+    }
+
+    /// Changes a variable of the loaded program
+    fn set(&mut self, variable: &Variable, value: i16) -> Result<(), String> {
+        let address = self.address(variable)?;
+        match &mut self.machine {
+            Some(Machine::Vm(vm)) => { vm.poke(address.ok_or_else(|| unknown_variable(variable))?, value); }
+            Some(Machine::Cpu(cpu)) => {
+                match (address, variable.name.as_str()) {
+                    (Some(address), _) => { cpu.poke(address, value); }
+                    (None, "A") => { cpu.set_a(value); }
+                    (None, "D") => { cpu.set_d(value); }
+                    (None, "PC") => { cpu.set_pc(value as u16); }
+                    _ => { return Err(unknown_variable(variable)); }
+                }
+            }
+            None => { return Err("no program is loaded".to_string()); }
+        }
+        Ok(())
+    }
+
+    /// Finds the RAM address of a variable, or None for a register of the CPU
+    fn address(&self, variable: &Variable) -> Result<Option<usize>, String> {
+        let register = |register: usize| match &self.machine {
+            Some(Machine::Vm(vm)) => { vm.peek(register) as u16 as usize }
+            _ => { 0 }
+        };
+        let is_vm = matches!(self.machine, Some(Machine::Vm(_)));
+        let address = match (variable.name.as_str(), variable.index) {
+            ("RAM", Some(index)) => { index }
+            ("sp", None) if is_vm => { SP }
+            ("local", None) if is_vm => { LCL }
+            ("argument", None) if is_vm => { ARG }
+            ("this", None) if is_vm => { THIS }
+            ("that", None) if is_vm => { THAT }
+            ("temp", Some(index)) if is_vm && index < 8 => { TEMP_BASE as usize + index }
+            ("local", Some(index)) if is_vm => { register(LCL) + index }
+            ("argument", Some(index)) if is_vm => { register(ARG) + index }
+            ("this", Some(index)) if is_vm => { register(THIS) + index }
+            ("that", Some(index)) if is_vm => { register(THAT) + index }
+            ("A" | "D" | "PC", None) if !is_vm => { return Ok(None); }
+            _ => { return Err(unknown_variable(variable)); }
+        };
+        if address >= RAM_SIZE {
+            return Err(format!("the address {} of {} is outside the RAM", address, variable.name));
+        }
+        Ok(Some(address))
+    }
+
+    /// Checks the condition of a while loop
+    fn holds(&self, condition: &Condition) -> Result<bool, String> {
+        let value = self.get(&condition.variable)?;
+        Ok(match condition.operator.as_str() {
+            "=" => { value == condition.value }
+            "<>" => { value != condition.value }
+            "<" => { value < condition.value }
+            ">" => { value > condition.value }
+            "<=" => { value <= condition.value }
+            _ => { value >= condition.value }
+        })
+    }
+}
+
+/// The message for a variable the loaded program doesn't have
+fn unknown_variable(variable: &Variable) -> String {
+    match variable.index {
+        Some(index) => { format!("unknown variable '{}[{}]'", variable.name, index) }
+        None => { format!("unknown variable '{}'", variable.name) }
+    }
+}
+
+/// Keeps the last digits of a number, padding it with zeros if it is shorter
+fn last_digits(digits: &str, width: usize) -> String {
+    let padded = format!("{:0>width$}", digits, width = width);
+    padded[padded.len() - width..].to_string()
+}
+
+/// Runs a test script file, and writes its output file
+///
+/// # Arguments
+///
+/// * `path` - The path of the .tst file
+/// * `max_steps` - The number of vm commands or instructions after which the script is stopped
+///
+/// # Returns
+///
+/// * What the script did, or the rendered error that stopped it, which a comparison failure is
+pub fn run_file(path: &Path, max_steps: u64) -> Result<TestReport, String> {
+    let display_path = path.display().to_string();
+    let source = fs::read_to_string(path).map_err(|e| format!("error: could not read {}: {}\n", display_path, e))?;
+    let commands = parse(&source).map_err(|e| e.render(&display_path, &source))?;
+    let mut runner = TestRunner::new(path.parent().unwrap_or(Path::new(".")), max_steps);
+    let result = runner.run(&commands).map_err(|e| e.render(&display_path, &source));
+
+    if let Some(output_file) = runner.output_file() {
+        let output: String = runner.report().output.iter().map(|line| format!("{}\n", line)).collect();
+        fs::write(output_file, output).map_err(|e| format!("error: could not write {}: {}\n", output_file.display(), e))?;
+    }
+    result.map(|_| runner.report().clone())
+}
@@ -1,101 +1,239 @@
-use std::fs;
-use regex::Regex;
-use crate::xmlwriter::XmlWriter;
+use std::io::Write;
 
+use crate::error::{CompileError, Span};
+use crate::xmlwriter::XmlWriter;
 
-static SAVED_KEYWORDS: [&str; 21] = ["class", "constructor", "function", "method", "field", "static", "var", "int", "char", "boolean", "void", "true", "false", "null", "this", "let", "do", "if", "else", "while", "return"];
-static SAVED_SYMBOLS: [&str; 19] = [";", "-", "=", "+", "/", ".", "{", "}", "(", ")", "[", "]", "<", ">", "&", "|", "*", ",", "~"];
 
-//It is was too complicated to transform this function into a class, like the suggested api
-pub fn tokenizer(xml_file_path: String) {
+pub static SAVED_KEYWORDS: [&str; 21] = ["class", "constructor", "function", "method", "field", "static", "var", "int", "char", "boolean", "void", "true", "false", "null", "this", "let", "do", "if", "else", "while", "return"];
+pub static SAVED_SYMBOLS: [&str; 19] = [";", "-", "=", "+", "/", ".", "{", "}", "(", ")", "[", "]", "<", ">", "&", "|", "*", ",", "~"];
 
-    //this way we kick out all the comments:
-    let regex_no_comments:Regex = Regex::new(r#"/\*\*.*\*/|//.*\n|/\*.*\*/\n\*/"#).unwrap();
+/// The largest integer constant jack allows
+pub const MAX_INT_CONST: u16 = 32767;
 
-    let mut xml_writer:XmlWriter = XmlWriter::new(&(xml_file_path.to_owned().split(".jack").collect::<Vec<_>>()[0].to_owned() + "MyT.jack"));
+/// The type of a token, as defined in the jack grammar
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenType {
+    Keyword,
+    Symbol,
+    Identifier,
+    IntConst,
+    StringConst,
+}
 
-    xml_writer.open_tag("tokens".to_string());
-    //reading the data and *it has to be owned other ways regex will not be able to use it*:
-    let file_raw_data =fs::read_to_string(xml_file_path).unwrap().as_str().to_owned();
-    //none readable data that's way next line i transferred it to chars:
-    let after_no_comments = regex_no_comments.replace_all(&file_raw_data, "");
-    //a vectors for all the chars:
-    let mut chars_vec =vec![];
-    for text in after_no_comments.chars() {
-        //print!("{}", text);
-        if text != '\n' || text != ' ' {
-            chars_vec.push(text);
+impl TokenType {
+    /// The xml tag used for tokens of this type
+    pub fn tag(&self) -> &'static str {
+        match self {
+            TokenType::Keyword => { "keyword" }
+            TokenType::Symbol => { "symbol" }
+            TokenType::Identifier => { "identifier" }
+            TokenType::IntConst => { "integerConstant" }
+            TokenType::StringConst => { "stringConstant" }
         }
     }
-    let mut word =String::new();
-    for index in 0..(chars_vec.len()-1)  {
-        //checking if the index is oversize the array:
-        if index <chars_vec.len(){
-            if chars_vec[index] == '/' && chars_vec[index+1] == '*'{
-                while !(chars_vec[index+1] == '/'){
-                    chars_vec.remove(index);
+}
+
+/// A single token of jack code.
+/// The text of a string constant does not include the double quotes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenType,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Token {
+    /// Checks if this token is the given keyword or symbol
+    pub fn is(&self, text: &str) -> bool {
+        matches!(self.kind, TokenType::Keyword | TokenType::Symbol) && self.text == text
+    }
+}
+
+/// Breaks jack code into tokens, skipping whitespace and comments.
+/// Follows the proposed JackTokenizer api: `advance` makes the next token the current one,
+/// and the other methods describe the current token.
+pub struct Tokenizer {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Tokenizer {
+    /// Tokenizes jack code
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The contents of a jack file
+    ///
+    /// # Returns
+    ///
+    /// * The tokenizer, positioned before the first token, or an error at the first invalid token
+    pub fn new(source: &str) -> Result<Self, CompileError> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut tokens = Vec::new();
+        let mut index = 0;
+        let mut line = 1;
+        let mut column = 1;
+
+        while index < chars.len() {
+            let current = chars[index];
+            let next = chars.get(index + 1).copied();
+            let start = index;
+            let span = Span::new(line, column, 1);
+
+            if current.is_whitespace() {
+                index += 1;
+            } else if current == '/' && next == Some('/') {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
                 }
-                chars_vec.remove(index);
-                chars_vec.remove(index);
-            }
-            word.push(chars_vec[index]); //combining all the chars until we get a valid word\symbol\identifier
-            if !(word.contains("\t") || word.contains("\n")) {
-                //checking if the word is a Key word
-                if SAVED_KEYWORDS.contains(&&*word) && (SAVED_SYMBOLS.contains(&&*chars_vec[index+1].to_string())  || (chars_vec[index+1] == ' ')) {
-                    xml_writer.write("keyword".to_string(),word.to_string());
-                    word.clear();
+            } else if current == '/' && next == Some('*') {
+                index += 2;
+                while index < chars.len() && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/')) {
+                    index += 1;
+                }
+                if index >= chars.len() {
+                    return Err(CompileError::new(Span::new(line, column, 2), "unterminated comment, expected '*/'".to_string()));
+                }
+                index += 2;
+            } else if current == '"' {
+                index += 1;
+                while index < chars.len() && chars[index] != '"' && chars[index] != '\n' {
+                    index += 1;
                 }
-                else if word == " " {
-                    word.clear()
+                if chars.get(index) != Some(&'"') {
+                    return Err(CompileError::new(span, "unterminated string constant, expected '\"'".to_string()));
                 }
-                //checking if the word is a string:
-                else if chars_vec[index] == '"' { //checking if the word is a string
-                    word.clear();
-                    chars_vec.remove(index);
-                    while chars_vec[index] != '"' {
-                        word.push(chars_vec[index]);
-                        chars_vec.remove(index);
-                    }
-                    xml_writer.write("stringConstant".to_string(),word.to_string());
-                    word.clear();
+                index += 1;
+                let text: String = chars[start + 1..index - 1].iter().collect();
+                tokens.push(Token { kind: TokenType::StringConst, text, span: Span::new(line, column, index - start) });
+            } else if SAVED_SYMBOLS.contains(&current.to_string().as_str()) {
+                index += 1;
+                tokens.push(Token { kind: TokenType::Symbol, text: current.to_string(), span });
+            } else if current.is_ascii_digit() {
+                while index < chars.len() && chars[index].is_ascii_digit() {
+                    index += 1;
                 }
-                //checking if its a symbol:
-                //******note!!! -> the check for symbols most come before the check for identifier*****
-                else if SAVED_SYMBOLS.contains(&&*word) { //checking if its a symbol
-                    if word == "<"{
-                        xml_writer.write("symbol".to_string(), "&lt;".to_string());
-                    }
-                    else if word ==">"{
-                        xml_writer.write("symbol".to_string(), "&gt;".to_string());
-                    }
-                    else if word == "&" {
-                        xml_writer.write("symbol".to_string(), "&amp;".to_string());
-                    }
-                    else if word == "\"" {
-                        xml_writer.write("symbol".to_string(), "&quet;".to_string());
-                    }
-                    else {
-                        xml_writer.write("symbol".to_string(), word.to_string());
-                    }
-                    word.clear()
+                let text: String = chars[start..index].iter().collect();
+                let span = Span::new(line, column, index - start);
+                if text.parse::<u16>().map_or(true, |value| value > MAX_INT_CONST) {
+                    return Err(CompileError::new(span, format!("integer constant {} is larger than {}", text, MAX_INT_CONST)));
                 }
-                // checking if its identifier:
-                //******note!!! -> the check for symbols most come before the check for identifier*****
-                else if SAVED_SYMBOLS.contains(&&*chars_vec[index + 1].to_string()) || chars_vec[index+1] ==' ' {
-                    if word.parse::<i32>().is_ok() {
-                        xml_writer.write("integerConstant".to_string(), word.to_string());
-                    }
-                    else {
-                        xml_writer.write("identifier".to_string(), word.to_string());
-                    }
-                    word.clear();
+                tokens.push(Token { kind: TokenType::IntConst, text, span });
+            } else if current.is_ascii_alphabetic() || current == '_' {
+                while index < chars.len() && (chars[index].is_ascii_alphanumeric() || chars[index] == '_') {
+                    index += 1;
                 }
+                let text: String = chars[start..index].iter().collect();
+                let kind = if SAVED_KEYWORDS.contains(&text.as_str()) { TokenType::Keyword } else { TokenType::Identifier };
+                tokens.push(Token { kind, text, span: Span::new(line, column, index - start) });
+            } else {
+                return Err(CompileError::new(span, format!("unexpected character '{}'", current)));
             }
-            else {
-                word.clear();
-            }//if the word is \n or \t or white space it removes it
+
+            // moves the line and column past everything that was consumed
+            for consumed in chars[start..index].iter() {
+                if *consumed == '\n' {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+            }
+        }
+
+        Ok(Tokenizer {
+            tokens,
+            next: 0,
+        })
+    }
+
+    /// Are there more tokens in the input?
+    pub fn has_more_tokens(&self) -> bool {
+        self.next < self.tokens.len()
+    }
+
+    /// Gets the next token from the input, and makes it the current token.
+    /// Should be called only if has_more_tokens is true.
+    pub fn advance(&mut self) {
+        if self.has_more_tokens() {
+            self.next += 1;
         }
     }
 
-    xml_writer.close_tag("tokens".to_string());
-}
\ No newline at end of file
+    /// Returns the current token, or None before the first advance
+    pub fn current(&self) -> Option<&Token> {
+        self.next.checked_sub(1).and_then(|index| self.tokens.get(index))
+    }
+
+    /// Returns the token after the current one, without advancing
+    pub fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    /// Returns the type of the current token
+    pub fn token_type(&self) -> Option<TokenType> {
+        self.current().map(|token| token.kind)
+    }
+
+    /// Returns the keyword which is the current token.
+    /// Returns None if token_type is not Keyword.
+    pub fn keyword(&self) -> Option<&str> {
+        self.text_of(TokenType::Keyword)
+    }
+
+    /// Returns the character which is the current token.
+    /// Returns None if token_type is not Symbol.
+    pub fn symbol(&self) -> Option<char> {
+        self.text_of(TokenType::Symbol).and_then(|text| text.chars().next())
+    }
+
+    /// Returns the identifier which is the current token.
+    /// Returns None if token_type is not Identifier.
+    pub fn identifier(&self) -> Option<&str> {
+        self.text_of(TokenType::Identifier)
+    }
+
+    /// Returns the integer value of the current token.
+    /// Returns None if token_type is not IntConst.
+    pub fn int_val(&self) -> Option<u16> {
+        self.text_of(TokenType::IntConst).and_then(|text| text.parse().ok())
+    }
+
+    /// Returns the string value of the current token, without the double quotes.
+    /// Returns None if token_type is not StringConst.
+    pub fn string_val(&self) -> Option<&str> {
+        self.text_of(TokenType::StringConst)
+    }
+
+    /// Returns every token of the input, regardless of the current position
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Writes every token of the input to an xml file, inside a <tokens> tag
+    pub fn write_xml<W: Write>(&self, xml_writer: &mut XmlWriter<W>) -> std::io::Result<()> {
+        xml_writer.open_tag("tokens".to_string())?;
+        for token in self.tokens.iter() {
+            xml_writer.write(token.kind.tag().to_string(), token.text.to_string())?;
+        }
+        xml_writer.close_tag("tokens".to_string())
+    }
+
+    /// Returns the text of the current token if it is of the given type
+    fn text_of(&self, kind: TokenType) -> Option<&str> {
+        self.current().filter(|token| token.kind == kind).map(|token| token.text.as_str())
+    }
+}
+
+impl Iterator for Tokenizer {
+    type Item = Token;
+
+    /// Advances, and returns the new current token
+    fn next(&mut self) -> Option<Token> {
+        if !self.has_more_tokens() {
+            return None;
+        }
+        self.advance();
+        self.current().cloned()
+    }
+}
@@ -0,0 +1,400 @@
+/// This file contains the optional type checker, which walks the syntax tree of a compiled class
+/// and checks that every value is used where its type fits
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{Class, Evaluation, Expression, KeywordConstant, Name, Op, Statement, SubroutineCall, SubroutineDec, Term, UnaryOp};
+use crate::error::{CompileError, Span, Warning};
+use crate::signatures::Signatures;
+use crate::utility::Precedence;
+
+/// The type of a jack value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Int,
+    Char,
+    Boolean,
+    /// The value of `null`, which fits every object
+    Null,
+    /// What a subroutine declared `void` returns, which can't be used
+    Void,
+    /// An object of the named class, `Array` being an address of any kind of data
+    Object(String),
+    /// The value of an array entry or of a call to a class whose subroutines are unknown, which fits everything
+    Unknown,
+}
+
+impl Type {
+    /// Finds the type written as a name in a declaration
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "int" => { Type::Int }
+            "char" => { Type::Char }
+            "boolean" => { Type::Boolean }
+            "void" => { Type::Void }
+            _ => { Type::Object(name.to_string()) }
+        }
+    }
+
+    /// Whether the type is a number, which int and char both are in jack
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Char)
+    }
+
+    /// Whether the type is an address, of an object or of nothing
+    fn is_reference(&self) -> bool {
+        matches!(self, Type::Object(_) | Type::Null)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => { write!(f, "int") }
+            Type::Char => { write!(f, "char") }
+            Type::Boolean => { write!(f, "boolean") }
+            Type::Null => { write!(f, "null") }
+            Type::Void => { write!(f, "void") }
+            Type::Object(class_name) => { write!(f, "{}", class_name) }
+            Type::Unknown => { write!(f, "unknown") }
+        }
+    }
+}
+
+/// How well a value fits where a type is expected
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Fit {
+    Fits,
+    /// Jack allows it, but it is likely a mistake, such as an int used as a boolean
+    Doubtful,
+    Mismatch,
+}
+
+/// Finds how well a value of type `found` fits where a value of type `expected` is expected
+fn fit(expected: &Type, found: &Type) -> Fit {
+    match (expected, found) {
+        (Type::Unknown, _) | (_, Type::Unknown) => { Fit::Fits }
+        _ if expected == found || (expected.is_numeric() && found.is_numeric()) => { Fit::Fits }
+        (Type::Object(_), Type::Null) => { Fit::Fits }
+        (Type::Object(expected), Type::Object(found)) if expected == "Array" || found == "Array" => { Fit::Fits }
+        (Type::Object(expected), found) if expected == "Array" && found.is_numeric() => { Fit::Doubtful }
+        (Type::Boolean, found) if found.is_numeric() => { Fit::Doubtful }
+        (expected, Type::Boolean) if expected.is_numeric() => { Fit::Doubtful }
+        _ => { Fit::Mismatch }
+    }
+}
+
+/// Checks the types of the values of a class. Mismatches that jack allows, but are likely mistakes,
+/// such as an int used as a condition, are warnings; the others are errors.
+pub struct TypeChecker<'a> {
+    /// The subroutines the calls go to
+    signatures: &'a Signatures,
+    precedence: Precedence,
+    class_name: String,
+    /// The types of the statics and fields
+    class_variables: HashMap<String, Type>,
+    /// The types of the arguments and locals of the subroutine being checked
+    variables: HashMap<String, Type>,
+    /// The full name of the subroutine being checked
+    subroutine_name: String,
+    return_type: Type,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> TypeChecker<'a> {
+    /// Gets ready to check classes
+    ///
+    /// # Arguments
+    ///
+    /// * `signatures` - The subroutines of the program, including those of the checked class
+    /// * `precedence` - How the operators of an expression are ordered, which decides the types of the operands
+    pub fn new(signatures: &'a Signatures, precedence: Precedence) -> Self {
+        TypeChecker {
+            signatures,
+            precedence,
+            class_name: String::new(),
+            class_variables: HashMap::new(),
+            variables: HashMap::new(),
+            subroutine_name: String::new(),
+            return_type: Type::Void,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// The warnings found while checking
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Checks every subroutine of a class
+    ///
+    /// # Returns
+    ///
+    /// * Nothing, or the first type mismatch that is an error
+    pub fn check(&mut self, class: &Class) -> Result<(), CompileError> {
+        self.class_name = class.name.name.to_string();
+        self.class_variables = class.var_decs.iter()
+            .flat_map(|var_dec| var_dec.names.iter().map(|name| (name.name.to_string(), Type::from_name(&var_dec.data_type.name))))
+            .collect();
+        for subroutine in class.subroutines.iter() {
+            self.check_subroutine(subroutine)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the statements of a subroutine
+    fn check_subroutine(&mut self, subroutine: &SubroutineDec) -> Result<(), CompileError> {
+        self.subroutine_name = format!("{}.{}", self.class_name, subroutine.name.name);
+        self.return_type = Type::from_name(&subroutine.return_type.name);
+        self.variables = subroutine.parameters.iter()
+            .map(|parameter| (parameter.name.name.to_string(), Type::from_name(&parameter.data_type.name)))
+            .chain(subroutine.var_decs.iter()
+                .flat_map(|var_dec| var_dec.names.iter().map(|name| (name.name.to_string(), Type::from_name(&var_dec.data_type.name)))))
+            .collect();
+        self.check_statements(&subroutine.statements)
+    }
+
+    /// Checks a list of statements
+    fn check_statements(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+        for statement in statements.iter() {
+            match statement {
+                Statement::Let { name, index, value, .. } => {
+                    let value_type = self.type_of(value)?;
+                    match index {
+                        Some(index) => {
+                            self.check_indexed(name)?;
+                            let index_type = self.type_of(index)?;
+                            self.expect(&Type::Int, &index_type, &format!("the index of '{}'", name.name), index.span())?;
+                        }
+                        None => {
+                            let variable_type = self.variable_type(&name.name);
+                            self.expect(&variable_type, &value_type, &format!("the assignment to '{}'", name.name), value.span())?;
+                        }
+                    }
+                }
+                Statement::If { condition, statements, else_statements, .. } => {
+                    self.check_condition(condition)?;
+                    self.check_statements(statements)?;
+                    if let Some(else_statements) = else_statements {
+                        self.check_statements(else_statements)?;
+                    }
+                }
+                Statement::While { condition, statements, .. } => {
+                    self.check_condition(condition)?;
+                    self.check_statements(statements)?;
+                }
+                Statement::Do { call, .. } => { self.check_call(call)?; }
+                Statement::Return { value, .. } => {
+                    // a missing or extra return value is a mistake of its own, not a type mismatch
+                    if let Some(value) = value {
+                        let value_type = self.type_of(value)?;
+                        if self.return_type != Type::Void {
+                            let return_type = self.return_type.clone();
+                            self.expect(&return_type, &value_type, &format!("the return value of {}", self.subroutine_name), value.span())?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the condition of an if or while statement is a boolean
+    fn check_condition(&mut self, condition: &Expression) -> Result<(), CompileError> {
+        let condition_type = self.type_of(condition)?;
+        let message = format!("the condition is of type {}, not boolean", condition_type);
+        match fit(&Type::Boolean, &condition_type) {
+            Fit::Fits => { Ok(()) }
+            Fit::Doubtful => {
+                self.warnings.push(Warning::new(condition.span(), message));
+                Ok(())
+            }
+            Fit::Mismatch => { Err(CompileError::new(condition.span(), message)) }
+        }
+    }
+
+    /// Checks that a value fits where a type is expected
+    ///
+    /// # Arguments
+    ///
+    /// * `expected` - The type the value should have
+    /// * `found` - The type of the value
+    /// * `context` - Where the value is used, for the message
+    /// * `span` - The place of the value
+    fn expect(&mut self, expected: &Type, found: &Type, context: &str, span: Span) -> Result<(), CompileError> {
+        let message = format!("type mismatch in {}: expected {}, found {}", context, expected, found);
+        match fit(expected, found) {
+            Fit::Fits => { Ok(()) }
+            Fit::Doubtful => {
+                self.warnings.push(Warning::new(span, message));
+                Ok(())
+            }
+            Fit::Mismatch => { Err(CompileError::new(span, message)) }
+        }
+    }
+
+    /// Checks that a variable is indexed only if it is an Array
+    fn check_indexed(&self, name: &Name) -> Result<(), CompileError> {
+        match self.variable_type(&name.name) {
+            Type::Unknown => { Ok(()) }
+            Type::Object(class_name) if class_name == "Array" => { Ok(()) }
+            data_type => {
+                Err(CompileError::new(name.span, format!("'{}' is of type {}, only an Array can be indexed", name.name, data_type)))
+            }
+        }
+    }
+
+    /// The declared type of a variable, or Unknown if it isn't declared
+    fn variable_type(&self, name: &str) -> Type {
+        self.variables.get(name).or_else(|| self.class_variables.get(name)).cloned().unwrap_or(Type::Unknown)
+    }
+
+    /// Finds the type of an expression, applying the operators in the same order as the compiled code
+    fn type_of(&mut self, expression: &Expression) -> Result<Type, CompileError> {
+        let mut operands = Vec::new();
+        for evaluation in expression.evaluation_order(self.precedence) {
+            match evaluation {
+                Evaluation::Term(term) => { operands.push(self.term_type(term)?); }
+                Evaluation::Op(op, span) => { self.apply(&mut operands, op, span)?; }
+            }
+        }
+        Ok(operands.pop().unwrap_or(Type::Unknown))
+    }
+
+    /// Replaces the two operands on top of the stack with the type of the operator applied to them
+    fn apply(&mut self, operands: &mut Vec<Type>, op: Op, span: Span) -> Result<(), CompileError> {
+        let right = operands.pop().unwrap_or(Type::Unknown);
+        let left = operands.pop().unwrap_or(Type::Unknown);
+        let result = match op {
+            Op::Add | Op::Sub | Op::Mul | Op::Div => {
+                self.check_number(op.symbol(), &left, span)?;
+                self.check_number(op.symbol(), &right, span)?;
+                Type::Int
+            }
+            Op::Lt | Op::Gt => {
+                self.check_number(op.symbol(), &left, span)?;
+                self.check_number(op.symbol(), &right, span)?;
+                Type::Boolean
+            }
+            Op::And | Op::Or => {
+                for operand in [&left, &right] {
+                    if operand.is_reference() || *operand == Type::Void {
+                        return Err(CompileError::new(span, format!("'{}' can't be applied to a value of type {}", op.symbol(), operand)));
+                    }
+                }
+                match (&left, &right) {
+                    (Type::Boolean, Type::Boolean) | (Type::Boolean, Type::Unknown) | (Type::Unknown, Type::Boolean) => { Type::Boolean }
+                    (Type::Unknown, Type::Unknown) => { Type::Unknown }
+                    (Type::Boolean, _) | (_, Type::Boolean) => {
+                        self.warnings.push(Warning::new(span, format!("'{}' is applied to a boolean and a number", op.symbol())));
+                        Type::Int
+                    }
+                    _ => { Type::Int }
+                }
+            }
+            Op::Eq => {
+                let group = |operand: &Type| if operand.is_numeric() { 0 } else if operand.is_reference() { 1 } else { 2 };
+                if left != Type::Unknown && right != Type::Unknown && group(&left) != group(&right) {
+                    self.warnings.push(Warning::new(span, format!("'=' compares a value of type {} with a value of type {}", left, right)));
+                }
+                Type::Boolean
+            }
+        };
+        operands.push(result);
+        Ok(())
+    }
+
+    /// Checks an operand of an arithmetic operator, which should be a number
+    fn check_number(&mut self, symbol: &str, operand: &Type, span: Span) -> Result<(), CompileError> {
+        match operand {
+            Type::Int | Type::Char | Type::Unknown => { Ok(()) }
+            Type::Boolean => {
+                self.warnings.push(Warning::new(span, format!("'{}' is applied to a value of type boolean", symbol)));
+                Ok(())
+            }
+            Type::Object(class_name) if class_name == "Array" => {
+                // the address arithmetic of Memory.peek(a + 1) and the like
+                self.warnings.push(Warning::new(span, format!("'{}' is applied to a value of type Array", symbol)));
+                Ok(())
+            }
+            _ => { Err(CompileError::new(span, format!("'{}' can't be applied to a value of type {}", symbol, operand))) }
+        }
+    }
+
+    /// Finds the type of a term
+    fn term_type(&mut self, term: &Term) -> Result<Type, CompileError> {
+        let term_type = match term {
+            Term::IntegerConstant { .. } => { Type::Int }
+            Term::StringConstant { .. } => { Type::Object("String".to_string()) }
+            Term::KeywordConstant { keyword, .. } => {
+                match keyword {
+                    KeywordConstant::True | KeywordConstant::False => { Type::Boolean }
+                    KeywordConstant::Null => { Type::Null }
+                    KeywordConstant::This => { Type::Object(self.class_name.to_string()) }
+                }
+            }
+            Term::Variable(name) => { self.variable_type(&name.name) }
+            Term::ArrayEntry { name, index } => {
+                self.check_indexed(name)?;
+                let index_type = self.type_of(index)?;
+                self.expect(&Type::Int, &index_type, &format!("the index of '{}'", name.name), index.span())?;
+                Type::Unknown
+            }
+            Term::Call(call) => {
+                let return_type = self.check_call(call)?;
+                if return_type == Type::Void {
+                    return Err(CompileError::new(call.span(), format!(
+                        "{}.{} returns void, so it has no value to use", self.class_of(call), call.name.name
+                    )));
+                }
+                return_type
+            }
+            Term::Parenthesized { expression, .. } => { self.type_of(expression)? }
+            Term::Unary { op, term, span } => {
+                let operand = self.term_type(term)?;
+                match op {
+                    UnaryOp::Neg => {
+                        self.check_number(op.symbol(), &operand, *span)?;
+                        Type::Int
+                    }
+                    UnaryOp::Not if operand.is_reference() || operand == Type::Void => {
+                        return Err(CompileError::new(*span, format!("'~' can't be applied to a value of type {}", operand)));
+                    }
+                    UnaryOp::Not if operand.is_numeric() => { Type::Int }
+                    UnaryOp::Not => { operand }
+                }
+            }
+        };
+        Ok(term_type)
+    }
+
+    /// The class of the subroutine a call goes to
+    fn class_of(&self, call: &SubroutineCall) -> String {
+        match &call.receiver {
+            Some(receiver) => {
+                match self.variable_type(&receiver.name) {
+                    Type::Object(class_name) => { class_name }
+                    _ => { receiver.name.to_string() }
+                }
+            }
+            None => { self.class_name.to_string() }
+        }
+    }
+
+    /// Checks the types of the arguments of a call
+    ///
+    /// # Returns
+    ///
+    /// * The type the called subroutine returns, or Unknown if its signature is unknown
+    fn check_call(&mut self, call: &SubroutineCall) -> Result<Type, CompileError> {
+        let class_name = self.class_of(call);
+        let argument_types = call.arguments.iter().map(|argument| self.type_of(argument)).collect::<Result<Vec<_>, _>>()?;
+        let Some(signature) = self.signatures.subroutine(&class_name, &call.name.name) else { return Ok(Type::Unknown); };
+        let signature = signature.clone();
+        for (index, ((parameter, argument), found)) in signature.parameters.iter().zip(call.arguments.iter()).zip(argument_types.iter()).enumerate() {
+            let context = format!("argument {} of {}.{}", index + 1, class_name, call.name.name);
+            self.expect(&Type::from_name(parameter), found, &context, argument.span())?;
+        }
+        Ok(Type::from_name(&signature.return_type))
+    }
+}
@@ -1,5 +1,8 @@
 /// This file contains all the constants/data types that i have defined, along the proposed implementation
-#[derive(Clone, Copy, PartialEq)]
+use crate::error::Span;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Kind {
     STATIC,
     FIELD,
@@ -8,6 +11,39 @@ pub enum Kind {
     NONE,
 }
 
+impl Kind {
+    /// What a variable of the kind is called in messages
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Kind::STATIC => { "static variable" }
+            Kind::FIELD => { "field" }
+            Kind::ARG => { "argument" }
+            Kind::VAR => { "local variable" }
+            Kind::NONE => { "name" }
+        }
+    }
+
+    /// The short name of the kind, as in the symbol tables of nand2tetris chapter 11
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Kind::STATIC => { "static" }
+            Kind::FIELD => { "field" }
+            Kind::ARG => { "argument" }
+            Kind::VAR => { "local" }
+            Kind::NONE => { "none" }
+        }
+    }
+}
+
+/// How the binary operators of an expression are ordered
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precedence {
+    /// Strictly from left to right, as the jack language defines
+    LeftToRight,
+    /// `* /` before `+ -` before `< > =` before `& |`, and from left to right within a level
+    Standard,
+}
+
 pub const ADD: usize = 1;
 pub const SUB: usize = 2;
 pub const NEG: usize = 3;
@@ -24,32 +60,57 @@ pub static KEYWORD_CONSTANT: [&str; 4] = ["true", "false", "null", "this"];
 pub static CLASS_VAR_TYPES: [&str; 2] = ["static", "field"];
 pub static DATA_TYPES: [&str; 4] = ["int", "boolean", "char", "void"];
 pub static OP: [&str; 9] = ["+", "-", "*", "/", "&", "|", "<", ">", "="];
+/// The conventional precedence level of every operator in OP. Higher levels are applied first.
+pub static OP_PRECEDENCE: [usize; 9] = [3, 3, 4, 4, 1, 1, 2, 2, 2];
 pub static UNARY_OP: [&str; 2] = ["-", "~"];
 pub static CLASS_FUNC_TYPES: [&str; 3] = ["function", "method", "constructor"];
 
 
+/// Finds the conventional precedence level of a binary operator
+///
+/// # Arguments
+///
+/// * `op` - The symbol of the operator
+///
+/// # Returns
+///
+/// * The level from OP_PRECEDENCE, or 0 if the symbol is not an operator
+pub fn op_precedence(op: &str) -> usize {
+    OP.iter().position(|symbol| *symbol == op).map_or(0, |index| OP_PRECEDENCE[index])
+}
+
 /// Struct for cleaner code.
+#[derive(Clone, Debug)]
 pub struct Symbol {
     name: String,
     data_type: String,
     kind: Kind,
     index: usize,
+    /// Where the symbol is declared
+    span: Span,
+    /// Whether the code reads the value of the symbol
+    read: bool,
+    /// Whether the code assigns to the symbol
+    written: bool,
 }
 
 impl Symbol {
     /// Constructor for Symbol. For cleaner code.
-    pub fn new(name: String, data_type: String, kind: Kind, index: usize) -> Self {
+    pub fn new(name: String, data_type: String, kind: Kind, index: usize, span: Span) -> Self {
         Symbol {
             name,
             data_type,
             kind,
             index,
+            span,
+            read: false,
+            written: false,
         }
     }
 
     /// Getter for name of the symbol
-    pub fn get_name(&self) -> String {
-        self.name.to_string()
+    pub fn get_name(&self) -> &str {
+        &self.name
     }
 
     /// Getter for kind of the symbol
@@ -64,12 +125,40 @@ impl Symbol {
     }
 
     /// Getter for data type of the symbol
-    pub fn get_data_type(&self) -> String {
-        self.data_type.to_string()
+    pub fn get_data_type(&self) -> &str {
+        &self.data_type
     }
 
     /// Getter for index of the symbol
     pub fn get_index(&self) -> usize {
         self.index
     }
-}
\ No newline at end of file
+
+    /// Getter for the place the symbol is declared
+    pub fn get_span(&self) -> Span {
+        self.span
+    }
+
+    /// Whether the code reads the value of the symbol
+    pub fn is_read(&self) -> bool {
+        self.read
+    }
+
+    /// Whether the code assigns to the symbol
+    pub fn is_written(&self) -> bool {
+        self.written
+    }
+
+    /// Records a use of the symbol
+    ///
+    /// # Arguments
+    ///
+    /// * `written` - Whether the use assigns to the symbol, rather than reads it
+    pub fn mark_used(&mut self, written: bool) {
+        if written {
+            self.written = true;
+        } else {
+            self.read = true;
+        }
+    }
+}
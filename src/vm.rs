@@ -0,0 +1,520 @@
+/// This file contains the vm interpreter, which runs compiled programs without the VMEmulator
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use crate::jack_os::{JackOs, Native, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::key_script::KeyEvent;
+use crate::vm2asm::{POINTER_BASE, STACK_BASE, TEMP_BASE};
+use crate::vm_command::{parse, ArithmeticCommand, Segment, VmCommand};
+
+/// The number of words in the RAM
+pub const RAM_SIZE: usize = 32768;
+/// The RAM address of the first static variable
+pub const STATIC_BASE: u16 = 16;
+/// The RAM address the heap starts at, which is where the stack must end
+pub const HEAP_BASE: u16 = 2048;
+/// The RAM address of the screen memory map
+pub const SCREEN: usize = 16384;
+/// The RAM address of the keyboard memory map
+pub const KEYBOARD: usize = 24576;
+
+/// The RAM addresses of the registers
+pub const SP: usize = 0;
+pub const LCL: usize = 1;
+pub const ARG: usize = 2;
+pub const THIS: usize = 3;
+pub const THAT: usize = 4;
+
+/// The Sys.init of programs that don't define it: like the OS, it runs Main.main and halts.
+/// The other OS classes need no initialization, as they are native.
+static SYS_INIT: &str = "function Sys.init 0
+call Main.main 0
+pop temp 0
+call Sys.halt 0
+";
+
+/// A vm command, with its labels and static variables resolved to addresses
+#[derive(Clone, Debug)]
+enum Instruction {
+    Push(Segment, u16),
+    Pop(Segment, u16),
+    Arithmetic(ArithmeticCommand),
+    Label,
+    Goto(usize),
+    IfGoto(usize),
+    Function { locals: u16 },
+    Call { name: String, arguments: u16 },
+    Return,
+}
+
+/// Where an instruction was written, for runtime errors
+#[derive(Clone, Debug)]
+struct Location {
+    file: String,
+    line: usize,
+    function: String,
+}
+
+/// Why the interpreter stopped running
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stop {
+    /// The function the program was started from returned
+    Returned,
+    /// The program called Sys.halt
+    Halted,
+    /// The maximum number of steps was reached while the program was still running
+    StepLimit,
+}
+
+/// The vm interpreter. It models the 32K RAM of the hack computer, with the stack, the registers
+/// and the segments in the same addresses the VMEmulator uses.
+/// The OS functions the program doesn't define itself run natively.
+pub struct Vm {
+    ram: Vec<i16>,
+    instructions: Vec<Instruction>,
+    locations: Vec<Location>,
+    functions: HashMap<String, usize>,
+    /// The RAM address of the first static variable of the next loaded file
+    next_static: usize,
+    os: JackOs,
+    /// The instruction to return to, for every function call that didn't return yet.
+    /// `None` is the return from the function the program was started from.
+    return_addresses: Vec<Option<usize>>,
+    pc: Option<usize>,
+    steps: u64,
+    halted: bool,
+    /// The scripted key events that didn't happen yet
+    key_events: VecDeque<KeyEvent>,
+}
+
+impl Vm {
+    /// Loads the vm code of a program
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The name of every vm file without its extension, and its contents
+    ///
+    /// # Returns
+    ///
+    /// * The interpreter, ready to start running the program, or the rendered error of the first bad command
+    pub fn new(files: &[(String, String)]) -> Result<Self, String> {
+        let mut vm = Vm {
+            ram: vec![0; RAM_SIZE],
+            instructions: Vec::new(),
+            locations: Vec::new(),
+            functions: HashMap::new(),
+            next_static: STATIC_BASE as usize,
+            os: JackOs::new(),
+            return_addresses: Vec::new(),
+            pc: None,
+            steps: 0,
+            halted: false,
+            key_events: VecDeque::new(),
+        };
+        for (file_name, code) in files.iter() {
+            vm.load_file(file_name, code)?;
+        }
+        if !vm.has_function("Sys.init") && vm.has_function("Main.main") {
+            vm.load_file("Sys", SYS_INIT)?;
+        }
+        Ok(vm)
+    }
+
+    /// Adds the commands of a vm file to the program
+    fn load_file(&mut self, file_name: &str, code: &str) -> Result<(), String> {
+        let display_path = format!("{}.vm", file_name);
+        let commands = parse(code).map_err(|e| e.render(&display_path, code))?;
+        let first = self.instructions.len();
+
+        // The labels of a function are known before its gotos are resolved
+        let mut labels: HashMap<(String, String), usize> = HashMap::new();
+        let mut function = file_name.to_string();
+        for (offset, (command, _)) in commands.iter().enumerate() {
+            match command {
+                VmCommand::Function { name, .. } => {
+                    if self.functions.insert(name.to_string(), first + offset).is_some() {
+                        return Err(format!("error: the function {} is defined more than once", name));
+                    }
+                    function = name.to_string();
+                }
+                VmCommand::Label(label) => { labels.insert((function.to_string(), label.to_string()), first + offset); }
+                _ => {}
+            }
+        }
+
+        let mut static_count = 0;
+        let mut function = file_name.to_string();
+        for (command, span) in commands.iter() {
+            if let VmCommand::Function { name, .. } = command {
+                function = name.to_string();
+            }
+            let location = Location { file: display_path.to_string(), line: span.line, function: function.to_string() };
+            let find_label = |label: &String| {
+                labels.get(&(function.to_string(), label.to_string())).copied()
+                    .ok_or_else(|| format!("error: {}:{}: the label {} is not defined in {}", display_path, span.line, label, function))
+            };
+            let instruction = match command {
+                VmCommand::Push { segment: Segment::Static, index } | VmCommand::Pop { segment: Segment::Static, index } => {
+                    static_count = static_count.max(*index as usize + 1);
+                    let address = (self.next_static + *index as usize) as u16;
+                    if matches!(command, VmCommand::Push { .. }) {
+                        Instruction::Push(Segment::Static, address)
+                    } else {
+                        Instruction::Pop(Segment::Static, address)
+                    }
+                }
+                VmCommand::Push { segment, index } => { Instruction::Push(*segment, *index) }
+                VmCommand::Pop { segment, index } => { Instruction::Pop(*segment, *index) }
+                VmCommand::Arithmetic(command) => { Instruction::Arithmetic(*command) }
+                VmCommand::Label(_) => { Instruction::Label }
+                VmCommand::Goto(label) => { Instruction::Goto(find_label(label)?) }
+                VmCommand::IfGoto(label) => { Instruction::IfGoto(find_label(label)?) }
+                VmCommand::Function { locals, .. } => { Instruction::Function { locals: *locals } }
+                VmCommand::Call { name, arguments } => { Instruction::Call { name: name.to_string(), arguments: *arguments } }
+                VmCommand::Return => { Instruction::Return }
+            };
+            self.instructions.push(instruction);
+            self.locations.push(location);
+        }
+        self.next_static += static_count;
+        if self.next_static > STACK_BASE as usize {
+            return Err(format!("error: the static variables of the program don't fit below the stack, at {}", STACK_BASE));
+        }
+        Ok(())
+    }
+
+    /// Loads every .vm file of a directory, or a single .vm file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut paths = Vec::new();
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path).map_err(|e| format!("error: could not read {}: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|entry| entry.extension().is_some_and(|extension| extension == "vm"))
+                .collect::<Vec<_>>();
+            entries.sort();
+            paths.append(&mut entries);
+        } else {
+            paths.push(path.to_path_buf());
+        }
+
+        let mut files = Vec::new();
+        for path in paths {
+            let code = fs::read_to_string(&path).map_err(|e| format!("error: could not read {}: {}", path.display(), e))?;
+            files.push((path.file_stem().unwrap_or_default().to_string_lossy().to_string(), code));
+        }
+        Vm::new(&files)
+    }
+
+    /// Whether the program defines the given function
+    pub fn has_function(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Gets ready to run the program from a function, called without arguments.
+    /// Like the bootstrap code, the stack starts at 256.
+    pub fn start(&mut self, function: &str) -> Result<(), String> {
+        self.ram[SP] = STACK_BASE as i16;
+        self.return_addresses.clear();
+        self.steps = 0;
+        self.halted = false;
+        self.pc = None;
+        self.call(function, 0, None)
+    }
+
+    /// Gets ready to run the program as the VMEmulator does after loading it: from the first command
+    /// of Sys.init without calling it, or from the first command of the program if there is no Sys.init.
+    /// The RAM is left as it is, so a test script can set up the stack and the segments.
+    pub fn start_without_call(&mut self) {
+        self.return_addresses.clear();
+        self.steps = 0;
+        self.halted = false;
+        self.pc = match self.functions.get("Sys.init") {
+            Some(&target) => { Some(target) }
+            None if self.instructions.is_empty() => { None }
+            None => { Some(0) }
+        };
+    }
+
+    /// Runs until the program stops or the maximum number of steps is reached.
+    /// Every vm command is a single step.
+    ///
+    /// # Returns
+    ///
+    /// * Why the program stopped, or the message of the runtime error that stopped it
+    pub fn run(&mut self, max_steps: u64) -> Result<Stop, String> {
+        while self.steps < max_steps {
+            if let Some(stop) = self.step()? {
+                return Ok(stop);
+            }
+        }
+        Ok(Stop::StepLimit)
+    }
+
+    /// Runs a single vm command
+    ///
+    /// # Returns
+    ///
+    /// * Why the program stopped if it did, or the message of the runtime error
+    pub fn step(&mut self) -> Result<Option<Stop>, String> {
+        if self.halted {
+            return Ok(Some(Stop::Halted));
+        }
+        let Some(pc) = self.pc else { return Ok(Some(Stop::Returned)); };
+        while let Some(event) = self.key_events.front().filter(|event| event.step <= self.steps) {
+            self.ram[KEYBOARD] = event.key;
+            self.key_events.pop_front();
+        }
+        let Some(instruction) = self.instructions.get(pc).cloned() else {
+            return Err("runtime error: the program ran past its last command".to_string());
+        };
+        self.steps += 1;
+        self.pc = Some(pc + 1);
+        self.execute(instruction).map_err(|message| self.runtime_error(pc, &message))?;
+
+        if self.halted {
+            return Ok(Some(Stop::Halted));
+        }
+        Ok(if self.pc.is_none() { Some(Stop::Returned) } else { None })
+    }
+
+    /// The number of steps run so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The whole RAM
+    pub fn ram(&self) -> &[i16] {
+        &self.ram
+    }
+
+    /// Reads a word of the RAM
+    pub fn peek(&self, address: usize) -> i16 {
+        self.ram[address % RAM_SIZE]
+    }
+
+    /// Writes a word of the RAM
+    pub fn poke(&mut self, address: usize, value: i16) {
+        self.ram[address % RAM_SIZE] = value;
+    }
+
+    /// The values on the stack, from the bottom of the current function's working stack to the top
+    pub fn stack(&self) -> &[i16] {
+        let top = (self.ram[SP] as u16 as usize).clamp(STACK_BASE as usize, RAM_SIZE);
+        &self.ram[STACK_BASE as usize..top]
+    }
+
+    /// The name of the function that runs the next command
+    pub fn current_function(&self) -> Option<&str> {
+        self.pc.and_then(|pc| self.locations.get(pc)).map(|location| location.function.as_str())
+    }
+
+    /// Everything the program printed with the Output class
+    pub fn output(&self) -> &str {
+        self.os.output()
+    }
+
+    /// The text the Output class left on the screen, row by row
+    pub fn text_screen(&self) -> String {
+        self.os.text_screen()
+    }
+
+    /// Takes a snapshot of the screen memory map, as a binary PBM image of 512x256 pixels
+    pub fn screen_pbm(&self) -> Vec<u8> {
+        screen_pbm(&self.ram)
+    }
+
+    /// Drives the keyboard memory map by a script instead of a keyboard. Every event writes its key
+    /// to the keyboard memory map once the program has made the event's number of steps.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - The key events made by `key_script::parse`, in the order they happen
+    pub fn script_keyboard(&mut self, events: Vec<KeyEvent>) {
+        self.key_events = events.into();
+    }
+
+    /// Stops the program, as Sys.halt does
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    /// Runs the command of an instruction
+    fn execute(&mut self, instruction: Instruction) -> Result<(), String> {
+        match instruction {
+            Instruction::Push(segment, index) => {
+                let value = match segment {
+                    Segment::Constant => { index as i16 }
+                    _ => { self.ram[self.address(segment, index)?] }
+                };
+                self.push(value)?;
+            }
+            Instruction::Pop(segment, index) => {
+                let value = self.pop()?;
+                let address = self.address(segment, index)?;
+                self.ram[address] = value;
+            }
+            Instruction::Arithmetic(command) => {
+                let value = match command {
+                    ArithmeticCommand::Neg => { self.pop()?.wrapping_neg() }
+                    ArithmeticCommand::Not => { !self.pop()? }
+                    _ => {
+                        let y = self.pop()?;
+                        let x = self.pop()?;
+                        match command {
+                            ArithmeticCommand::Add => { x.wrapping_add(y) }
+                            ArithmeticCommand::Sub => { x.wrapping_sub(y) }
+                            ArithmeticCommand::And => { x & y }
+                            ArithmeticCommand::Or => { x | y }
+                            ArithmeticCommand::Eq => { -((x == y) as i16) }
+                            ArithmeticCommand::Gt => { -((x > y) as i16) }
+                            _ => { -((x < y) as i16) }
+                        }
+                    }
+                };
+                self.push(value)?;
+            }
+            Instruction::Label => {}
+            Instruction::Goto(target) => { self.pc = Some(target); }
+            Instruction::IfGoto(target) => {
+                if self.pop()? != 0 {
+                    self.pc = Some(target);
+                }
+            }
+            Instruction::Function { locals } => {
+                for _ in 0..locals {
+                    self.push(0)?;
+                }
+            }
+            Instruction::Call { name, arguments } => {
+                if !self.functions.contains_key(&name) && JackOs::has_function(&name) {
+                    self.call_native(&name, arguments)?;
+                } else {
+                    let return_address = self.pc;
+                    self.call(&name, arguments, return_address)?;
+                }
+            }
+            Instruction::Return => { self.return_from_function()?; }
+        }
+        Ok(())
+    }
+
+    /// Calls a function whose arguments are on top of the stack, saving the frame of the caller
+    fn call(&mut self, name: &str, arguments: u16, return_address: Option<usize>) -> Result<(), String> {
+        let Some(&target) = self.functions.get(name) else {
+            return Err(format!("the function {} is not defined", name));
+        };
+        self.push(return_address.map_or(-1, |address| address as i16))?;
+        for register in [LCL, ARG, THIS, THAT] {
+            self.push(self.ram[register])?;
+        }
+        self.ram[ARG] = self.ram[SP] - 5 - arguments as i16;
+        self.ram[LCL] = self.ram[SP];
+        self.return_addresses.push(return_address);
+        self.pc = Some(target);
+        Ok(())
+    }
+
+    /// Calls a native OS function with the arguments on top of the stack, and replaces them with its return value.
+    /// A function that waits is called again on the next step.
+    fn call_native(&mut self, name: &str, arguments: u16) -> Result<(), String> {
+        let sp = self.ram[SP] as u16 as usize;
+        if sp < STACK_BASE as usize + arguments as usize {
+            return Err(format!("{} is called with {} argument(s), but the stack doesn't have them", name, arguments));
+        }
+        let argument_values = self.ram[sp - arguments as usize..sp].to_vec();
+        match self.os.call(name, &argument_values, &mut self.ram)? {
+            Native::Return(value) => {
+                self.ram[SP] -= arguments as i16;
+                self.push(value)?;
+            }
+            Native::Wait => { self.pc = self.pc.map(|pc| pc - 1); }
+            Native::Halt => { self.halt(); }
+        }
+        Ok(())
+    }
+
+    /// Returns from the current function: puts the return value in place of the arguments and restores the frame of the caller
+    fn return_from_function(&mut self) -> Result<(), String> {
+        let frame = self.ram[LCL] as u16 as usize;
+        if frame < 5 {
+            return Err("return without a frame to return to".to_string());
+        }
+        let value = self.pop()?;
+        let argument = self.ram[ARG] as u16 as usize % RAM_SIZE;
+        self.ram[argument] = value;
+        self.ram[SP] = self.ram[ARG] + 1;
+        self.ram[THAT] = self.ram[frame - 1];
+        self.ram[THIS] = self.ram[frame - 2];
+        self.ram[ARG] = self.ram[frame - 3];
+        self.ram[LCL] = self.ram[frame - 4];
+        self.pc = self.return_addresses.pop().flatten();
+        Ok(())
+    }
+
+    /// Pushes a value onto the stack
+    fn push(&mut self, value: i16) -> Result<(), String> {
+        let sp = self.ram[SP] as u16 as usize;
+        if sp >= HEAP_BASE as usize {
+            return Err("stack overflow".to_string());
+        }
+        self.ram[sp] = value;
+        self.ram[SP] += 1;
+        Ok(())
+    }
+
+    /// Pops the value on top of the stack
+    fn pop(&mut self) -> Result<i16, String> {
+        let sp = self.ram[SP] as u16 as usize;
+        if sp <= STACK_BASE as usize {
+            return Err("pop from an empty stack".to_string());
+        }
+        self.ram[SP] -= 1;
+        Ok(self.ram[sp - 1])
+    }
+
+    /// The RAM address of an entry of a segment. Static entries are already resolved to their address.
+    fn address(&self, segment: Segment, index: u16) -> Result<usize, String> {
+        let base = match segment {
+            Segment::Local => { self.ram[LCL] }
+            Segment::Argument => { self.ram[ARG] }
+            Segment::This => { self.ram[THIS] }
+            Segment::That => { self.ram[THAT] }
+            Segment::Pointer => { POINTER_BASE as i16 }
+            Segment::Temp => { TEMP_BASE as i16 }
+            Segment::Static | Segment::Constant => { 0 }
+        };
+        let address = base as u16 as usize + index as usize;
+        if address >= RAM_SIZE {
+            return Err(format!("the address {} of {} {} is outside the RAM", address, segment.name(), index));
+        }
+        Ok(address)
+    }
+
+    /// Adds the place of an instruction to the message of a runtime error
+    fn runtime_error(&self, pc: usize, message: &str) -> String {
+        match self.locations.get(pc) {
+            Some(location) => { format!("runtime error: {}\n --> {}:{} (in {})", message, location.file, location.line, location.function) }
+            None => { format!("runtime error: {}", message) }
+        }
+    }
+}
+
+/// Takes a snapshot of the screen memory map of a RAM, as a binary PBM image of 512x256 pixels
+///
+/// # Arguments
+///
+/// * `ram` - The whole RAM of the hack computer
+///
+/// # Returns
+///
+/// * The bytes of the image file, where a black pixel is a set bit
+pub fn screen_pbm(ram: &[i16]) -> Vec<u8> {
+    let mut image = format!("P4\n{} {}\n", SCREEN_WIDTH, SCREEN_HEIGHT).into_bytes();
+    for word in &ram[SCREEN..KEYBOARD] {
+        // The screen shows the lowest bit of a word on the left, PBM the highest bit of a byte
+        let word = (*word as u16).reverse_bits();
+        image.extend_from_slice(&word.to_be_bytes());
+    }
+    image
+}
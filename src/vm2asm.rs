@@ -0,0 +1,328 @@
+/// This file contains the translation of vm code into hack assembly, as in nand2tetris projects 7 and 8
+use std::collections::HashSet;
+
+use crate::error::{CompileError, Span};
+use crate::vm_command::{ArithmeticCommand, Segment, VmCommand};
+
+/// The RAM address the stack starts at
+pub const STACK_BASE: u16 = 256;
+/// The RAM address of the temp segment
+pub const TEMP_BASE: u16 = 5;
+/// The RAM address of the pointer segment, which holds THIS and THAT
+pub const POINTER_BASE: u16 = 3;
+
+/// Translates the vm files of a program into a single hack assembly file
+pub struct Translator {
+    asm: String,
+    file_name: String,
+    function_name: String,
+    label_index: usize,
+}
+
+impl Default for Translator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Translator {
+    /// Gets ready to translate a program
+    pub fn new() -> Self {
+        Translator {
+            asm: String::new(),
+            file_name: String::new(),
+            function_name: String::new(),
+            label_index: 0,
+        }
+    }
+
+    /// Writes the code that sets the stack pointer and calls Sys.init.
+    /// Must be written before any file is translated.
+    pub fn write_bootstrap(&mut self) {
+        self.comment("bootstrap");
+        self.function_name = "bootstrap".to_string();
+        self.emit(&[&format!("@{}", STACK_BASE), "D=A", "@SP", "M=D"]);
+        self.write_call("Sys.init", 0);
+    }
+
+    /// Translates the commands of a single vm file
+    ///
+    /// # Arguments
+    ///
+    /// * `file_name` - The name of the vm file without its extension, which names its static variables
+    /// * `commands` - The parsed commands of the file
+    pub fn translate_file(&mut self, file_name: &str, commands: &[VmCommand]) {
+        self.file_name = file_name.to_string();
+        self.function_name = file_name.to_string();
+        for command in commands.iter() {
+            self.translate(command);
+        }
+    }
+
+    /// The assembly code written so far
+    pub fn asm(&self) -> &str {
+        &self.asm
+    }
+
+    /// Translates a single command
+    fn translate(&mut self, command: &VmCommand) {
+        match command {
+            VmCommand::Push { segment, index } => {
+                self.comment(&format!("push {} {}", segment.name(), index));
+                self.write_push(*segment, *index);
+            }
+            VmCommand::Pop { segment, index } => {
+                self.comment(&format!("pop {} {}", segment.name(), index));
+                self.write_pop(*segment, *index);
+            }
+            VmCommand::Arithmetic(command) => { self.write_arithmetic(*command); }
+            VmCommand::Label(label) => {
+                let label = self.scoped_label(label);
+                self.asm.push_str(&format!("({})\n", label));
+            }
+            VmCommand::Goto(label) => {
+                self.comment(&format!("goto {}", label));
+                let label = self.scoped_label(label);
+                self.emit(&[&format!("@{}", label), "0;JMP"]);
+            }
+            VmCommand::IfGoto(label) => {
+                self.comment(&format!("if-goto {}", label));
+                let label = self.scoped_label(label);
+                self.emit(&["@SP", "AM=M-1", "D=M", &format!("@{}", label), "D;JNE"]);
+            }
+            VmCommand::Function { name, locals } => {
+                self.comment(&format!("function {} {}", name, locals));
+                self.function_name = name.to_string();
+                self.asm.push_str(&format!("({})\n", name));
+                for _ in 0..*locals {
+                    self.emit(&["@SP", "A=M", "M=0", "@SP", "M=M+1"]);
+                }
+            }
+            VmCommand::Call { name, arguments } => { self.write_call(name, *arguments); }
+            VmCommand::Return => { self.write_return(); }
+        }
+    }
+
+    /// Writes the code of a push command
+    fn write_push(&mut self, segment: Segment, index: u16) {
+        match segment {
+            Segment::Constant => { self.emit(&[&format!("@{}", index), "D=A"]); }
+            Segment::Local | Segment::Argument | Segment::This | Segment::That => {
+                self.emit(&[&format!("@{}", index), "D=A", base_register(segment), "A=D+M", "D=M"]);
+            }
+            Segment::Static | Segment::Pointer | Segment::Temp => {
+                let address = self.fixed_address(segment, index);
+                self.emit(&[&address, "D=M"]);
+            }
+        }
+        self.emit(&["@SP", "A=M", "M=D", "@SP", "M=M+1"]);
+    }
+
+    /// Writes the code of a pop command. The constant segment can't be popped into.
+    fn write_pop(&mut self, segment: Segment, index: u16) {
+        match segment {
+            Segment::Local | Segment::Argument | Segment::This | Segment::That => {
+                // The target address is saved in R13 while the value is popped
+                self.emit(&[&format!("@{}", index), "D=A", base_register(segment), "D=D+M", "@R13", "M=D"]);
+                self.emit(&["@SP", "AM=M-1", "D=M", "@R13", "A=M", "M=D"]);
+            }
+            Segment::Static | Segment::Pointer | Segment::Temp => {
+                let address = self.fixed_address(segment, index);
+                self.emit(&["@SP", "AM=M-1", "D=M", &address, "M=D"]);
+            }
+            Segment::Constant => {}
+        }
+    }
+
+    /// Writes the code of an arithmetic-logical command
+    fn write_arithmetic(&mut self, command: ArithmeticCommand) {
+        match command {
+            ArithmeticCommand::Add => { self.binary("add", "M=D+M"); }
+            ArithmeticCommand::Sub => { self.binary("sub", "M=M-D"); }
+            ArithmeticCommand::And => { self.binary("and", "M=D&M"); }
+            ArithmeticCommand::Or => { self.binary("or", "M=D|M"); }
+            ArithmeticCommand::Neg => { self.unary("neg", "M=-M"); }
+            ArithmeticCommand::Not => { self.unary("not", "M=!M"); }
+            ArithmeticCommand::Eq => { self.comparison("eq", "JEQ"); }
+            ArithmeticCommand::Gt => { self.comparison("gt", "JGT"); }
+            ArithmeticCommand::Lt => { self.comparison("lt", "JLT"); }
+        }
+    }
+
+    /// Writes a command that replaces the two values on top of the stack with the result of the computation
+    fn binary(&mut self, name: &str, computation: &str) {
+        self.comment(name);
+        self.emit(&["@SP", "AM=M-1", "D=M", "A=A-1", computation]);
+    }
+
+    /// Writes a command that replaces the value on top of the stack with the result of the computation
+    fn unary(&mut self, name: &str, computation: &str) {
+        self.comment(name);
+        self.emit(&["@SP", "A=M-1", computation]);
+    }
+
+    /// Writes a command that replaces the two values on top of the stack with true (-1) if the jump condition holds
+    /// for their difference, and false (0) otherwise.
+    /// The difference of values of opposite signs can overflow, so it is only computed for values of the same sign;
+    /// otherwise the sign of the first value decides the order. Equality doesn't depend on the overflow.
+    fn comparison(&mut self, name: &str, jump: &str) {
+        self.comment(name);
+        let label = format!("COMPARE_{}", self.next_label_index());
+        if jump == "JEQ" {
+            self.emit(&["@SP", "AM=M-1", "D=M", "A=A-1", "D=M-D"]);
+        } else {
+            // R13 = y, D = x
+            self.emit(&["@SP", "AM=M-1", "D=M", "@R13", "M=D", "@SP", "A=M-1", "D=M"]);
+            self.emit(&[&format!("@{}_X_NEGATIVE", label), "D;JLT"]);
+            // x >= 0: if y < 0 too, x > y, and D = 1 stands for the difference
+            self.emit(&["@R13", "D=M", &format!("@{}_SAME_SIGN", label), "D;JGE", "D=1", &format!("@{}_DIFFERENCE", label), "0;JMP"]);
+            // x < 0: if y >= 0, x < y, and D = -1 stands for the difference
+            self.asm.push_str(&format!("({}_X_NEGATIVE)\n", label));
+            self.emit(&["@R13", "D=M", &format!("@{}_SAME_SIGN", label), "D;JLT", "D=-1", &format!("@{}_DIFFERENCE", label), "0;JMP"]);
+            self.asm.push_str(&format!("({}_SAME_SIGN)\n", label));
+            self.emit(&["@R13", "D=M", "@SP", "A=M-1", "D=M-D"]);
+            self.asm.push_str(&format!("({}_DIFFERENCE)\n", label));
+            self.emit(&["@SP", "A=M-1"]);
+        }
+        self.emit(&["M=-1", &format!("@{}", label), &format!("D;{}", jump)]);
+        self.emit(&["@SP", "A=M-1", "M=0"]);
+        self.asm.push_str(&format!("({})\n", label));
+    }
+
+    /// Writes the code of a call command: saves the frame of the caller and jumps to the function
+    fn write_call(&mut self, name: &str, arguments: u16) {
+        self.comment(&format!("call {} {}", name, arguments));
+        let label_index = self.next_label_index();
+        let return_label = format!("{}$ret.{}", self.function_name, label_index);
+
+        self.emit(&[&format!("@{}", return_label), "D=A", "@SP", "A=M", "M=D", "@SP", "M=M+1"]);
+        for register in ["@LCL", "@ARG", "@THIS", "@THAT"] {
+            self.emit(&[register, "D=M", "@SP", "A=M", "M=D", "@SP", "M=M+1"]);
+        }
+        // ARG = SP - 5 - arguments, LCL = SP
+        self.emit(&["@SP", "D=M", &format!("@{}", 5 + arguments as usize), "D=D-A", "@ARG", "M=D"]);
+        self.emit(&["@SP", "D=M", "@LCL", "M=D"]);
+        self.emit(&[&format!("@{}", name), "0;JMP"]);
+        self.asm.push_str(&format!("({})\n", return_label));
+    }
+
+    /// Writes the code of a return command: restores the frame of the caller and jumps back to it
+    fn write_return(&mut self) {
+        self.comment("return");
+        // R13 = the end of the frame, R14 = the return address
+        self.emit(&["@LCL", "D=M", "@R13", "M=D"]);
+        self.emit(&["@5", "A=D-A", "D=M", "@R14", "M=D"]);
+        // *ARG = the return value, SP = ARG + 1
+        self.emit(&["@SP", "AM=M-1", "D=M", "@ARG", "A=M", "M=D"]);
+        self.emit(&["@ARG", "D=M+1", "@SP", "M=D"]);
+        for register in ["@THAT", "@THIS", "@ARG", "@LCL"] {
+            self.emit(&["@R13", "AM=M-1", "D=M", register, "M=D"]);
+        }
+        self.emit(&["@R14", "A=M", "0;JMP"]);
+    }
+
+    /// The address instruction of a static, pointer or temp entry
+    fn fixed_address(&self, segment: Segment, index: u16) -> String {
+        match segment {
+            Segment::Static => { format!("@{}.{}", self.file_name, index) }
+            Segment::Pointer => { format!("@{}", POINTER_BASE + index) }
+            _ => { format!("@{}", TEMP_BASE + index) }
+        }
+    }
+
+    /// The name of a label inside the current function, so functions can use the same labels
+    fn scoped_label(&self, label: &str) -> String {
+        format!("{}${}", self.function_name, label)
+    }
+
+    /// Returns a number that wasn't used for a generated label yet
+    fn next_label_index(&mut self) -> usize {
+        self.label_index += 1;
+        self.label_index - 1
+    }
+
+    /// Writes a comment with the vm command being translated
+    fn comment(&mut self, text: &str) {
+        self.asm.push_str(&format!("// {}\n", text));
+    }
+
+    /// Writes assembly instructions, one per line
+    fn emit(&mut self, instructions: &[&str]) {
+        for instruction in instructions.iter() {
+            self.asm.push_str(instruction);
+            self.asm.push('\n');
+        }
+    }
+}
+
+/// The register holding the base address of a segment that lives on the heap or the stack
+fn base_register(segment: Segment) -> &'static str {
+    match segment {
+        Segment::Local => { "@LCL" }
+        Segment::Argument => { "@ARG" }
+        Segment::This => { "@THIS" }
+        _ => { "@THAT" }
+    }
+}
+
+/// Translates a whole program. If one of the files defines Sys.init, the bootstrap code is written first.
+///
+/// # Arguments
+///
+/// * `files` - The name of every vm file without its extension, and its parsed commands
+///
+/// # Returns
+///
+/// * The hack assembly code of the program
+pub fn translate_program(files: &[(String, Vec<VmCommand>)]) -> String {
+    let mut translator = Translator::new();
+    let has_sys_init = files.iter()
+        .flat_map(|(_, commands)| commands.iter())
+        .any(|command| matches!(command, VmCommand::Function { name, .. } if name == "Sys.init"));
+    if has_sys_init {
+        translator.write_bootstrap();
+    }
+    for (file_name, commands) in files.iter() {
+        translator.translate_file(file_name, commands);
+    }
+    translator.asm().to_string()
+}
+
+/// Checks that a program can run once it is translated: it must define Sys.init, which the bootstrap calls,
+/// and every function it calls. Without them the hack code would jump to addresses the assembler makes up.
+///
+/// # Arguments
+///
+/// * `files` - The name of every vm file without its extension, and its parsed commands with their spans
+///
+/// # Returns
+///
+/// * Nothing, or an error for the missing Sys.init and for every function that is called but not defined,
+///   each with the index of the file it points into
+pub fn check_program(files: &[(String, Vec<(VmCommand, Span)>)]) -> Result<(), Vec<(usize, CompileError)>> {
+    let defined: HashSet<&str> = files.iter()
+        .flat_map(|(_, commands)| commands.iter())
+        .filter_map(|(command, _)| match command {
+            VmCommand::Function { name, .. } => { Some(name.as_str()) }
+            _ => { None }
+        })
+        .collect();
+
+    let mut errors = Vec::new();
+    if !defined.contains("Sys.init") {
+        let file_index = files.iter().position(|(_, commands)| !commands.is_empty()).unwrap_or(0);
+        let span = files.get(file_index).and_then(|(_, commands)| commands.first()).map_or(Span::default(), |(_, span)| *span);
+        errors.push((file_index, CompileError::new(span, "the program doesn't define Sys.init, which the bootstrap code calls to start it".to_string())));
+    }
+    let mut reported = HashSet::new();
+    for (file_index, (_, commands)) in files.iter().enumerate() {
+        for (command, span) in commands.iter() {
+            if let VmCommand::Call { name, .. } = command {
+                if !defined.contains(name.as_str()) && reported.insert(name.as_str()) {
+                    errors.push((file_index, CompileError::new(*span, format!("the function {} is called, but no file of the program defines it", name))));
+                }
+            }
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
@@ -0,0 +1,200 @@
+/// This file contains the parsing of vm code into commands, shared by the translator and the interpreter
+use crate::error::{CompileError, Span};
+use crate::tokenizer::MAX_INT_CONST;
+use crate::utility::MEMORY_AREAS;
+
+/// One of the eight memory segments of the vm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    Static,
+    This,
+    That,
+    Local,
+    Argument,
+    Constant,
+    Pointer,
+    Temp,
+}
+
+impl Segment {
+    /// Finds the segment by its name in MEMORY_AREAS
+    pub fn from_name(name: &str) -> Option<Segment> {
+        match MEMORY_AREAS.iter().position(|area| *area == name)? {
+            0 => { Some(Segment::Static) }
+            1 => { Some(Segment::This) }
+            2 => { Some(Segment::That) }
+            3 => { Some(Segment::Local) }
+            4 => { Some(Segment::Argument) }
+            5 => { Some(Segment::Constant) }
+            6 => { Some(Segment::Pointer) }
+            _ => { Some(Segment::Temp) }
+        }
+    }
+
+    /// The name of the segment in vm code
+    pub fn name(&self) -> &'static str {
+        match self {
+            Segment::Static => { MEMORY_AREAS[0] }
+            Segment::This => { MEMORY_AREAS[1] }
+            Segment::That => { MEMORY_AREAS[2] }
+            Segment::Local => { MEMORY_AREAS[3] }
+            Segment::Argument => { MEMORY_AREAS[4] }
+            Segment::Constant => { MEMORY_AREAS[5] }
+            Segment::Pointer => { MEMORY_AREAS[6] }
+            Segment::Temp => { MEMORY_AREAS[7] }
+        }
+    }
+
+    /// The largest index the segment has, if it is smaller than the largest constant
+    fn max_index(&self) -> Option<u16> {
+        match self {
+            Segment::Pointer => { Some(1) }
+            Segment::Temp => { Some(7) }
+            _ => { None }
+        }
+    }
+}
+
+/// An arithmetic-logical command, which works on the values on top of the stack
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArithmeticCommand {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+}
+
+impl ArithmeticCommand {
+    /// Finds the arithmetic command by its name in vm code
+    pub fn from_name(name: &str) -> Option<ArithmeticCommand> {
+        match name {
+            "add" => { Some(ArithmeticCommand::Add) }
+            "sub" => { Some(ArithmeticCommand::Sub) }
+            "neg" => { Some(ArithmeticCommand::Neg) }
+            "eq" => { Some(ArithmeticCommand::Eq) }
+            "gt" => { Some(ArithmeticCommand::Gt) }
+            "lt" => { Some(ArithmeticCommand::Lt) }
+            "and" => { Some(ArithmeticCommand::And) }
+            "or" => { Some(ArithmeticCommand::Or) }
+            "not" => { Some(ArithmeticCommand::Not) }
+            _ => { None }
+        }
+    }
+}
+
+/// A single vm command
+#[derive(Clone, Debug, PartialEq)]
+pub enum VmCommand {
+    Push { segment: Segment, index: u16 },
+    Pop { segment: Segment, index: u16 },
+    Arithmetic(ArithmeticCommand),
+    Label(String),
+    Goto(String),
+    IfGoto(String),
+    Function { name: String, locals: u16 },
+    Call { name: String, arguments: u16 },
+    Return,
+}
+
+/// Parses vm code into its commands
+///
+/// # Arguments
+///
+/// * `source` - The contents of a vm file
+///
+/// # Returns
+///
+/// * The commands in the order they are written, with the line each one is on, or the first error found
+pub fn parse(source: &str) -> Result<Vec<(VmCommand, Span)>, CompileError> {
+    let mut commands = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let code = line.split("//").next().unwrap_or("");
+        let words: Vec<(usize, &str)> = split_words(code);
+        let Some(&(column, _)) = words.first() else { continue; };
+        let span = Span::new(line_index + 1, column, code.trim().chars().count());
+        let command = parse_command(words[0], &words[1..], line_index + 1)?;
+        commands.push((command, span));
+    }
+    Ok(commands)
+}
+
+/// Splits a line into its words, with the column every word starts at
+fn split_words(code: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+    for (index, ch) in code.char_indices().chain(std::iter::once((code.len(), ' '))) {
+        if ch.is_whitespace() {
+            if let Some(word_start) = start.take() {
+                words.push((code[..word_start].chars().count() + 1, &code[word_start..index]));
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    words
+}
+
+/// Parses a single command from its name and arguments
+fn parse_command((name_column, name): (usize, &str), arguments: &[(usize, &str)], line: usize) -> Result<VmCommand, CompileError> {
+    let arithmetic = ArithmeticCommand::from_name(name);
+    let argument_count = match name {
+        "push" | "pop" | "function" | "call" => { 2 }
+        "label" | "goto" | "if-goto" => { 1 }
+        _ => {
+            if arithmetic.is_none() && name != "return" {
+                return Err(CompileError::new(Span::new(line, name_column, name.len()), format!("unknown vm command '{}'", name)));
+            }
+            0
+        }
+    };
+    if arguments.len() != argument_count {
+        return Err(CompileError::new(Span::new(line, name_column, name.len()),
+                                     format!("'{}' takes {} argument(s), found {}", name, argument_count, arguments.len())));
+    }
+    if let Some(command) = arithmetic {
+        return Ok(VmCommand::Arithmetic(command));
+    }
+
+    match name {
+        "push" | "pop" => {
+            let (segment_column, segment_name) = arguments[0];
+            let Some(segment) = Segment::from_name(segment_name) else {
+                return Err(CompileError::new(Span::new(line, segment_column, segment_name.len()),
+                                             format!("unknown memory segment '{}'", segment_name)));
+            };
+            let index = parse_number(arguments[1], line)?;
+            if let Some(max_index) = segment.max_index() {
+                if index > max_index {
+                    return Err(CompileError::new(Span::new(line, arguments[1].0, arguments[1].1.len()),
+                                                 format!("the {} segment has no index {}, the largest is {}", segment.name(), index, max_index)));
+                }
+            }
+            if name == "push" {
+                Ok(VmCommand::Push { segment, index })
+            } else if segment == Segment::Constant {
+                Err(CompileError::new(Span::new(line, segment_column, segment_name.len()), "cannot pop into the constant segment".to_string()))
+            } else {
+                Ok(VmCommand::Pop { segment, index })
+            }
+        }
+        "label" => { Ok(VmCommand::Label(arguments[0].1.to_string())) }
+        "goto" => { Ok(VmCommand::Goto(arguments[0].1.to_string())) }
+        "if-goto" => { Ok(VmCommand::IfGoto(arguments[0].1.to_string())) }
+        "function" => { Ok(VmCommand::Function { name: arguments[0].1.to_string(), locals: parse_number(arguments[1], line)? }) }
+        "call" => { Ok(VmCommand::Call { name: arguments[0].1.to_string(), arguments: parse_number(arguments[1], line)? }) }
+        _ => { Ok(VmCommand::Return) }
+    }
+}
+
+/// Parses a non negative number argument, which must fit in a constant
+fn parse_number((column, word): (usize, &str), line: usize) -> Result<u16, CompileError> {
+    match word.parse::<u16>() {
+        Ok(number) if number <= MAX_INT_CONST => { Ok(number) }
+        _ => { Err(CompileError::new(Span::new(line, column, word.len()), format!("expected a number from 0 to {}, found '{}'", MAX_INT_CONST, word))) }
+    }
+}
@@ -1,10 +1,12 @@
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
 
 use crate::utility::{ADD, AND, EQ, GT, Kind, LT, MEMORY_AREAS, NEG, NOT, OR, SUB};
 
-pub struct VMWriter {
-    pub vm_file: File,
+/// Writes vm commands to a file, or to any other writer
+pub struct VMWriter<W: Write = File> {
+    pub vm_file: W,
 }
 
 
@@ -13,19 +15,33 @@ impl VMWriter {
     ///
     /// # Arguments
     ///
-    /// * `path` - A path to the jack file, including the file extension
+    /// * `path` - A path to the vm file, including the file extension
     ///
     /// # Returns
     ///
-    /// * This self vmwriter object
-    pub fn new(path: &String) -> Self {
+    /// * This self vmwriter object, or the error of creating the file
+    pub fn new(path: &Path) -> std::io::Result<Self> {
+        Ok(VMWriter {
+            vm_file: File::create(path)?,
+        })
+    }
+}
+
+impl<W: Write> VMWriter<W> {
+    /// Writes the vm commands to the given writer instead of a file, such as a Vec<u8> kept in memory
+    pub fn from_writer(writer: W) -> Self {
         VMWriter {
-            vm_file: File::create(path.to_owned().split(".jack").collect::<Vec<_>>()[0].to_owned() + ".vm").unwrap(),
+            vm_file: writer,
         }
     }
 
+    /// Returns the writer the vm commands were written to
+    pub fn into_inner(self) -> W {
+        self.vm_file
+    }
+
     /// Writes a VM push command
-    pub fn write_push(&mut self, segment: Kind, segmen: String, index: usize) {
+    pub fn write_push(&mut self, segment: Kind, segmen: String, index: usize) -> std::io::Result<()> {
         if segmen.is_empty() {
             let seg = match segment {
                 Kind::STATIC => { "static" }
@@ -34,18 +50,16 @@ impl VMWriter {
                 Kind::VAR => { "local" }
                 Kind::NONE => { "constant" }
             };
-            self.vm_file.write(format!("push {} {}\n", seg, index).as_ref()).expect("write push failed");
+            self.vm_file.write_all(format!("push {} {}\n", seg, index).as_ref())
+        } else if MEMORY_AREAS.contains(&&*segmen) {
+            self.vm_file.write_all(format!("push {} {}\n", segmen, index).as_ref())
         } else {
-            if MEMORY_AREAS.contains(&&*segmen) {
-                self.vm_file.write(format!("push {} {}\n", segmen, index).as_ref()).expect("write push failed");
-            } else {
-                self.vm_file.write(format!("push {}\n", segmen).as_ref()).expect("write push failed");
-            }
+            self.vm_file.write_all(format!("push {}\n", segmen).as_ref())
         }
     }
 
     /// Writes a VM pop command - receives a constant in segment
-    pub fn write_pop(&mut self, segment: Kind, segmen: String, index: usize) {
+    pub fn write_pop(&mut self, segment: Kind, segmen: String, index: usize) -> std::io::Result<()> {
         if segmen.is_empty() {
             let seg = match segment {
                 Kind::STATIC => { "static" }
@@ -54,53 +68,53 @@ impl VMWriter {
                 Kind::VAR => { "local" }
                 Kind::NONE => { "constant" }
             };
-            self.vm_file.write(format!("pop {} {}\n", seg, index).as_ref()).expect("write pop failed");
-        } else { self.vm_file.write(format!("pop {} {}\n", segmen, index).as_ref()).expect("write pop failed"); }
+            self.vm_file.write_all(format!("pop {} {}\n", seg, index).as_ref())
+        } else { self.vm_file.write_all(format!("pop {} {}\n", segmen, index).as_ref()) }
     }
 
     /// Writes a VM arithmetic-logical command
-    pub fn write_arithmetic(&mut self, command: usize) {
+    pub fn write_arithmetic(&mut self, command: usize) -> std::io::Result<()> {
         match command {
-            ADD => { self.vm_file.write("add\n".as_ref()).expect("TODO: panic message"); }
-            SUB => { self.vm_file.write("sub\n".as_ref()).expect("TODO: panic message"); }
-            NEG => { self.vm_file.write("neg\n".as_ref()).expect("TODO: panic message"); }
-            EQ => { self.vm_file.write("eq\n".as_ref()).expect("TODO: panic message"); }
-            GT => { self.vm_file.write("gt\n".as_ref()).expect("TODO: panic message"); }
-            LT => { self.vm_file.write("lt\n".as_ref()).expect("TODO: panic message"); }
-            AND => { self.vm_file.write("and\n".as_ref()).expect("TODO: panic message"); }
-            OR => { self.vm_file.write("or\n".as_ref()).expect("TODO: panic message"); }
-            NOT => { self.vm_file.write("not\n".as_ref()).expect("TODO: panic message"); }
-            _ => {}
-        };
+            ADD => { self.vm_file.write_all("add\n".as_ref()) }
+            SUB => { self.vm_file.write_all("sub\n".as_ref()) }
+            NEG => { self.vm_file.write_all("neg\n".as_ref()) }
+            EQ => { self.vm_file.write_all("eq\n".as_ref()) }
+            GT => { self.vm_file.write_all("gt\n".as_ref()) }
+            LT => { self.vm_file.write_all("lt\n".as_ref()) }
+            AND => { self.vm_file.write_all("and\n".as_ref()) }
+            OR => { self.vm_file.write_all("or\n".as_ref()) }
+            NOT => { self.vm_file.write_all("not\n".as_ref()) }
+            _ => { Ok(()) }
+        }
     }
 
     /// Writes a VM label command
-    pub fn write_label(&mut self, label: String) {
-        self.vm_file.write(format!("label {}\n", label).as_ref()).expect("write label failed");
+    pub fn write_label(&mut self, label: String) -> std::io::Result<()> {
+        self.vm_file.write_all(format!("label {}\n", label).as_ref())
     }
 
     /// Writes a VM goto command
-    pub fn write_goto(&mut self, label: String) {
-        self.vm_file.write(format!("goto {}\n", label).as_ref()).expect("write goto failed");
+    pub fn write_goto(&mut self, label: String) -> std::io::Result<()> {
+        self.vm_file.write_all(format!("goto {}\n", label).as_ref())
     }
 
     /// Writes a VM if-goto command
-    pub fn write_if(&mut self, label: String) {
-        self.vm_file.write(format!("if-goto {}\n", label).as_ref()).expect("write if-goto failed");
+    pub fn write_if(&mut self, label: String) -> std::io::Result<()> {
+        self.vm_file.write_all(format!("if-goto {}\n", label).as_ref())
     }
 
     /// Writes a VM call command
-    pub fn write_call(&mut self, name: String, n_args: usize) {
-        self.vm_file.write(format!("call {} {}\n", name, n_args).as_ref()).expect("write if-goto failed");
+    pub fn write_call(&mut self, name: String, n_args: usize) -> std::io::Result<()> {
+        self.vm_file.write_all(format!("call {} {}\n", name, n_args).as_ref())
     }
 
     /// Writes a VM function command
-    pub fn write_function(&mut self, name: String, n_args: usize) {
-        self.vm_file.write(format!("function {} {}\n", name, n_args).as_ref()).expect("write function failed");
+    pub fn write_function(&mut self, name: String, n_args: usize) -> std::io::Result<()> {
+        self.vm_file.write_all(format!("function {} {}\n", name, n_args).as_ref())
     }
 
     /// Writes a VM return command
-    pub fn write_return(&mut self) {
-        self.vm_file.write("return\n".as_ref()).expect("write return failed");
+    pub fn write_return(&mut self) -> std::io::Result<()> {
+        self.vm_file.write_all("return\n".as_ref())
     }
 }
\ No newline at end of file
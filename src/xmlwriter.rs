@@ -1,12 +1,16 @@
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
 
 static RIGHT_BRACKET: &str = ">";
 static LEFT_BRACKET: &str = "<";
 static WHITESPACE: &str = " ";
 
-pub struct XmlWriter {
-    pub xmlfile: File,
+/// Writes xml tags to a file, or to any other writer
+pub struct XmlWriter<W: Write = File> {
+    pub xmlfile: W,
+    indentation: usize,
+    depth: usize,
 }
 
 impl XmlWriter {
@@ -14,36 +18,82 @@ impl XmlWriter {
     ///
     /// # Arguments
     ///
-    /// * `path` - A path to the jack file, including the file extension
+    /// * `path` - A path to the xml file, including the file extension
     ///
     /// # Returns
     ///
-    /// * This self xmlwriter object
-    pub fn new(path: &String) -> Self {
+    /// * This self xmlwriter object, or the error of creating the file
+    pub fn new(path: &Path) -> std::io::Result<Self> {
+        Ok(XmlWriter {
+            xmlfile: File::create(path)?,
+            indentation: 0,
+            depth: 0,
+        })
+    }
+}
+
+impl<W: Write> XmlWriter<W> {
+    /// Writes the xml to the given writer instead of a file, such as a Vec<u8> kept in memory
+    pub fn from_writer(writer: W) -> Self {
         XmlWriter {
-            xmlfile: File::create(path.to_owned().split(".jack").collect::<Vec<_>>()[0].to_owned() + ".xml").unwrap(),
+            xmlfile: writer,
+            indentation: 0,
+            depth: 0,
         }
     }
 
+    /// Returns the writer the xml was written to
+    pub fn into_inner(self) -> W {
+        self.xmlfile
+    }
+
+    /// Sets the number of spaces every nested tag is indented by. The default is no indentation.
+    pub fn set_indentation(&mut self, spaces: usize) {
+        self.indentation = spaces;
+    }
+
+    /// The whitespace written before a tag at the current depth
+    fn indent(&self) -> String {
+        WHITESPACE.repeat(self.indentation * self.depth)
+    }
+
     /// Writes to an xml file
     ///
     /// # Arguments
     ///
     /// * `tag` - the tag in xml
-    /// * `content` - the content of the tag
-    pub fn write(&mut self, tag: String, content: String) {
+    /// * `content` - the content of the tag, which is escaped before writing
+    pub fn write(&mut self, tag: String, content: String) -> std::io::Result<()> {
         let opening_tag = LEFT_BRACKET.to_string() + tag.as_str() + RIGHT_BRACKET;
         let closing_tag = LEFT_BRACKET.to_string() + "/" + tag.as_str() + RIGHT_BRACKET;
-        self.xmlfile.write((opening_tag + WHITESPACE + content.as_str() + WHITESPACE + closing_tag.as_str() + "\n").as_ref()).expect("ERROR WRITING TOKENS");
+        self.xmlfile.write_all((self.indent() + &opening_tag + WHITESPACE + escape(&content).as_str() + WHITESPACE + closing_tag.as_str() + "\n").as_ref())
     }
 
     /// Writes an opening tag
-    pub fn open_tag(&mut self, tag:String){
-        self.xmlfile.write(("<".to_owned() + &tag + ">\n".as_ref()).as_ref()).expect("ERROR WRITING TOKENS");
+    pub fn open_tag(&mut self, tag:String) -> std::io::Result<()> {
+        self.xmlfile.write_all((self.indent() + "<" + &tag + ">\n").as_ref())?;
+        self.depth += 1;
+        Ok(())
     }
 
     /// Writes a closing tag
-    pub fn close_tag(&mut self, tag:String) {
-        self.xmlfile.write(("</".to_owned() + &tag + ">\n".as_ref()).as_ref()).expect("ERROR WRITING TOKENS");
+    pub fn close_tag(&mut self, tag:String) -> std::io::Result<()> {
+        self.depth = self.depth.saturating_sub(1);
+        self.xmlfile.write_all((self.indent() + "</" + &tag + ">\n").as_ref())
     }
-}
\ No newline at end of file
+}
+
+/// Escapes the characters that have a special meaning in xml
+pub fn escape(content: &str) -> String {
+    let mut escaped = String::with_capacity(content.len());
+    for ch in content.chars() {
+        match ch {
+            '<' => { escaped.push_str("&lt;") }
+            '>' => { escaped.push_str("&gt;") }
+            '&' => { escaped.push_str("&amp;") }
+            '"' => { escaped.push_str("&quot;") }
+            _ => { escaped.push(ch) }
+        }
+    }
+    escaped
+}
@@ -0,0 +1,78 @@
+//! Assembles programs and compares them with the rom images of the nand2tetris reference assembler.
+use jack2vm::assembler::{assemble, to_hack_text};
+
+#[test]
+fn max_program_of_project_6() {
+    let instructions = assemble("
+        // Computes R2 = max(R0, R1)
+        @R0
+        D=M              // D = first number
+        @R1
+        D=D-M            // D = first number - second number
+        @OUTPUT_FIRST
+        D;JGT            // if D>0 (first is greater) goto output_first
+        @R1
+        D=M              // D = second number
+        @OUTPUT_D
+        0;JMP            // goto output_d
+    (OUTPUT_FIRST)
+        @R0
+        D=M              // D = first number
+    (OUTPUT_D)
+        @R2
+        M=D              // M[2] = D (greatest number)
+    (INFINITE_LOOP)
+        @INFINITE_LOOP
+        0;JMP            // infinite loop
+    ").expect("the program should assemble");
+
+    assert_eq!(to_hack_text(&instructions), "\
+0000000000000000
+1111110000010000
+0000000000000001
+1111010011010000
+0000000000001010
+1110001100000001
+0000000000000001
+1111110000010000
+0000000000001100
+1110101010000111
+0000000000000000
+1111110000010000
+0000000000000010
+1110001100001000
+0000000000001110
+1110101010000111
+");
+}
+
+#[test]
+fn variables_start_at_16_in_order_of_appearance() {
+    let instructions = assemble("@i\nM=1\n@sum\nM=0\n@i\nD=M\n@SCREEN\n@KBD\n@LOOP\n(LOOP)").expect("the program should assemble");
+    assert_eq!(instructions, vec![16, 0b1110111111001000, 17, 0b1110101010001000, 16, 0b1111110000010000, 16384, 24576, 9]);
+}
+
+#[test]
+fn every_destination_and_jump() {
+    let instructions = assemble("AMD=D+1;JMP\nMD=M-1;JLE\nA=!D\nD;JNE\nAM=D|M;JGE").expect("the program should assemble");
+    assert_eq!(instructions, vec![
+        0b1110011111111111,
+        0b1111110010011110,
+        0b1110001101100000,
+        0b1110001100000101,
+        0b1111010101101011,
+    ]);
+}
+
+#[test]
+fn errors_point_at_the_line() {
+    let error = assemble("@1\nD=A\nD=D*A").unwrap_err();
+    assert_eq!(error.span.line, 3);
+    assert!(error.message.contains("unknown computation 'D*A'"));
+
+    assert!(assemble("0;JUMP").unwrap_err().message.contains("unknown jump"));
+    assert!(assemble("@40000").unwrap_err().message.contains("expected an address"));
+    assert!(assemble("(A)\n(A)").unwrap_err().message.contains("defined more than once"));
+    assert!(assemble("@1abc").unwrap_err().message.contains("expected an address"));
+    assert!(assemble("@a-b").unwrap_err().message.contains("not a valid symbol"));
+}
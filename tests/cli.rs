@@ -0,0 +1,114 @@
+//! Runs the compiler from the command line, and checks how its options are parsed and where it writes its files.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_DIRECTORY: AtomicUsize = AtomicUsize::new(0);
+
+const MAIN: &str = "class Main { function void main() { return; } }";
+
+/// Makes an empty directory for the files of a test
+fn test_directory() -> PathBuf {
+    let index = NEXT_DIRECTORY.fetch_add(1, Ordering::SeqCst);
+    let directory = std::env::temp_dir().join(format!("jack2vm_cli_{}_{}", std::process::id(), index));
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Writes a jack file, making the directories it is in
+fn write_class(path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, MAIN).unwrap();
+}
+
+/// Runs the compiler with the given arguments
+fn jack2vm(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_jack2vm")).args(args).output().expect("the compiler should run")
+}
+
+/// Checks that the compiler refused its arguments with the given message
+fn assert_usage_error(args: &[&str], message: &str) {
+    let output = jack2vm(args);
+    assert_eq!(output.status.code(), Some(2), "{:?}", args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with(&format!("error: {}\n", message)), "{:?}: {}", args, stderr);
+    assert!(stderr.contains("Usage: jack2vm"), "{}", stderr);
+}
+
+#[test]
+fn option_values_can_follow_an_equals_sign_or_the_option() {
+    let directory = test_directory();
+    let main = directory.join("Main.jack");
+    write_class(&main);
+
+    let out_dir = directory.join("out");
+    let output = jack2vm(&["-q", &format!("--out-dir={}", out_dir.display()), "--emit=vm,tokens", "--precedence=standard", main.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(out_dir.join("Main.vm").is_file());
+    assert!(out_dir.join("MainT.xml").is_file());
+
+    let out_dir = directory.join("other");
+    let output = jack2vm(&["-q", "-o", out_dir.to_str().unwrap(), "-e", "xml", "-p", "jack", main.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(out_dir.join("Main.xml").is_file());
+    assert!(!out_dir.join("Main.vm").exists());
+}
+
+#[test]
+fn unknown_options_and_values_are_refused() {
+    assert_usage_error(&["--frobnicate", "Main.jack"], "unknown option '--frobnicate'");
+    assert_usage_error(&["-x", "Main.jack"], "unknown option '-x'");
+    assert_usage_error(&["--emit=vm,pdf", "Main.jack"], "unknown file kind 'pdf' for --emit");
+    assert_usage_error(&["--precedence", "c", "Main.jack"], "unknown precedence mode 'c', expected 'jack' or 'standard'");
+}
+
+#[test]
+fn options_at_the_end_need_their_value() {
+    assert_usage_error(&["Main.jack", "--out-dir"], "missing directory after '--out-dir'");
+    assert_usage_error(&["Main.jack", "-e"], "missing file kinds after '-e'");
+    assert_usage_error(&["Main.jack", "--precedence"], "missing precedence mode after '--precedence'");
+}
+
+#[test]
+fn some_input_is_needed_unless_asking_for_help() {
+    assert_usage_error(&[], "no input files or directories were given");
+    assert_usage_error(&["-v", "--strict-types"], "no input files or directories were given");
+
+    let output = jack2vm(&["--help"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Usage: jack2vm"));
+}
+
+#[test]
+fn recursive_searches_keep_the_layout_of_the_directory_in_the_out_dir() {
+    let directory = test_directory();
+    let source = directory.join("src");
+    write_class(&source.join("Main.jack"));
+    write_class(&source.join("games/pong/Main.jack"));
+    write_class(&source.join("games/snake/Main.jack"));
+
+    // without --recursive, only the files right in the directory are compiled
+    let out_dir = directory.join("flat");
+    let output = jack2vm(&["-q", "--out-dir", out_dir.to_str().unwrap(), source.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(out_dir.join("Main.vm").is_file());
+    assert!(!out_dir.join("games").exists());
+
+    let out_dir = directory.join("tree");
+    let output = jack2vm(&["-q", "-r", "--out-dir", out_dir.to_str().unwrap(), source.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(out_dir.join("Main.vm").is_file());
+    assert!(out_dir.join("games/pong/Main.vm").is_file());
+    assert!(out_dir.join("games/snake/Main.vm").is_file());
+
+    // without --out-dir, every file is compiled next to its source
+    let output = jack2vm(&["-q", "--recursive", source.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(source.join("games/pong/Main.vm").is_file());
+
+    // a directory whose .jack files are all in its subdirectories needs --recursive
+    let output = jack2vm(&[source.join("games").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: no .jack files found in "));
+}
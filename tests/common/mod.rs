@@ -0,0 +1,40 @@
+//! Helpers shared by the integration tests.
+// every test crate includes this module, but not every one uses all of it
+#![allow(dead_code)]
+
+use jack2vm::compilation_engine::CompilationEngine;
+use jack2vm::error::CompileError;
+use jack2vm::parser::parse;
+use jack2vm::vm_writer::VMWriter;
+
+/// Creates a compilation engine that writes the vm code in memory
+pub fn engine() -> CompilationEngine<Vec<u8>> {
+    CompilationEngine::new(VMWriter::from_writer(Vec::new()))
+}
+
+/// Parses and compiles a class in memory
+///
+/// # Returns
+///
+/// * The compilation engine, with the vm code, warnings and symbol tables of the class, or the first error
+pub fn compile(source: &str) -> Result<CompilationEngine<Vec<u8>>, CompileError> {
+    compile_with(source, |_| {})
+}
+
+/// Parses and compiles a class in memory, after setting the options of the compilation engine
+///
+/// # Arguments
+///
+/// * `source` - The jack code of the class
+/// * `configure` - Sets the options, such as the precedence or the strict types, before the class is compiled
+///
+/// # Returns
+///
+/// * The compilation engine, with the vm code, warnings and symbol tables of the class, or the first error
+pub fn compile_with<F: FnOnce(&mut CompilationEngine<Vec<u8>>)>(source: &str, configure: F) -> Result<CompilationEngine<Vec<u8>>, CompileError> {
+    let class = parse(source).expect("the source should parse");
+    let mut compilation_engine = engine();
+    configure(&mut compilation_engine);
+    compilation_engine.compile(&class)?;
+    Ok(compilation_engine)
+}
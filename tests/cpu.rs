@@ -0,0 +1,107 @@
+//! Runs hack programs in the CPU emulator, and checks the whole pipeline against the vm interpreter.
+mod common;
+
+use jack2vm::assembler::{assemble, from_hack_text, to_hack_text};
+use jack2vm::cpu::{alu, compare_ram, Cpu, Stop};
+use jack2vm::parser::parse;
+use jack2vm::vm::{self, Vm};
+use jack2vm::vm2asm::translate_program;
+use jack2vm::vm_command;
+
+/// Compiles jack classes in memory into the name and vm code of every class
+fn compile(classes: &[&str]) -> Vec<(String, String)> {
+    classes.iter().map(|source| {
+        let class = parse(source).expect("the source should parse");
+        let mut compilation_engine = common::engine();
+        compilation_engine.compile(&class).expect("the class should compile");
+        let code = String::from_utf8(compilation_engine.into_vm_writer().into_inner()).expect("vm code is text");
+        (class.name.name, code)
+    }).collect()
+}
+
+#[test]
+fn alu_computes_every_function_of_the_spec() {
+    let (x, y) = (13, -6);
+    let expected = [
+        (0b101010, 0), (0b111111, 1), (0b111010, -1), (0b001100, x), (0b110000, y),
+        (0b001101, !x), (0b110001, !y), (0b001111, -x), (0b110011, -y), (0b011111, x + 1),
+        (0b110111, y + 1), (0b001110, x - 1), (0b110010, y - 1), (0b000010, x + y),
+        (0b010011, x - y), (0b000111, y - x), (0b000000, x & y), (0b010101, x | y),
+    ];
+    for (control, out) in expected {
+        assert_eq!(alu(x, y, control), out, "control bits {:06b}", control);
+    }
+}
+
+#[test]
+fn max_program_reaches_its_end_loop() {
+    let rom = assemble("@R0\nD=M\n@R1\nD=D-M\n@FIRST\nD;JGT\n@R1\nD=M\n@OUT\n0;JMP\n(FIRST)\n@R0\nD=M\n(OUT)\n@R2\nM=D\n(END)\n@END\n0;JMP").unwrap();
+    let rom = from_hack_text(&to_hack_text(&rom)).unwrap();
+    let mut cpu = Cpu::new(rom);
+    cpu.poke(0, -3);
+    cpu.poke(1, 12);
+    assert_eq!(cpu.run(1000), Ok(Stop::EndLoop));
+    assert_eq!(cpu.peek(2), 12);
+    assert_eq!(cpu.cycles(), 14);
+    assert_eq!(compare_ram(cpu.ram(), "|RAM[0]|RAM[1]|RAM[2]|\n|  -3  |  12  |  12  |\n"), Ok(()));
+    assert_eq!(compare_ram(cpu.ram(), "|RAM[1]|RAM[2]|\n|   *  |  -3  |"), Err("RAM[2]: expected -3, found 12".to_string()));
+}
+
+#[test]
+fn cycle_limits_and_runtime_errors() {
+    let mut cpu = Cpu::new(assemble("(LOOP)\nM=M+1\n@LOOP\n0;JMP").unwrap());
+    assert_eq!(cpu.run(301), Ok(Stop::CycleLimit));
+    assert_eq!(cpu.peek(0), 101);
+
+    let mut cpu = Cpu::new(assemble("@30000\nM=1").unwrap());
+    assert!(cpu.run(10).unwrap_err().contains("the address 30000 is outside of the memory"));
+
+    let mut cpu = Cpu::new(assemble("D=1").unwrap());
+    assert_eq!(cpu.run(10), Ok(Stop::EndOfProgram));
+    assert_eq!(cpu.d(), 1);
+
+    assert!(from_hack_text("0000000000000001\n101").unwrap_err().message.contains("16 binary digits"));
+}
+
+#[test]
+fn translated_program_gives_the_same_results_as_the_vm_interpreter() {
+    // Without the OS in vm code, the program can't call OS functions, such as Math.multiply for '*'
+    let files = compile(&[
+        "class Main {
+            static int fib, product;
+            static Array squares;
+            function int fib(int n) {
+                if (n < 2) { return n; }
+                return Main.fib(n - 1) + Main.fib(n - 2);
+            }
+            function void main() {
+                var int i;
+                let fib = Main.fib(12);
+                let product = -(123 + 45) & ~7;
+                let squares = 3000;
+                while (i < 8) {
+                    let squares[i] = (i + i + i) - (i | 5);
+                    let i = i + 1;
+                }
+                return;
+            }
+        }",
+        "class Sys { static int done; function void init() { do Main.main(); let done = true; while (true) {} return; } }",
+    ]);
+
+    let mut vm = Vm::new(&files).unwrap();
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(1_000_000), Ok(vm::Stop::StepLimit));
+
+    let commands: Vec<_> = files.iter()
+        .map(|(name, code)| (name.to_string(), vm_command::parse(code).unwrap().into_iter().map(|(command, _)| command).collect()))
+        .collect();
+    let mut cpu = Cpu::new(assemble(&translate_program(&commands)).unwrap());
+    assert_eq!(cpu.run(1_000_000), Ok(Stop::CycleLimit));
+
+    assert_eq!(cpu.peek(19), -1, "Sys.init should have finished Main.main");
+    assert_eq!(&cpu.ram()[16..20], &vm.ram()[16..20]);
+    assert_eq!(&cpu.ram()[3000..3008], &vm.ram()[3000..3008]);
+    assert_eq!(vm.peek(16), 144);
+    assert_eq!(vm.peek(17), -168);
+}
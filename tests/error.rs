@@ -0,0 +1,105 @@
+//! Renders compile errors and warnings with their snippet of code, and checks that broken code is an error, not a panic.
+mod common;
+
+use jack2vm::error::{CompileError, Span, Warning};
+use jack2vm::parser::parse;
+
+#[test]
+fn errors_show_the_line_and_a_caret_under_the_span() {
+    let source = "class Main {\n  function void f() {\n    let count = 1;\n  }\n}";
+    let error = CompileError::new(Span::new(3, 9, 5), "'count' is not defined".to_string());
+    assert_eq!(error.render("Main.jack", source), "\
+error: 'count' is not defined
+ --> Main.jack:3:9
+  |
+3 |     let count = 1;
+  |         ^^^^^
+");
+    assert_eq!(error.to_string(), "3:9: 'count' is not defined");
+}
+
+#[test]
+fn the_gutter_grows_with_the_line_number_and_empty_spans_get_one_caret() {
+    let source = format!("{}x", "\n".repeat(11));
+    let rendered = Warning::new(Span::new(12, 1, 0), "a warning".to_string()).render("A.jack", &source);
+    assert_eq!(rendered, "\
+warning: a warning
+  --> A.jack:12:1
+   |
+12 | x
+   | ^
+");
+}
+
+#[test]
+fn tabs_are_shown_as_single_spaces_so_the_caret_lines_up() {
+    // the tokenizer counts a tab as a single column
+    let source = "class Main {\n\t\tfield\tint 5;\n}";
+    let error = parse(source).unwrap_err();
+    assert_eq!(error.span, Span::new(2, 13, 1));
+    assert_eq!(error.render("Main.jack", source), "\
+error: expected a variable name, found '5'
+ --> Main.jack:2:13
+  |
+2 |   field int 5;
+  |             ^
+");
+}
+
+#[test]
+fn notes_are_rendered_after_the_message() {
+    let source = "class Main {\n  field int x;\n  field int x;\n}";
+    let error = CompileError::new(Span::new(3, 13, 1), "'x' is already declared".to_string())
+        .with_note(Span::new(2, 13, 1), "'x' is first declared here".to_string());
+    assert_eq!(error.render("Main.jack", source), "\
+error: 'x' is already declared
+ --> Main.jack:3:13
+  |
+3 |   field int x;
+  |             ^
+note: 'x' is first declared here
+ --> Main.jack:2:13
+  |
+2 |   field int x;
+  |             ^
+");
+}
+
+#[test]
+fn spans_past_the_end_of_the_file_have_no_snippet() {
+    let error = CompileError::new(Span::new(7, 1, 1), "expected '}', found the end of the file".to_string());
+    assert_eq!(error.render("Main.jack", "class Main {"), "error: expected '}', found the end of the file\n --> Main.jack:7:1\n");
+}
+
+#[test]
+fn malformed_programs_are_errors_not_panics() {
+    let source = "class Main {
+        field Array entries;
+        static int count;
+        constructor Main new(int size) { let entries = Array.new(size); return this; }
+        method int sum() {
+            var int i, total;
+            let i = 0;
+            let total = 0;
+            while (i < count) { let total = total + entries[i]; let i = i + 1; }
+            if (~(total = 0)) { do Output.printString(\"sum: \"); }
+            return -total;
+        }
+    }";
+    assert!(common::compile(source).is_ok());
+
+    // every prefix of the class is broken somewhere, and so is the class with any single character removed
+    let chars: Vec<char> = source.chars().collect();
+    let mut broken: Vec<String> = (0..chars.len()).map(|end| chars[..end].iter().collect()).collect();
+    broken.extend((0..chars.len()).map(|index| chars.iter().enumerate().filter(|(other, _)| *other != index).map(|(_, ch)| *ch).collect()));
+    broken.extend(["class", "class Main { function }", "class Main { method void f() { let x[ = 1; } }", "\"", "/*", "99999"].map(str::to_string));
+
+    for source in broken.iter() {
+        // code that still parses must then compile or fail with an error
+        if let Ok(class) = parse(source) {
+            let mut compilation_engine = common::engine();
+            let _ = compilation_engine.compile(&class);
+        }
+    }
+    assert!(broken[..chars.len()].iter().all(|source| parse(source).is_err()));
+}
@@ -0,0 +1,342 @@
+//! Compiles tricky expressions and compares the vm code with the output of the nand2tetris reference compiler.
+mod common;
+
+use jack2vm::error::Warning;
+use jack2vm::utility::Precedence;
+
+/// Compiles a class and returns the lines of the vm code
+fn compile(source: &str) -> Vec<String> {
+    let (lines, warnings) = compile_with(source, Precedence::LeftToRight);
+    assert_eq!(warnings, []);
+    lines
+}
+
+/// Compiles a class with the given operator precedence, and returns the lines of the vm code and the warnings
+fn compile_with(source: &str, precedence: Precedence) -> (Vec<String>, Vec<Warning>) {
+    let compilation_engine = common::compile_with(source, |compilation_engine| compilation_engine.set_precedence(precedence))
+        .expect("the class should compile");
+    let warnings = compilation_engine.warnings().to_vec();
+    let vm = String::from_utf8(compilation_engine.into_vm_writer().into_inner()).expect("vm code is text");
+    (vm.lines().map(|line| line.to_string()).collect(), warnings)
+}
+
+/// Compiles `let x = <expression>;` inside a function and returns the vm lines of the expression only.
+/// The locals are a, b, c, d, x (local 0 - 4), arr (local 5) and p (local 6), and s is static 0.
+/// Every variable is assigned before the expression and read after it, so only the expression can cause warnings.
+fn compile_expression(expression: &str) -> Vec<String> {
+    let (lines, warnings) = compile_expression_with(expression, Precedence::LeftToRight);
+    assert_eq!(warnings, []);
+    lines
+}
+
+/// Like compile_expression, with the given operator precedence, also returning the warnings
+fn compile_expression_with(expression: &str, precedence: Precedence) -> (Vec<String>, Vec<Warning>) {
+    let source = format!(
+        "class Main {{
+            static int s;
+            function void main() {{
+                var int a, b, c, d, x;
+                var Array arr;
+                var Point p;
+                let a = 0; let b = 0; let c = 0; let d = 0; let x = 0;
+                let arr = Array.new(2); let p = Point.new();
+                let s = 0;
+                let x = {};
+                do Output.printInt(x + a + b + c + d + s);
+                do arr.dispose();
+                do p.dispose();
+                return;
+            }}
+        }}",
+        expression
+    );
+    let (lines, warnings) = compile_with(&source, precedence);
+    assert_eq!(lines[0], "function Main.main 7");
+    // the expression starts after `let s = 0;` and ends with `let x`
+    let start = lines.iter().position(|line| line == "pop static 0").expect("s is assigned") + 1;
+    let end = lines.iter().rposition(|line| line == "pop local 4").expect("x is assigned");
+    (lines[start..end].to_vec(), warnings)
+}
+
+/// The lines of the expected vm code, without the indentation of the test
+fn vm(code: &str) -> Vec<String> {
+    code.lines().map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect()
+}
+
+#[test]
+fn parenthesized_term_in_the_middle() {
+    assert_eq!(compile_expression("a - (b * c) + d"), vm("
+        push local 0
+        push local 1
+        push local 2
+        call Math.multiply 2
+        sub
+        push local 3
+        add
+    "));
+}
+
+#[test]
+fn operators_are_applied_from_left_to_right() {
+    // the order of both is warned about, see mixed_precedence_warning
+    assert_eq!(compile_expression_with("1 + 2 * 3", Precedence::LeftToRight).0, vm("
+        push constant 1
+        push constant 2
+        add
+        push constant 3
+        call Math.multiply 2
+    "));
+    assert_eq!(compile_expression_with("a / b - c / d", Precedence::LeftToRight).0, vm("
+        push local 0
+        push local 1
+        call Math.divide 2
+        push local 2
+        sub
+        push local 3
+        call Math.divide 2
+    "));
+}
+
+#[test]
+fn comparison_with_a_negative_number() {
+    assert_eq!(compile_expression("x < -1"), vm("
+        push local 4
+        push constant 1
+        neg
+        lt
+    "));
+}
+
+#[test]
+fn minus_of_a_negative_term() {
+    assert_eq!(compile_expression("-a - -b"), vm("
+        push local 0
+        neg
+        push local 1
+        neg
+        sub
+    "));
+}
+
+#[test]
+fn not_of_a_parenthesized_comparison() {
+    assert_eq!(compile_expression("~(a = 0) & (b > c) | s"), vm("
+        push local 0
+        push constant 0
+        eq
+        not
+        push local 1
+        push local 2
+        gt
+        and
+        push static 0
+        or
+    "));
+}
+
+#[test]
+fn keyword_constants() {
+    assert_eq!(compile_expression("true & ~false | (null = a)"), vm("
+        push constant 0
+        not
+        push constant 0
+        not
+        and
+        push constant 0
+        push local 0
+        eq
+        or
+    "));
+}
+
+#[test]
+fn string_with_operators_inside() {
+    assert_eq!(compile_expression("\"a+b\""), vm("
+        push constant 3
+        call String.new 1
+        push constant 97
+        call String.appendChar 2
+        push constant 43
+        call String.appendChar 2
+        push constant 98
+        call String.appendChar 2
+    "));
+}
+
+#[test]
+fn string_with_parentheses_as_an_argument() {
+    assert_eq!(compile_expression("Text.length(\")(\", a - 1)"), vm("
+        push constant 2
+        call String.new 1
+        push constant 41
+        call String.appendChar 2
+        push constant 40
+        call String.appendChar 2
+        push local 0
+        push constant 1
+        sub
+        call Text.length 2
+    "));
+}
+
+#[test]
+fn array_entries_in_an_expression() {
+    assert_eq!(compile_expression("arr[a + 1] * arr[arr[b]]"), vm("
+        push local 0
+        push constant 1
+        add
+        push local 5
+        add
+        pop pointer 1
+        push that 0
+        push local 1
+        push local 5
+        add
+        pop pointer 1
+        push that 0
+        push local 5
+        add
+        pop pointer 1
+        push that 0
+        call Math.multiply 2
+    "));
+}
+
+#[test]
+fn nested_calls_as_arguments() {
+    assert_eq!(compile_expression("Math.max(a, Math.min(b + c, d)) + p.getX()"), vm("
+        push local 0
+        push local 1
+        push local 2
+        add
+        push local 3
+        call Math.min 2
+        call Math.max 2
+        push local 6
+        call Point.getX 1
+        add
+    "));
+}
+
+#[test]
+fn assignment_to_an_array_entry() {
+    let lines = compile("class Main {
+        function void main(Array a, Array b, int i) {
+            let a[i] = b[i + 1] - a[i];
+            return;
+        }
+    }");
+    assert_eq!(lines, vm("
+        function Main.main 0
+        push argument 2
+        push argument 0
+        add
+        push argument 2
+        push constant 1
+        add
+        push argument 1
+        add
+        pop pointer 1
+        push that 0
+        push argument 2
+        push argument 0
+        add
+        pop pointer 1
+        push that 0
+        sub
+        pop temp 0
+        pop pointer 1
+        push temp 0
+        pop that 0
+        push constant 0
+        return
+    "));
+}
+
+#[test]
+fn method_calls_on_this_and_fields() {
+    let lines = compile("class Point {
+        field int x, y;
+        method int distance(Point other) {
+            return Math.abs(x - other.getX()) + (y * distance(this));
+        }
+        method int getX() { return x; }
+    }");
+    assert_eq!(lines, vm("
+        function Point.distance 0
+        push argument 0
+        pop pointer 0
+        push this 0
+        push argument 1
+        call Point.getX 1
+        sub
+        call Math.abs 1
+        push this 1
+        push pointer 0
+        push pointer 0
+        call Point.distance 2
+        call Math.multiply 2
+        add
+        return
+        function Point.getX 0
+        push argument 0
+        pop pointer 0
+        push this 0
+        return
+    "));
+}
+
+#[test]
+fn standard_precedence() {
+    let (lines, warnings) = compile_expression_with("a + b * c - d / 2 < x & ~s", Precedence::Standard);
+    assert_eq!(lines, vm("
+        push local 0
+        push local 1
+        push local 2
+        call Math.multiply 2
+        add
+        push local 3
+        push constant 2
+        call Math.divide 2
+        sub
+        push local 4
+        lt
+        push static 0
+        not
+        and
+    "));
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn standard_precedence_keeps_parentheses_and_left_to_right_within_a_level() {
+    assert_eq!(compile_expression_with("(a + b) * c - d + 1", Precedence::Standard).0, vm("
+        push local 0
+        push local 1
+        add
+        push local 2
+        call Math.multiply 2
+        push local 3
+        sub
+        push constant 1
+        add
+    "));
+}
+
+#[test]
+fn mixed_precedence_warning() {
+    let (_, warnings) = compile_expression_with("a + b * c", Precedence::LeftToRight);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.contains("'+' is applied before '*'"));
+
+    let (_, warnings) = compile_expression_with("x < (a + b * c)", Precedence::LeftToRight);
+    assert_eq!(warnings.len(), 1);
+}
+
+#[test]
+fn no_warning_when_the_order_is_the_same_either_way() {
+    for expression in ["a * b + c", "a + b - c", "a + b < c & d", "(a + b) * c", "-a * b"] {
+        let (_, warnings) = compile_expression_with(expression, Precedence::LeftToRight);
+        assert!(warnings.is_empty(), "unexpected warning for {}", expression);
+    }
+}
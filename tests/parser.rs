@@ -0,0 +1,102 @@
+//! Parses jack classes into syntax trees, whatever their formatting, and checks where parse errors point.
+use jack2vm::ast::{Op, Statement, Term};
+use jack2vm::error::Span;
+use jack2vm::parse_tree_writer::ParseTreeWriter;
+use jack2vm::parser::parse;
+use jack2vm::xmlwriter::XmlWriter;
+
+/// Parses a class and writes its parse tree as xml, which leaves out the spans
+fn parse_tree(source: &str) -> String {
+    let class = parse(source).expect("the source should parse");
+    let mut parse_tree_writer = ParseTreeWriter::new(XmlWriter::from_writer(Vec::new()));
+    parse_tree_writer.write_class(&class).expect("writing to memory can't fail");
+    String::from_utf8(parse_tree_writer.into_xml_writer().into_inner()).expect("xml is text")
+}
+
+/// Parses a class whose first subroutine is made of the given statements
+fn statements(statements: &str) -> Vec<Statement> {
+    let class = parse(&format!("class Main {{ function void main() {{ {} }} }}", statements)).expect("the source should parse");
+    class.subroutines[0].statements.clone()
+}
+
+#[test]
+fn formatting_does_not_change_the_tree() {
+    let one_line = "class Main { function int f(int a, int b) { var int x; let x = (a + b) * Math.max(a, b); if (x > 0) { return x; } else { return -x; } } }";
+    let spread = "
+        class Main
+        {
+            function int f(
+                int a,
+                int b
+            ) {
+                var int x;
+                let x =
+                    (a
+                        + b)
+                    * Math.max(a,
+                               b);
+                if (x > 0) { return x; }
+                else
+                {
+                    return -x;
+                }
+            }
+        }";
+    assert_eq!(parse_tree(spread), parse_tree(one_line));
+}
+
+#[test]
+fn several_statements_on_one_line() {
+    let statements = statements("var int a; let a = 1; let a = a + 2; do Output.printInt(a); return;");
+    assert_eq!(statements.len(), 4);
+    let columns: Vec<usize> = statements.iter().map(|statement| statement.span().column).collect();
+    assert_eq!(columns, [48, 59, 74, 97]);
+    assert!(matches!(statements[2], Statement::Do { .. }));
+    assert!(matches!(statements[3], Statement::Return { value: None, .. }));
+}
+
+#[test]
+fn multi_line_expressions_keep_every_operator() {
+    let statements = statements("var int a;\nlet a = 1\n  + 2\n  - 3;\nreturn;");
+    let Statement::Let { value, .. } = &statements[0] else { panic!("expected a let statement") };
+    assert!(matches!(value.term, Term::IntegerConstant { value: 1, .. }));
+    let rest: Vec<(Op, usize)> = value.rest.iter().map(|(op, term)| (*op, term.span().line)).collect();
+    assert_eq!(rest, [(Op::Add, 3), (Op::Sub, 4)]);
+}
+
+#[test]
+fn braces_and_semicolons_inside_strings_are_text() {
+    let statements = statements("do Output.printString(\"} { ; ) (\"); do Output.printString(\"// not a comment\"); return;");
+    assert_eq!(statements.len(), 3);
+    let strings: Vec<&str> = statements[..2].iter().map(|statement| {
+        let Statement::Do { call, .. } = statement else { panic!("expected a do statement, found {:?}", statement) };
+        match &call.arguments[0].term {
+            Term::StringConstant { value, .. } => { value.as_str() }
+            term => { panic!("expected a string constant, found {:?}", term) }
+        }
+    }).collect();
+    assert_eq!(strings, ["} { ; ) (", "// not a comment"]);
+}
+
+#[test]
+fn parse_errors_point_at_the_unexpected_token() {
+    let error = parse("class Main {\n  function void f() {\n    let x = 1\n    return;\n  }\n}").unwrap_err();
+    assert_eq!(error.span, Span::new(4, 5, 6));
+    assert_eq!(error.message, "expected ';', found 'return'");
+
+    let error = parse("class Main { function void f() { do g(1,); return; } }").unwrap_err();
+    assert_eq!((error.span.line, error.span.column), (1, 41));
+    assert!(error.message.starts_with("expected "), "{}", error);
+
+    let error = parse("class Main { method void f() { let \"x\" = 1; } }").unwrap_err();
+    assert_eq!(error.span, Span::new(1, 36, 3));
+    assert_eq!(error.message, "expected a variable name, found \"x\"");
+
+    // a class that isn't closed ends right after its last token
+    let error = parse("class Main {\n  field int x;").unwrap_err();
+    assert_eq!(error.span, Span::new(2, 15, 1));
+    assert!(error.message.ends_with("found the end of the file"), "{}", error);
+
+    let error = parse("class Main { } class Other { }").unwrap_err();
+    assert_eq!((error.span.column, error.message.as_str()), (16, "expected the end of the file after the class, found 'class'"));
+}
@@ -0,0 +1,31 @@
+// Tests Mult.asm on the CPU emulator.
+
+load Mult.asm,
+output-file Mult.out,
+compare-to Mult.cmp,
+output-list RAM[0]%D2.6.2 RAM[1]%D2.6.2 RAM[2]%D2.6.2;
+
+set RAM[0] 0, set RAM[1] 0, set RAM[2] -1;
+repeat 20 {
+  ticktock;
+}
+output;
+
+set PC 0, set RAM[0] 3, set RAM[1] 1, set RAM[2] -1;
+repeat 50 {
+  ticktock;
+}
+output;
+
+set PC 0, set RAM[0] 6, set RAM[1] 7, set RAM[2] -1;
+while PC <> 18 {
+  ticktock;
+}
+output;
+
+/* Big numbers wrap around */
+set PC 0, set RAM[0] 300, set RAM[1] 200, set RAM[2] -1;
+while PC <> 18 {
+  ticktock;
+}
+output;
@@ -0,0 +1,193 @@
+//! Checks the errors the compiler reports for code that parses, but has no meaning.
+mod common;
+
+use common::compile;
+use jack2vm::error::CompileError;
+use jack2vm::parser::parse;
+use jack2vm::signatures::Signatures;
+
+/// Compiles a class that has an error, and returns the error
+fn error(source: &str) -> CompileError {
+    compile(source).err().expect("the class should not compile")
+}
+
+/// Compiles the first of the classes of a whole program, returning the first error
+fn compile_program(sources: &[&str]) -> Result<(), CompileError> {
+    let classes: Vec<_> = sources.iter().map(|source| parse(source).expect("the source should parse")).collect();
+    let mut signatures = Signatures::new();
+    for class in classes.iter() {
+        signatures.add_class(class);
+    }
+    signatures.set_complete(true);
+    let mut compilation_engine = common::engine();
+    compilation_engine.set_signatures(signatures);
+    compilation_engine.compile(&classes[0])
+}
+
+#[test]
+fn undefined_variables_are_reported_where_they_are_used() {
+    let error = error("class Main {\n  function int f() {\n    var int a;\n    return a + b;\n  }\n}");
+    assert_eq!(error.message, "the variable 'b' is not defined");
+    assert_eq!((error.span.line, error.span.column), (4, 16));
+
+    assert!(self::error("class Main { function void f() { var Array a; let a[i] = 1; return; } }").message.contains("'i' is not defined"));
+    assert!(self::error("class Main { function int f() { return c[1]; } }").message.contains("'c' is not defined"));
+}
+
+#[test]
+fn assignments_need_a_variable() {
+    assert_eq!(error("class Main { function void f() { let x = 1; return; } }").message, "can't assign to 'x': the variable 'x' is not defined");
+    assert_eq!(error("class Main { function void f() { let f = 1; return; } }").message, "can't assign to 'f': 'f' is a subroutine, not a variable");
+    assert_eq!(error("class Main { function void f() { let Main = 1; return; } }").message, "can't assign to 'Main': 'Main' is a class, not a variable");
+}
+
+#[test]
+fn subroutines_called_on_this_must_exist() {
+    let error = error("class Main { method void f() { do g(); return; } }");
+    assert_eq!(error.message, "the class Main has no subroutine named 'g'");
+    assert_eq!(error.span.column, 35);
+    assert!(compile("class Main { method void f() { do f(); return; } }").is_ok());
+}
+
+#[test]
+fn functions_have_no_object() {
+    assert!(error("class Main { field int x; function int f() { return x; } }").message.contains("the field 'x' can't be used in a function"));
+    assert!(error("class Main { field Array x; function void f() { do x.dispose(); return; } }").message.contains("the field 'x' can't be used in a function"));
+    assert!(error("class Main { function Main f() { return this; } }").message.contains("'this' can't be used in a function"));
+    assert!(compile("class Main { field int x; static int y; method int f() { return x + y; } constructor Main new() { return this; } }").is_ok());
+}
+
+#[test]
+fn calls_must_match_the_kind_of_the_subroutine() {
+    let point = "class Point { field int x; constructor Point new() { return this; } method int getX() { return x; } function int zero() { return 0; } }";
+    assert!(compile_program(&["class Main { function void f() { var Point p; let p = Point.new(); do p.getX(); do Point.zero(); return; } }", point]).is_ok());
+
+    let error = compile_program(&["class Main { function void f() { do Point.getX(); return; } }", point]).unwrap_err();
+    assert_eq!(error.message, "Point.getX is a method, so it must be called on an object");
+    let error = compile_program(&["class Main { function void f() { var Point p; do p.zero(); return; } }", point]).unwrap_err();
+    assert_eq!(error.message, "Point.zero is a function, so it must be called as Point.zero(...)");
+    assert_eq!(error.span.column, 50);
+    assert!(self::error("class Main { function void f() { do g(); return; } method void g() { return; } }").message
+        .contains("the method Main.g can't be called from a function"));
+    assert!(self::error("class Main { function void f() { var int a; do a.g(); return; } }").message
+        .contains("'a' is of type int, which has no subroutines"));
+}
+
+#[test]
+fn calls_must_have_the_right_number_of_arguments() {
+    let error = error("class Main { function void f() { do Output.printInt(1, 2); return; } }");
+    assert_eq!(error.message, "Output.printInt takes 1 argument(s), but is called with 2");
+    assert_eq!(error.span.column, 44);
+    assert!(self::error("class Main { function void f() { var String s; do s.appendChar(); return; } }").message.contains("String.appendChar takes 1"));
+    assert!(self::error("class Main { function void f() { do Output.printLine(); return; } }").message.contains("the class Output has no subroutine named 'printLine'"));
+}
+
+#[test]
+fn classes_must_be_part_of_the_program() {
+    // a single class may call classes compiled separately, a whole program may not
+    assert!(compile("class Main { function void f() { do Game.run(); return; } }").is_ok());
+    let error = compile_program(&["class Main { function void f() { do Game.run(); return; } }"]).unwrap_err();
+    assert_eq!(error.message, "the class 'Game' is not defined");
+    let error = compile_program(&["class Main { function void f() { do game.run(); return; } }"]).unwrap_err();
+    assert_eq!(error.message, "the variable 'game' is not defined");
+}
+
+#[test]
+fn return_statements_must_match_the_return_type() {
+    let error = error("class Main { function void f() { return 1; } }");
+    assert_eq!(error.message, "Main.f is declared void, so it can't return a value");
+    assert_eq!(error.span.column, 41);
+    assert_eq!(self::error("class Main { function int f() { return; } }").message, "Main.f returns int, so the return statement needs a value");
+}
+
+#[test]
+fn constructors_that_do_not_return_this_are_warnings() {
+    let compilation_engine = compile("class Main { constructor Main new() { return null; } }").unwrap();
+    let warnings: Vec<_> = compilation_engine.warnings().iter().map(|warning| (warning.message.as_str(), warning.span.column)).collect();
+    assert_eq!(warnings, [("Main.new is a constructor, so it should return this", 39)]);
+    assert_eq!(compile("class Main { constructor Main new() { return this; } }").unwrap().warnings(), []);
+}
+
+#[test]
+fn every_path_must_return() {
+    let error = error("class Main {\n  function int f(boolean b) {\n    if (b) { return 1; }\n  }\n}");
+    assert_eq!(error.message, "Main.f can reach its end without a return statement");
+    assert_eq!((error.span.line, error.span.column), (2, 16));
+    assert!(self::error("class Main { function void f() { } }").message.contains("without a return statement"));
+    assert!(compile("class Main { function int f(boolean b) { if (b) { return 1; } else { return 2; } } }").is_ok());
+    assert!(compile("class Main { function void f() { while (true) { do Output.println(); } } }").is_ok());
+}
+
+#[test]
+fn statements_after_a_return_are_unreachable() {
+    let compilation_engine = compile("class Main {\n  function int f() {\n    var int x;\n    return 1;\n    let x = 2;\n    return x;\n  }\n}").unwrap();
+    let warnings = compilation_engine.warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].message, "this statement is never reached");
+    assert_eq!(warnings[0].span.line, 5);
+}
+
+#[test]
+fn names_are_declared_once_per_scope() {
+    let error = error("class Main {\n  function void f(int x) {\n    var int y, x;\n    return;\n  }\n}");
+    assert_eq!(error.message, "'x' is already declared in Main.f");
+    assert_eq!((error.span.line, error.span.column), (3, 16));
+    assert_eq!(error.notes, [(jack2vm::error::Span::new(2, 23, 1), "'x' is first declared here".to_string())]);
+    let rendered = error.render("Main.jack", "class Main {\n  function void f(int x) {\n    var int y, x;\n    return;\n  }\n}");
+    assert!(rendered.contains("note: 'x' is first declared here\n --> Main.jack:2:23"), "{}", rendered);
+
+    assert_eq!(self::error("class Main { field int a; static int a; }").message, "'a' is already declared in the class Main");
+}
+
+#[test]
+fn locals_shadowing_class_variables_are_warned_about() {
+    let compilation_engine = compile("class Main {\n  field int x;\n  static int y;\n  method void f(int x) { return; }\n  function void g() { var int x, y; return; }\n}").unwrap();
+    let warnings: Vec<_> = compilation_engine.warnings().iter().filter(|warning| warning.message.contains("shadows")).collect();
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert_eq!(warnings[0].message, "the argument 'x' shadows the field of the same name");
+    assert_eq!((warnings[0].span.line, warnings[0].notes[0].0.line), (4, 2));
+    assert_eq!(warnings[1].message, "the local variable 'y' shadows the static variable of the same name");
+}
+
+#[test]
+fn unused_variables_are_warned_about() {
+    let compilation_engine = compile("class Main {
+        field int used, unused;
+        static int counter;
+        method int f(int a, int b) {
+            var int read, written, never;
+            var Array entries;
+            let written = a;
+            let read = 0;
+            let entries = Array.new(1);
+            let entries[read] = used;
+            return read;
+        }
+    }").unwrap();
+    let messages: Vec<_> = compilation_engine.warnings().iter().map(|warning| warning.message.as_str()).collect();
+    assert_eq!(messages, [
+        "the argument 'b' is never used",
+        "the local variable 'written' is assigned, but never read",
+        "the local variable 'never' is never used",
+        "the field 'unused' is never used",
+        "the static variable 'counter' is never used",
+    ]);
+}
+
+#[test]
+fn locals_read_before_they_are_assigned_are_warned_about() {
+    let compilation_engine = compile("class Main {
+        function int f(boolean b) {
+            var int sum, i, j, k;
+            if (b) { let i = 1; let j = 1; } else { let i = 2; }
+            if (b) { return 0; } else { let k = 3; }
+            while (b) { let sum = sum + i + k; }
+            return j + sum;
+        }
+    }").unwrap();
+    let warnings: Vec<_> = compilation_engine.warnings().iter().filter(|warning| warning.message.contains("before it is assigned")).collect();
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert_eq!(warnings[0].message, "the local variable 'sum' may be read before it is assigned");
+    assert_eq!((warnings[0].span.line, warnings[0].span.column), (6, 35));
+    assert_eq!(warnings[1].message, "the local variable 'j' may be read before it is assigned");
+}
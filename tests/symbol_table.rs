@@ -0,0 +1,75 @@
+//! Checks the scopes of the symbol table.
+mod common;
+
+use common::compile;
+use jack2vm::error::Span;
+use jack2vm::symbol_table::{dump_json, dump_text, DefineError, SymbolTable};
+use jack2vm::utility::Kind;
+
+#[test]
+fn names_resolve_from_the_innermost_scope_outwards() {
+    let mut symbol_table = SymbolTable::new();
+    symbol_table.define("x".to_string(), "int".to_string(), Kind::FIELD, Span::new(1, 1, 1)).unwrap();
+    symbol_table.define("count".to_string(), "int".to_string(), Kind::STATIC, Span::new(2, 1, 5)).unwrap();
+
+    symbol_table.start_subroutine();
+    symbol_table.define("x".to_string(), "Array".to_string(), Kind::ARG, Span::new(3, 1, 1)).unwrap();
+    symbol_table.push_scope();
+    symbol_table.define("y".to_string(), "char".to_string(), Kind::VAR, Span::new(4, 1, 1)).unwrap();
+    assert_eq!(symbol_table.resolve("x").map(|symbol| (symbol.get_kind(), symbol.get_data_type())), Some((Kind::ARG, "Array")));
+    assert_eq!(symbol_table.resolve("count").map(|symbol| symbol.get_kind()), Some(Kind::STATIC));
+    assert_eq!(symbol_table.resolve("y").map(|symbol| symbol.get_index()), Some(0));
+
+    symbol_table.pop_scope();
+    assert!(symbol_table.resolve("y").is_none());
+    symbol_table.end_subroutine();
+    assert_eq!(symbol_table.resolve("x").map(|symbol| symbol.get_kind()), Some(Kind::FIELD));
+    assert!(symbol_table.resolve("missing").is_none());
+}
+
+#[test]
+fn names_are_defined_once_per_scope() {
+    let mut symbol_table = SymbolTable::new();
+    symbol_table.start_subroutine();
+    symbol_table.define("a".to_string(), "int".to_string(), Kind::VAR, Span::new(1, 5, 1)).unwrap();
+    let error = symbol_table.define("a".to_string(), "int".to_string(), Kind::ARG, Span::new(2, 5, 1)).unwrap_err();
+    assert_eq!(error, DefineError::Redeclared(Span::new(1, 5, 1)));
+    assert_eq!(symbol_table.define("b".to_string(), "int".to_string(), Kind::NONE, Span::default()), Err(DefineError::NoKind));
+
+    symbol_table.push_scope();
+    symbol_table.define("a".to_string(), "int".to_string(), Kind::VAR, Span::new(3, 5, 1)).unwrap();
+    assert_eq!(symbol_table.var_count(Kind::VAR), 2);
+    assert_eq!(symbol_table.symbols().len(), 1);
+}
+
+#[test]
+fn dumps_list_the_symbols_of_the_class_and_every_subroutine() {
+    let compilation_engine = compile("class Point {
+        field int x;
+        static int count;
+        method int plus(Point other) { var int sum; let sum = x + other.getX() + count; return sum; }
+        method int getX() { return x; }
+    }").unwrap();
+    let scopes = compilation_engine.symbol_dump();
+
+    assert_eq!(dump_text(scopes), "\
+class Point
+name   type  kind    #
+x      int   field   0
+count  int   static  0
+
+method Point.plus
+name   type   kind      #
+this   Point  argument  0
+other  Point  argument  1
+sum    int    local     0
+
+method Point.getX
+name  type   kind      #
+this  Point  argument  0
+");
+    let json = dump_json(scopes);
+    assert!(json.starts_with("{\n  \"class\": \"Point\",\n  \"symbols\": [\n    {\"name\": \"x\", \"type\": \"int\", \"kind\": \"field\", \"index\": 0},"), "{}", json);
+    assert!(json.contains("\"name\": \"Point.plus\",\n      \"kind\": \"method\""), "{}", json);
+    assert!(json.contains("{\"name\": \"sum\", \"type\": \"int\", \"kind\": \"local\", \"index\": 0}"), "{}", json);
+}
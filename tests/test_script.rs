@@ -0,0 +1,120 @@
+//! Runs nand2tetris test scripts against the vm interpreter and the CPU emulator.
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use jack2vm::test_script::{parse, run_file, Command};
+
+static NEXT_DIRECTORY: AtomicUsize = AtomicUsize::new(0);
+
+/// Makes an empty directory for the files of a test
+fn test_directory() -> PathBuf {
+    let index = NEXT_DIRECTORY.fetch_add(1, Ordering::SeqCst);
+    let directory = std::env::temp_dir().join(format!("jack2vm_test_script_{}_{}", std::process::id(), index));
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Copies a directory of tests/scripts, so the output file isn't written into the source tree
+fn copy_scripts(name: &str) -> PathBuf {
+    let directory = test_directory();
+    let source = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/scripts").join(name);
+    for entry in fs::read_dir(source).unwrap() {
+        let path = entry.unwrap().path();
+        fs::copy(&path, directory.join(path.file_name().unwrap())).unwrap();
+    }
+    directory
+}
+
+#[test]
+fn basic_test_of_project_7_on_the_vm_interpreter() {
+    let directory = copy_scripts("BasicTest");
+    let report = run_file(&directory.join("BasicTestVME.tst"), 1000).unwrap();
+    assert!(report.compared);
+    assert_eq!(fs::read_to_string(directory.join("BasicTest.out")).unwrap(), fs::read_to_string(directory.join("BasicTest.cmp")).unwrap());
+}
+
+#[test]
+fn mult_on_the_cpu_emulator() {
+    let directory = copy_scripts("Mult");
+    let report = run_file(&directory.join("Mult.tst"), 100_000).unwrap();
+    assert!(report.compared);
+    assert_eq!(report.output.len(), 5);
+}
+
+#[test]
+fn programs_with_sys_init_start_there_without_a_call() {
+    let directory = test_directory();
+    fs::write(directory.join("Sys.vm"), "function Sys.init 0\npush constant 4\ncall Main.double 1\npop static 0\nlabel END\ngoto END").unwrap();
+    fs::write(directory.join("Main.vm"), "function Main.double 0\npush argument 0\npush argument 0\nadd\nreturn").unwrap();
+    fs::write(directory.join("Double.tst"), "
+        load,
+        output-list sp%D1.4.1 RAM[16]%X1.4.1 RAM[16]%B1.8.1 RAM[16]%D2.3.2;
+        set sp 261;
+        echo \"running\";
+        repeat 12 { vmstep; }
+        output;
+    ").unwrap();
+    let report = run_file(&directory.join("Double.tst"), 1000).unwrap();
+    assert!(!report.compared);
+    assert_eq!(report.echoes, ["running"]);
+    assert_eq!(report.output, [
+        "|  sp  |RAM[16| RAM[16]  |RAM[16]|",
+        "|  261 | 0008 | 00001000 |    8  |",
+    ]);
+}
+
+#[test]
+fn clear_echo_removes_the_earlier_echoes() {
+    let directory = test_directory();
+    fs::write(directory.join("Main.vm"), "function Main.main 0\npush constant 0\nreturn").unwrap();
+    fs::write(directory.join("Echo.tst"), "load; echo \"first\"; echo \"second\"; clear-echo; echo \"third\";").unwrap();
+    let report = run_file(&directory.join("Echo.tst"), 1000).unwrap();
+    assert_eq!(report.echoes, ["third"]);
+}
+
+#[test]
+fn comparison_failures_name_the_line() {
+    let directory = test_directory();
+    fs::write(directory.join("Inc.asm"), "@R0\nM=M+1").unwrap();
+    fs::write(directory.join("Inc.cmp"), "|RAM[0]|\n|  *   |\n|    3 |\n").unwrap();
+    fs::write(directory.join("Inc.tst"), "
+        load Inc.asm, output-file Inc.out, compare-to Inc.cmp, output-list RAM[0]%D1.4.1;
+        set RAM[0] 1, output;
+        ticktock, ticktock, output;
+        set PC 0, ticktock, ticktock, output;
+    ").unwrap();
+    let error = run_file(&directory.join("Inc.tst"), 1000).unwrap_err();
+    assert!(error.contains("comparison failure at line 3"), "{}", error);
+    assert!(error.contains("expected: |    3 |\n   found: |    2 |"), "{}", error);
+    assert!(error.contains("Inc.tst:4:"), "{}", error);
+    assert_eq!(fs::read_to_string(directory.join("Inc.out")).unwrap(), "|RAM[0]|\n|    1 |\n|    2 |\n");
+}
+
+#[test]
+fn parse_commands_and_errors() {
+    let commands = parse("load A.vm, /* a comment */ repeat { vmstep; } while RAM[0] < %X10 { vmstep; }").unwrap();
+    assert_eq!(commands[0].0, Command::Load(Some("A.vm".to_string())));
+    assert!(matches!(&commands[1].0, Command::Repeat { count: None, body } if body.len() == 1));
+    assert!(matches!(&commands[2].0, Command::While { condition, .. } if condition.value == 16));
+
+    let error = parse("load A.vm,\nrepeat 3 { vmstep;").unwrap_err();
+    assert_eq!(error.span.line, 2);
+    assert!(error.message.contains("never closed"), "{}", error);
+    assert!(parse("output-list RAM[0]%Q1.6.1;").unwrap_err().message.contains("not a format"));
+    assert!(parse("set RAM[0] 70000;").unwrap_err().message.contains("not a 16-bit value"));
+    assert!(parse("tick;").unwrap_err().message.contains("unknown command 'tick'"));
+}
+
+#[test]
+fn loops_without_steps_are_stopped() {
+    let directory = test_directory();
+    fs::write(directory.join("Inc.asm"), "@R0\nM=M+1").unwrap();
+    fs::write(directory.join("Forever.tst"), "load Inc.asm, output-list RAM[0]%D1.4.1;\nrepeat { echo \"x\"; }").unwrap();
+    let error = run_file(&directory.join("Forever.tst"), 50).unwrap_err();
+    assert!(error.contains("the loops of the script ran more than 50 times"), "{}", error);
+
+    fs::write(directory.join("While.tst"), "load Inc.asm;\nwhile RAM[0] <> 1 { echo \"x\"; }").unwrap();
+    let error = run_file(&directory.join("While.tst"), 50).unwrap_err();
+    assert!(error.contains("While.tst:2:"), "{}", error);
+}
@@ -0,0 +1,102 @@
+//! Breaks jack code into tokens, and checks the tokenizer api and the token spans.
+use jack2vm::error::Span;
+use jack2vm::tokenizer::{TokenType, Tokenizer};
+
+/// Tokenizes jack code that is known to be valid, into the type and text of every token
+fn tokens(source: &str) -> Vec<(TokenType, String)> {
+    Tokenizer::new(source).expect("the source should tokenize").tokens().iter()
+        .map(|token| (token.kind, token.text.to_string()))
+        .collect()
+}
+
+#[test]
+fn the_api_describes_the_current_token() {
+    let mut tokenizer = Tokenizer::new("let x = \"a b\" + 42;").unwrap();
+    assert_eq!(tokenizer.token_type(), None);
+
+    assert!(tokenizer.has_more_tokens());
+    tokenizer.advance();
+    assert_eq!(tokenizer.token_type(), Some(TokenType::Keyword));
+    assert_eq!(tokenizer.keyword(), Some("let"));
+    assert_eq!(tokenizer.identifier(), None);
+
+    tokenizer.advance();
+    assert_eq!(tokenizer.identifier(), Some("x"));
+    tokenizer.advance();
+    assert_eq!(tokenizer.symbol(), Some('='));
+    tokenizer.advance();
+    assert_eq!(tokenizer.token_type(), Some(TokenType::StringConst));
+    assert_eq!(tokenizer.string_val(), Some("a b"));
+    assert_eq!(tokenizer.int_val(), None);
+    tokenizer.advance();
+    assert_eq!(tokenizer.symbol(), Some('+'));
+    assert_eq!(tokenizer.peek().map(|token| token.text.as_str()), Some("42"));
+    tokenizer.advance();
+    assert_eq!(tokenizer.int_val(), Some(42));
+    tokenizer.advance();
+    assert_eq!(tokenizer.symbol(), Some(';'));
+
+    assert!(!tokenizer.has_more_tokens());
+    tokenizer.advance();
+    assert_eq!(tokenizer.symbol(), Some(';'));
+}
+
+#[test]
+fn tokens_have_the_span_of_their_text() {
+    let tokenizer = Tokenizer::new("class Main {\n  field int count;\n\tdo f(\"hi\", 12);\n}").unwrap();
+    let spans: Vec<(&str, Span)> = tokenizer.tokens().iter().map(|token| (token.text.as_str(), token.span)).collect();
+    assert_eq!(spans[..5], [
+        ("class", Span::new(1, 1, 5)),
+        ("Main", Span::new(1, 7, 4)),
+        ("{", Span::new(1, 12, 1)),
+        ("field", Span::new(2, 3, 5)),
+        ("int", Span::new(2, 9, 3)),
+    ]);
+    // a string constant spans its quotes, and a tab is a single column
+    assert_eq!(spans[8..12], [
+        ("f", Span::new(3, 5, 1)),
+        ("(", Span::new(3, 6, 1)),
+        ("hi", Span::new(3, 7, 4)),
+        (",", Span::new(3, 11, 1)),
+    ]);
+    assert_eq!(spans.last(), Some(&("}", Span::new(4, 1, 1))));
+}
+
+#[test]
+fn comments_are_skipped_wherever_they_are() {
+    assert_eq!(tokens("/** a class\n * over lines */ class /* inline */ A // the name\n{ /* a\n\nb */ }"), [
+        (TokenType::Keyword, "class".to_string()),
+        (TokenType::Identifier, "A".to_string()),
+        (TokenType::Symbol, "{".to_string()),
+        (TokenType::Symbol, "}".to_string()),
+    ]);
+    // the lines of a comment still count
+    let tokenizer = Tokenizer::new("/*\n\n*/ x").unwrap();
+    assert_eq!(tokenizer.tokens()[0].span, Span::new(3, 4, 1));
+    // inside a string constant, comment markers are text
+    assert_eq!(tokens("\"// and /* */\""), [(TokenType::StringConst, "// and /* */".to_string())]);
+}
+
+#[test]
+fn integer_constants_are_at_most_32767() {
+    assert_eq!(tokens("32767"), [(TokenType::IntConst, "32767".to_string())]);
+
+    let error = Tokenizer::new("let x = 32768;").err().unwrap();
+    assert_eq!(error.span, Span::new(1, 9, 5));
+    assert_eq!(error.message, "integer constant 32768 is larger than 32767");
+    assert!(Tokenizer::new("99999999999").is_err());
+}
+
+#[test]
+fn unterminated_strings_and_comments_are_errors() {
+    let error = Tokenizer::new("do f(\"open\n);").err().unwrap();
+    assert_eq!(error.span, Span::new(1, 6, 1));
+    assert!(error.message.contains("unterminated string constant"), "{}", error);
+
+    let error = Tokenizer::new("class A {}\n  /* never closed").err().unwrap();
+    assert_eq!(error.span, Span::new(2, 3, 2));
+    assert!(error.message.contains("unterminated comment"), "{}", error);
+
+    let error = Tokenizer::new("let x = 5 # 3;").err().unwrap();
+    assert_eq!((error.span.column, error.message.as_str()), (11, "unexpected character '#'"));
+}
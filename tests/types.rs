@@ -0,0 +1,81 @@
+//! Checks the optional type checker, which reports values used where their type doesn't fit.
+mod common;
+
+use jack2vm::error::{CompileError, Warning};
+use jack2vm::utility::Precedence;
+
+/// Compiles a class with the types checked, returning the warnings or the first error
+fn check_with(source: &str, precedence: Precedence) -> Result<Vec<Warning>, CompileError> {
+    let compilation_engine = common::compile_with(source, |compilation_engine| {
+        compilation_engine.set_precedence(precedence);
+        compilation_engine.set_strict_types(true);
+    })?;
+    Ok(compilation_engine.warnings().to_vec())
+}
+
+/// Checks a function of a class Main, whose locals are i (int), c (char), b (boolean), a (Array) and s (String).
+/// Every local is assigned before the statements and read after them, so only the statements can cause warnings.
+fn check(statements: &str) -> Result<Vec<Warning>, CompileError> {
+    check_with(&format!(
+        "class Main {{
+            function void main() {{
+                var int i; var char c; var boolean b; var Array a; var String s;
+                let i = 0; let c = 0; let b = false; let a = Array.new(1); let s = \"\";
+                {}
+                if (b) {{ do Output.printInt(i + c); do Output.printString(s); do a.dispose(); }}
+                return;
+            }}
+            function int twice(int n) {{ return n + n; }}
+        }}",
+        statements
+    ), Precedence::LeftToRight)
+}
+
+#[test]
+fn well_typed_code_has_no_warnings() {
+    let warnings = check("
+        let i = Main.twice(c + 1); let b = (i < 3) & ~b; let a = Array.new(i); let a[i] = s; let s = a[0];
+        let s = null; let c = s.charAt(i); if (b | (s = null)) { do Output.printString(\"x\"); } while (~(i = 0)) { let i = i - 1; }
+    ").unwrap();
+    assert_eq!(warnings, []);
+}
+
+#[test]
+fn mismatches_are_errors() {
+    let error = check("let i = s;").unwrap_err();
+    assert_eq!(error.message, "type mismatch in the assignment to 'i': expected int, found String");
+    assert_eq!(check("let s = 1;").unwrap_err().message, "type mismatch in the assignment to 's': expected String, found int");
+    assert_eq!(check("let i = null;").unwrap_err().message, "type mismatch in the assignment to 'i': expected int, found null");
+    assert_eq!(check("let i = Main.twice(s);").unwrap_err().message, "type mismatch in argument 1 of Main.twice: expected int, found String");
+    assert_eq!(check("let i = s[0];").unwrap_err().message, "'s' is of type String, only an Array can be indexed");
+    assert_eq!(check("if (s) { }").unwrap_err().message, "the condition is of type String, not boolean");
+    assert_eq!(check("let i = s + 1;").unwrap_err().message, "'+' can't be applied to a value of type String");
+    assert_eq!(check("let i = Output.println();").unwrap_err().message, "Output.println returns void, so it has no value to use");
+    assert_eq!(
+        check_with("class Main { method int f() { return this; } }", Precedence::LeftToRight).unwrap_err().message,
+        "type mismatch in the return value of Main.f: expected int, found Main"
+    );
+}
+
+#[test]
+fn doubtful_uses_are_warnings() {
+    let warnings = check("while (i) { let i = i - 1; } let b = 1; let a = 2048; let i = i + b;").unwrap();
+    let messages: Vec<_> = warnings.iter().map(|warning| warning.message.as_str()).collect();
+    assert_eq!(messages, [
+        "the condition is of type int, not boolean",
+        "type mismatch in the assignment to 'b': expected boolean, found int",
+        "type mismatch in the assignment to 'a': expected Array, found int",
+        "'+' is applied to a value of type boolean",
+    ]);
+}
+
+#[test]
+fn operand_types_follow_the_precedence() {
+    let source = "class Main { function boolean f(int i, boolean b) { return i < 1 & b; } }";
+    assert_eq!(check_with(source, Precedence::LeftToRight).unwrap(), []);
+    // from left to right, ((i < 1) & j) < 2 applies '&' to a boolean and an int
+    let source = "class Main { function boolean f(int i, int j) { return i < 1 & j < 2; } }";
+    assert!(check_with(source, Precedence::LeftToRight).unwrap().iter().any(|warning| warning.message == "'&' is applied to a boolean and a number"));
+    let warnings = check_with(source, Precedence::Standard).unwrap();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
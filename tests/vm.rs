@@ -0,0 +1,355 @@
+//! Compiles small jack programs and runs them in the vm interpreter.
+mod common;
+
+use jack2vm::jack_os::{JackOs, Native};
+use jack2vm::key_script::{self, KeyEvent};
+use jack2vm::parser::parse;
+use jack2vm::signatures::OS_API;
+use jack2vm::vm::{Stop, Vm, RAM_SIZE};
+
+/// Compiles jack classes in memory, and loads them into the interpreter
+fn load(classes: &[&str]) -> Vm {
+    let files: Vec<(String, String)> = classes.iter().map(|source| {
+        let class = parse(source).expect("the source should parse");
+        let mut compilation_engine = common::engine();
+        compilation_engine.compile(&class).expect("the class should compile");
+        let code = String::from_utf8(compilation_engine.into_vm_writer().into_inner()).expect("vm code is text");
+        (class.name.name, code)
+    }).collect();
+    Vm::new(&files).expect("the vm code should load")
+}
+
+#[test]
+fn recursive_function_returns_its_value() {
+    let mut vm = load(&["class Main {
+        function int fib(int n) {
+            if (n < 2) { return n; }
+            return Main.fib(n - 1) + Main.fib(n - 2);
+        }
+        function int main() {
+            return Main.fib(10);
+        }
+    }"]);
+    vm.start("Main.main").unwrap();
+    assert_eq!(vm.run(1_000_000), Ok(Stop::Returned));
+    assert_eq!(vm.stack(), [55]);
+}
+
+#[test]
+fn statics_of_every_file_are_kept_apart() {
+    let mut vm = load(&[
+        "class A { static int x, y; function void set() { let x = 1; let y = 2; return; } }",
+        "class B { static int x; function void set() { let x = 3; return; } }",
+        "class Sys { function void init() { do A.set(); do B.set(); return; } }",
+    ]);
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(1000), Ok(Stop::Returned));
+    assert_eq!(&vm.ram()[16..20], [1, 2, 3, 0]);
+}
+
+#[test]
+fn loops_and_arithmetic_wrap_around_like_the_hardware() {
+    let mut vm = load(&["class Main {
+        function int main() {
+            var int i, sum;
+            let i = 0;
+            while (i < 10) {
+                let sum = sum + 4000;
+                let i = i + 1;
+            }
+            return sum;
+        }
+    }"]);
+    vm.start("Main.main").unwrap();
+    assert_eq!(vm.run(10_000), Ok(Stop::Returned));
+    assert_eq!(vm.stack(), [(40000u16) as i16]);
+}
+
+#[test]
+fn step_limit_stops_an_endless_loop() {
+    let mut vm = load(&["class Sys { function void init() { while (true) {} return; } }"]);
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(500), Ok(Stop::StepLimit));
+    assert_eq!(vm.steps(), 500);
+    assert_eq!(vm.current_function(), Some("Sys.init"));
+}
+
+#[test]
+fn runtime_errors_name_the_function() {
+    let mut vm = load(&["class Main { function void deep() { do Main.deep(); return; } }"]);
+    vm.start("Main.deep").unwrap();
+    let error = vm.run(1_000_000).unwrap_err();
+    assert!(error.contains("stack overflow"), "{}", error);
+    assert!(error.contains("Main.deep"), "{}", error);
+
+    // The compiler rejects calls to unknown OS functions, so the vm code is written by hand
+    let mut vm = Vm::new(&[("Main".to_string(), "function Main.main 0\ncall Output.printLine 0\nreturn".to_string())]).unwrap();
+    vm.start("Main.main").unwrap();
+    assert!(vm.run(100).unwrap_err().contains("the function Output.printLine is not defined"));
+}
+
+#[test]
+fn undefined_labels_are_found_when_loading() {
+    let error = Vm::new(&[("Main".to_string(), "function Main.main 0\ngoto NOWHERE".to_string())]).err().unwrap();
+    assert!(error.contains("the label NOWHERE is not defined in Main.main"), "{}", error);
+}
+
+#[test]
+fn sys_init_runs_main_and_halts_when_the_program_has_no_sys_class() {
+    let mut vm = load(&["class Main { static int x; function void main() { let x = 7; return; } }"]);
+    assert!(vm.has_function("Sys.init"));
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(1000), Ok(Stop::Halted));
+    assert_eq!(vm.peek(16), 7);
+}
+
+#[test]
+fn native_os_arrays_strings_and_output() {
+    let mut vm = load(&["class Main {
+        function void main() {
+            var Array a;
+            var String s;
+            var int i, sum;
+            let a = Array.new(5);
+            while (i < 5) {
+                let a[i] = Math.multiply(i, i) - 3;
+                let sum = sum + a[i];
+                let i = i + 1;
+            }
+            let s = \"sum = \";
+            do Output.printString(s);
+            do Output.printInt(sum / 3);
+            do Output.println();
+            let s = String.new(6);
+            do s.setInt(-1234);
+            do s.appendChar(Math.sqrt(2401) + 9);
+            do Output.printString(s);
+            do Output.printInt(s.length() + s.intValue());
+            do s.dispose();
+            do a.dispose();
+            return;
+        }
+    }"]);
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(100_000), Ok(Stop::Halted));
+    assert_eq!(vm.output(), "sum = 5\n-1234:-1228");
+}
+
+#[test]
+fn native_memory_reuses_freed_blocks() {
+    let mut vm = load(&["class Main {
+        function int main() {
+            var int a, b, c;
+            let a = Memory.alloc(10);
+            let b = Memory.alloc(20);
+            do Memory.deAlloc(a);
+            let c = Memory.alloc(5);
+            do Memory.poke(c, 99);
+            return (a = 2048) & (b = 2058) & (c = a) & (Memory.peek(2048) = 99);
+        }
+    }"]);
+    vm.start("Main.main").unwrap();
+    assert_eq!(vm.run(10_000), Ok(Stop::Returned));
+    assert_eq!(vm.stack(), [-1]);
+}
+
+#[test]
+fn native_screen_draws_into_the_screen_memory() {
+    let mut vm = load(&["class Main {
+        function void main() {
+            do Screen.drawLine(0, 0, 31, 0);
+            do Screen.drawRectangle(16, 2, 17, 3);
+            do Screen.setColor(false);
+            do Screen.drawPixel(1, 0);
+            return;
+        }
+    }"]);
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(10_000), Ok(Stop::Halted));
+    assert_eq!(vm.peek(16384), -3);
+    assert_eq!(vm.peek(16385), -1);
+    assert_eq!(vm.peek(16384 + 2 * 32 + 1), 3);
+    assert_eq!(vm.peek(16384 + 3 * 32 + 1), 3);
+}
+
+#[test]
+fn native_keyboard_waits_for_a_key() {
+    let mut vm = load(&["class Main { function int main() { return Keyboard.readChar(); } }"]);
+    vm.start("Main.main").unwrap();
+    assert_eq!(vm.run(1000), Ok(Stop::StepLimit));
+
+    vm.poke(24576, 65);
+    assert_eq!(vm.run(1010), Ok(Stop::StepLimit));
+    vm.poke(24576, 0);
+    assert_eq!(vm.run(2000), Ok(Stop::Returned));
+    assert_eq!(vm.stack(), [65]);
+    assert_eq!(vm.output(), "A");
+}
+
+#[test]
+fn native_wait_lets_steps_pass() {
+    let mut vm = load(&["class Main { function int main() { do Sys.wait(300); return Keyboard.keyPressed(); } }"]);
+    vm.script_keyboard(key_script::parse("at 200\npress a").expect("the key script should parse"));
+    vm.start("Main.main").unwrap();
+    assert_eq!(vm.run(250), Ok(Stop::StepLimit));
+    assert_eq!(vm.run(1000), Ok(Stop::Returned));
+    assert!(vm.steps() > 300);
+    assert_eq!(vm.stack(), [97]);
+}
+
+#[test]
+fn native_os_errors() {
+    let mut vm = load(&["class Main { function int main() { return 1 / 0; } }"]);
+    vm.start("Main.main").unwrap();
+    let error = vm.run(100).unwrap_err();
+    assert!(error.contains("OS error 3: Math.divide: division by zero"), "{}", error);
+}
+
+#[test]
+fn output_transcript_and_text_screen_follow_the_cursor() {
+    let mut vm = load(&["class Main {
+        function void main() {
+            do Output.printString(\"Hello, world\");
+            do Output.moveCursor(0, 7);
+            do Output.printString(\"jack!\");
+            do Output.moveCursor(2, 60);
+            do Output.printString(\"wrapped\");
+            do Output.backSpace();
+            do Output.println();
+            do Output.printInt(42);
+            return;
+        }
+    }"]);
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(10_000), Ok(Stop::Halted));
+    assert_eq!(vm.output(), "Hello, world\njack!\nwrap\npe\n42");
+    assert_eq!(vm.text_screen(), "Hello, jack!\n\n                                                            wrap\npe\n42\n");
+}
+
+#[test]
+fn screen_snapshot_matches_the_golden_image() {
+    let mut vm = load(&["class Main {
+        function void main() {
+            do Screen.drawRectangle(100, 50, 411, 205);
+            do Screen.setColor(false);
+            do Screen.drawCircle(256, 128, 60);
+            do Screen.setColor(true);
+            do Screen.drawLine(0, 0, 511, 255);
+            return;
+        }
+    }"]);
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(10_000), Ok(Stop::Halted));
+    let image = vm.screen_pbm();
+    assert!(image.starts_with(b"P4\n512 256\n"));
+    assert_eq!(image, include_bytes!("golden/screen.pbm"));
+}
+
+#[test]
+fn key_script_types_a_line_and_holds_keys() {
+    let mut vm = load(&["class Main {
+        function void main() {
+            var String name;
+            var int moves;
+            let name = Keyboard.readLine(\"name? \");
+            do Output.printString(name);
+            while (~(Keyboard.keyPressed() = 140)) {
+                if (Keyboard.keyPressed() = 132) {
+                    let moves = moves + 1;
+                }
+            }
+            do Output.println();
+            do Output.printInt(moves);
+            return;
+        }
+    }"]);
+    vm.script_keyboard(key_script::parse("
+        // answer the question, fixing a typo
+        wait 100
+        pace 10
+        type \"Jakc\\b\\bck\\n\"
+        wait 100
+        press RIGHT
+        wait 300
+        release
+        at 5000
+        press ESC
+    ").expect("the key script should parse"));
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(10_000), Ok(Stop::Halted));
+    assert!(vm.steps() > 5000);
+    let moves = vm.output().lines().last().unwrap().parse::<i32>().unwrap();
+    assert!((5..=50).contains(&moves), "{}", moves);
+    assert_eq!(vm.text_screen(), format!("name? Jack\nJack\n{}\n", moves));
+}
+
+#[test]
+fn backspace_on_an_empty_line_keeps_the_message() {
+    let mut vm = load(&["class Main {
+        function void main() {
+            do Output.printString(Keyboard.readLine(\"name? \"));
+            return;
+        }
+    }"]);
+    vm.script_keyboard(key_script::parse("pace 10\ntype \"\\b\\bJo\\b\\b\\bAl\\n\"").expect("the key script should parse"));
+    vm.start("Sys.init").unwrap();
+    assert_eq!(vm.run(10_000), Ok(Stop::Halted));
+    assert_eq!(vm.text_screen(), "name? Al\nAl\n");
+}
+
+#[test]
+fn key_script_errors_point_at_the_line() {
+    let events = key_script::parse("wait 5\npress a\nat 20\nrelease\ntype \"x\"").unwrap();
+    assert_eq!(events, vec![
+        KeyEvent { step: 5, key: 97 },
+        KeyEvent { step: 20, key: 0 },
+        KeyEvent { step: 20, key: 120 },
+        KeyEvent { step: 1020, key: 0 },
+    ]);
+
+    let error = key_script::parse("wait 10\nat 5").unwrap_err();
+    assert_eq!(error.span.line, 2);
+    assert!(error.message.contains("step 5 is before step 10"), "{}", error);
+    assert!(key_script::parse("press SHIFT").unwrap_err().message.contains("expected a key"));
+    assert!(key_script::parse("type hello").unwrap_err().message.contains("double quotes"));
+    assert!(key_script::parse("jump 3").unwrap_err().message.contains("unknown key script command"));
+
+    let error = key_script::parse("wait 18446744073709551615\nwait 5").unwrap_err();
+    assert_eq!(error.span.line, 2);
+    assert!(error.message.contains("past the last step"), "{}", error);
+    let error = key_script::parse("at 18446744073709551615\npace 1\ntype \"x\"").unwrap_err();
+    assert_eq!(error.span.line, 3);
+}
+
+#[test]
+fn key_script_comments_end_only_outside_quoted_text() {
+    let keys = |source: &str| -> Vec<i16> {
+        key_script::parse(source).unwrap().iter().map(|event| event.key).filter(|key| *key != 0).collect()
+    };
+    assert_eq!(keys("type \"a//b\" // types a//b"), "a//b".chars().map(|ch| ch as i16).collect::<Vec<_>>());
+    assert_eq!(keys("type \"http://x\""), "http://x".chars().map(|ch| ch as i16).collect::<Vec<_>>());
+    assert_eq!(keys("type \"\\\"//\" // a quote and two slashes"), "\"//".chars().map(|ch| ch as i16).collect::<Vec<_>>());
+    assert_eq!(keys("press a // press q"), [97]);
+}
+
+#[test]
+fn every_function_of_the_os_api_has_a_native_implementation() {
+    let mut ram = vec![0; RAM_SIZE];
+    for (kind, _, name, parameters) in OS_API.iter() {
+        // Sys.init is written in vm code by the interpreter, as it calls Main.main
+        assert_eq!(JackOs::has_function(name), *name != "Sys.init", "{}", name);
+        if *name == "Sys.init" {
+            continue;
+        }
+        let argument_count = parameters.len() + usize::from(*kind == "method");
+        let mut jack_os = JackOs::new();
+        let string = jack_os.call("String.new", &[4], &mut ram).unwrap();
+        let Native::Return(string) = string else { panic!("String.new should return at once") };
+        let arguments = vec![string; argument_count];
+        if let Err(message) = jack_os.call(name, &arguments, &mut ram) {
+            assert!(!message.contains("not defined") && !message.contains("argument"), "{}: {}", name, message);
+        }
+        let error = jack_os.call(name, &vec![1; argument_count + 1], &mut ram).unwrap_err();
+        assert_eq!(error, format!("{} takes {} argument(s), but was called with {}", name, argument_count, argument_count + 1));
+    }
+}
@@ -0,0 +1,125 @@
+//! Parses vm code and checks the shape of the hack assembly it is translated into.
+use jack2vm::assembler::assemble;
+use jack2vm::cpu::Cpu;
+use jack2vm::vm2asm::{check_program, translate_program};
+use jack2vm::vm_command::{parse, ArithmeticCommand, Segment, VmCommand};
+
+/// Parses vm code that is known to be valid, without the lines of the commands
+fn commands(code: &str) -> Vec<VmCommand> {
+    parse(code).expect("the vm code should parse").into_iter().map(|(command, _)| command).collect()
+}
+
+#[test]
+fn parse_all_kinds_of_commands() {
+    assert_eq!(commands("
+        // a comment
+        function Main.main 2   // with a trailing comment
+        push constant 7
+        pop temp 3
+        add
+        label LOOP
+        if-goto LOOP
+        goto END
+        call Math.multiply 2
+        return
+    "), vec![
+        VmCommand::Function { name: "Main.main".to_string(), locals: 2 },
+        VmCommand::Push { segment: Segment::Constant, index: 7 },
+        VmCommand::Pop { segment: Segment::Temp, index: 3 },
+        VmCommand::Arithmetic(ArithmeticCommand::Add),
+        VmCommand::Label("LOOP".to_string()),
+        VmCommand::IfGoto("LOOP".to_string()),
+        VmCommand::Goto("END".to_string()),
+        VmCommand::Call { name: "Math.multiply".to_string(), arguments: 2 },
+        VmCommand::Return,
+    ]);
+}
+
+#[test]
+fn parse_errors_point_at_the_mistake() {
+    let error = parse("push constant 1\n  pop constant 0").unwrap_err();
+    assert_eq!((error.span.line, error.span.column), (2, 7));
+
+    let error = parse("push temp 8").unwrap_err();
+    assert_eq!((error.span.line, error.span.column), (1, 11));
+
+    assert!(parse("jump somewhere").unwrap_err().message.contains("unknown vm command"));
+    assert!(parse("push local").unwrap_err().message.contains("takes 2 argument(s)"));
+    assert!(parse("push heap 0").unwrap_err().message.contains("unknown memory segment"));
+}
+
+#[test]
+fn bootstrap_only_when_sys_init_is_defined() {
+    let main = ("Main".to_string(), commands("function Main.main 0\npush constant 0\nreturn"));
+    let sys = ("Sys".to_string(), commands("function Sys.init 0\ncall Main.main 0\nlabel HALT\ngoto HALT"));
+
+    let without_sys = translate_program(std::slice::from_ref(&main));
+    assert!(without_sys.starts_with("// function Main.main 0\n(Main.main)\n"));
+
+    let with_sys = translate_program(&[main, sys]);
+    assert!(with_sys.starts_with("// bootstrap\n@256\nD=A\n@SP\nM=D\n// call Sys.init 0\n"));
+}
+
+#[test]
+fn programs_must_define_sys_init_and_every_function_they_call() {
+    let main = ("Main".to_string(), parse("function Main.main 0\ncall Output.printInt 1\n  call Output.printInt 1\ncall Main.main 0\nreturn").unwrap());
+    let sys = ("Sys".to_string(), parse("function Sys.init 0\ncall Main.main 0\n  call Memory.init 0\nlabel HALT\ngoto HALT").unwrap());
+
+    let errors = check_program(std::slice::from_ref(&main)).unwrap_err();
+    let errors: Vec<_> = errors.iter().map(|(file, error)| (*file, error.span.line, error.message.as_str())).collect();
+    assert_eq!(errors, [
+        (0, 1, "the program doesn't define Sys.init, which the bootstrap code calls to start it"),
+        (0, 2, "the function Output.printInt is called, but no file of the program defines it"),
+    ]);
+
+    let errors = check_program(&[main.clone(), sys.clone()]).unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!((errors[1].0, errors[1].1.span.line, errors[1].1.span.column), (1, 3, 3));
+    assert!(errors[1].1.message.contains("Memory.init"));
+
+    let os = ("Os".to_string(), parse("function Output.printInt 0\nreturn\nfunction Memory.init 0\nreturn").unwrap());
+    assert!(check_program(&[main, sys, os]).is_ok());
+}
+
+#[test]
+fn statics_are_named_after_their_file_and_labels_after_their_function() {
+    let asm = translate_program(&[("Counter".to_string(), commands("
+        function Counter.next 0
+        label LOOP
+        push static 2
+        pop static 0
+        goto LOOP
+    "))]);
+    assert!(asm.contains("@Counter.2\nD=M\n"));
+    assert!(asm.contains("@Counter.0\nM=D\n"));
+    assert!(asm.contains("(Counter.next$LOOP)\n"));
+    assert!(asm.contains("@Counter.next$LOOP\n0;JMP\n"));
+}
+
+#[test]
+fn comparisons_of_extreme_values_do_not_overflow() {
+    // x and y of every comparison, as vm code: 32767, -2, -32768 and 1
+    let (max, minus_two, min, one) = (
+        "push constant 32767",
+        "push constant 2\nneg",
+        "push constant 32767\nneg\npush constant 1\nsub",
+        "push constant 1",
+    );
+    let comparisons = [
+        (max, minus_two, "gt", -1), (max, minus_two, "lt", 0), (minus_two, max, "gt", 0), (minus_two, max, "lt", -1),
+        (min, one, "gt", 0), (min, one, "lt", -1), (one, min, "gt", -1), (max, min, "gt", -1), (min, max, "lt", -1),
+        (min, min, "lt", 0), (minus_two, min, "gt", -1), (max, max, "gt", 0), (max, min, "eq", 0), (min, min, "eq", -1),
+    ];
+    let mut code = "function Sys.init 0\n".to_string();
+    for (index, (x, y, command, _)) in comparisons.iter().enumerate() {
+        code.push_str(&format!("{}\n{}\n{}\npop static {}\n", x, y, command, index));
+    }
+    code.push_str("label HALT\ngoto HALT\n");
+
+    let asm = translate_program(&[("Sys".to_string(), commands(&code))]);
+    let mut cpu = Cpu::new(assemble(&asm).expect("the translated code should assemble"));
+    cpu.run(10_000).expect("the program should run");
+    for (index, (x, y, command, expected)) in comparisons.iter().enumerate() {
+        assert_eq!(cpu.peek(16 + index), *expected, "{} {} {}", x, y, command);
+    }
+}
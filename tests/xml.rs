@@ -0,0 +1,58 @@
+//! Writes the xml files of nand2tetris project 10 and compares them with the reference outputs byte for byte.
+use jack2vm::parse_tree_writer::ParseTreeWriter;
+use jack2vm::parser::parse;
+use jack2vm::tokenizer::Tokenizer;
+use jack2vm::xmlwriter::{escape, XmlWriter};
+
+/// Parses a class and writes its parse tree as xml, in memory
+fn parse_tree(source: &str) -> String {
+    let class = parse(source).expect("the source should parse");
+    let mut parse_tree_writer = ParseTreeWriter::new(XmlWriter::from_writer(Vec::new()));
+    parse_tree_writer.write_class(&class).expect("writing to memory can't fail");
+    String::from_utf8(parse_tree_writer.into_xml_writer().into_inner()).expect("xml is text")
+}
+
+/// Tokenizes a class and writes its tokens as xml, in memory
+fn tokens(source: &str) -> String {
+    let tokenizer = Tokenizer::new(source).expect("the source should tokenize");
+    let mut xml_writer = XmlWriter::from_writer(Vec::new());
+    tokenizer.write_xml(&mut xml_writer).expect("writing to memory can't fail");
+    String::from_utf8(xml_writer.into_inner()).expect("xml is text")
+}
+
+#[test]
+fn tokens_of_array_test_match_the_reference() {
+    let xml = tokens(include_str!("golden/ArrayTest/Main.jack"));
+    assert_eq!(xml, include_str!("golden/ArrayTest/MainT.xml"));
+    // string constants keep their spaces, without the quotes
+    assert!(xml.contains("<stringConstant> HOW MANY NUMBERS?  </stringConstant>\n"));
+    assert!(xml.ends_with("<symbol> } </symbol>\n</tokens>\n"));
+}
+
+#[test]
+fn tokens_escape_symbols() {
+    let xml = tokens(include_str!("golden/Escapes/Escapes.jack"));
+    assert_eq!(xml, include_str!("golden/Escapes/EscapesT.xml"));
+    for symbol in ["&lt;", "&gt;", "&amp;"] {
+        assert!(xml.contains(&format!("<symbol> {} </symbol>\n", symbol)), "{}", symbol);
+    }
+}
+
+#[test]
+fn parse_tree_of_array_test_matches_the_reference() {
+    assert_eq!(parse_tree(include_str!("golden/ArrayTest/Main.jack")), include_str!("golden/ArrayTest/Main.xml"));
+}
+
+#[test]
+fn parse_tree_escapes_symbols_and_lays_out_empty_lists() {
+    let xml = parse_tree(include_str!("golden/Escapes/Escapes.jack"));
+    assert_eq!(xml, include_str!("golden/Escapes/Escapes.xml"));
+    assert!(xml.contains("<symbol> &lt; </symbol>\n") && xml.contains("<symbol> &gt; </symbol>\n") && xml.contains("<symbol> &amp; </symbol>\n"));
+    assert!(xml.contains("    <parameterList>\n    </parameterList>\n"));
+    assert!(xml.contains("          <expressionList>\n          </expressionList>\n"));
+}
+
+#[test]
+fn every_special_character_is_escaped() {
+    assert_eq!(escape("a < b > c & \"d\""), "a &lt; b &gt; c &amp; &quot;d&quot;");
+}